@@ -1,15 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 
 use anyhow::{anyhow, Context, Result};
 use blake3::Hasher;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
-use crate::util::{log_event, normalize_path, run_cmd_allow_fail};
+use crate::util::progress::ProgressGroup;
+use crate::util::{normalize_path, run_cmd_allow_fail};
 
 const DEFAULT_MAX_BYTES: u64 = 1_000_000;
+const IMPORT_PREFIXES: [&str; 10] = [
+    "use ",
+    "pub use ",
+    "mod ",
+    "pub mod ",
+    "import ",
+    "from ",
+    "export ",
+    "#include",
+    "require",
+    "extern crate ",
+];
+const ENTRY_STEMS: [&str; 5] = ["mod", "lib", "main", "index", "__init__"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -30,14 +46,23 @@ pub struct IndexResult {
     pub index_hash: String,
 }
 
-pub fn build_index(repo_root: &Path, include_untracked: bool) -> Result<IndexResult> {
+pub fn build_index(
+    repo_root: &Path,
+    include_untracked: bool,
+    scope: &[String],
+    focus: Option<&BTreeSet<String>>,
+) -> Result<IndexResult> {
+    let scope = build_scope(scope)?;
     let mut files = list_git_files(repo_root, include_untracked)?;
+    files.retain(|rel| scope.as_ref().is_none_or(|set| set.is_match(rel)));
+    files.retain(|rel| focus.is_none_or(|focus| focus.contains(rel)));
     files.sort();
 
     let mut entries = Vec::new();
-    let mut file_hashes = HashMap::new();
 
+    let progress = ProgressGroup::new().bar(files.len() as u64, "index");
     for rel in files {
+        progress.inc();
         let full = repo_root.join(&rel);
         if should_exclude(&rel) {
             continue;
@@ -55,25 +80,143 @@ pub fn build_index(repo_root: &Path, include_untracked: bool) -> Result<IndexRes
         let path = match normalize_path(Path::new(&rel)) {
             Ok(path) => path,
             Err(err) => {
-                log_event("warn", &format!("skip invalid path {rel}: {err}"));
+                warn!("skip invalid path {rel}: {err}");
                 continue;
             }
         };
-        file_hashes.insert(path.clone(), hash.clone());
         entries.push(FileEntry { path, hash, size });
     }
 
-    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    progress.finish(&format!("{} files hashed", entries.len()));
+    Ok(index_result(FileIndex { files: entries }))
+}
+
+pub fn update_index(
+    repo_root: &Path,
+    index: &mut FileIndex,
+    changed: &[String],
+    include_untracked: bool,
+    scope: &[String],
+) -> Result<Vec<String>> {
+    let scope = build_scope(scope)?;
+    let mut updated = Vec::new();
+    for rel in changed {
+        if should_exclude(rel) || scope.as_ref().is_some_and(|set| !set.is_match(rel)) {
+            continue;
+        }
+        let position = index.files.iter().position(|entry| &entry.path == rel);
+        let full = repo_root.join(rel);
+        let indexable = full.is_file()
+            && std::fs::metadata(&full).is_ok_and(|m| m.len() <= DEFAULT_MAX_BYTES)
+            && !is_binary(&full)?
+            && (position.is_some() || is_listed(repo_root, rel, include_untracked)?);
+        match (position, indexable) {
+            (Some(pos), false) => {
+                index.files.remove(pos);
+            }
+            (Some(pos), true) => {
+                let hash = hash_file(&full)?;
+                if index.files[pos].hash == hash {
+                    continue;
+                }
+                index.files[pos].hash = hash;
+                index.files[pos].size = std::fs::metadata(&full).map(|m| m.len()).unwrap_or(0);
+            }
+            (None, true) => {
+                index.files.push(FileEntry {
+                    path: rel.clone(),
+                    hash: hash_file(&full)?,
+                    size: std::fs::metadata(&full).map(|m| m.len()).unwrap_or(0),
+                });
+            }
+            (None, false) => continue,
+        }
+        updated.push(rel.clone());
+    }
+    index.files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(updated)
+}
+
+pub fn with_dependents(
+    repo_root: &Path,
+    touched: &[String],
+    include_untracked: bool,
+) -> Result<BTreeSet<String>> {
+    let mut focus: BTreeSet<String> = touched.iter().cloned().collect();
+    let names: BTreeSet<String> = touched.iter().filter_map(|rel| module_name(rel)).collect();
+    if names.is_empty() {
+        return Ok(focus);
+    }
+    for rel in list_git_files(repo_root, include_untracked)? {
+        if focus.contains(&rel) || should_exclude(&rel) {
+            continue;
+        }
+        let full = repo_root.join(&rel);
+        if !std::fs::metadata(&full).is_ok_and(|m| m.is_file() && m.len() <= DEFAULT_MAX_BYTES) {
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(&full) else {
+            continue;
+        };
+        if contents.lines().any(|line| imports_any(line, &names)) {
+            focus.insert(rel);
+        }
+    }
+    Ok(focus)
+}
+
+fn module_name(rel: &str) -> Option<String> {
+    let path = Path::new(rel);
+    let stem = path.file_stem()?.to_str()?;
+    let name = if ENTRY_STEMS.contains(&stem) {
+        path.parent()?.file_name()?.to_str()?
+    } else {
+        stem
+    };
+    Some(name.to_string())
+}
+
+fn imports_any(line: &str, names: &BTreeSet<String>) -> bool {
+    let line = line.trim_start();
+    IMPORT_PREFIXES
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        && line
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .any(|word| names.contains(word))
+}
 
-    let index = FileIndex { files: entries };
+pub fn index_result(mut index: FileIndex) -> IndexResult {
+    index.files.sort_by(|a, b| a.path.cmp(&b.path));
+    let file_hashes = index
+        .files
+        .iter()
+        .map(|entry| (entry.path.clone(), entry.hash.clone()))
+        .collect();
     let index_hash = hash_index(&index);
     let repo_tree = build_repo_tree(&index);
-    Ok(IndexResult {
+    IndexResult {
         index,
         repo_tree,
         file_hashes,
         index_hash,
-    })
+    }
+}
+
+pub fn read_index(path: &Path) -> Result<FileIndex> {
+    let data = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parse {}", path.display()))
+}
+
+fn build_scope(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("invalid scope glob {pattern}"))?);
+    }
+    Ok(Some(builder.build().with_context(|| "build scope globs")?))
 }
 
 fn list_git_files(repo_root: &Path, include_untracked: bool) -> Result<Vec<String>> {
@@ -115,7 +258,25 @@ fn split_nul(data: &[u8]) -> Vec<String> {
         .collect()
 }
 
-fn should_exclude(rel: &str) -> bool {
+fn is_listed(repo_root: &Path, rel: &str, include_untracked: bool) -> Result<bool> {
+    let mut tracked_cmd = std::process::Command::new("git");
+    tracked_cmd
+        .current_dir(repo_root)
+        .args(["ls-files", "--error-unmatch", "--", rel]);
+    if run_cmd_allow_fail(tracked_cmd)?.status.success() {
+        return Ok(true);
+    }
+    if !include_untracked {
+        return Ok(false);
+    }
+    let mut ignored_cmd = std::process::Command::new("git");
+    ignored_cmd
+        .current_dir(repo_root)
+        .args(["check-ignore", "-q", "--", rel]);
+    Ok(!run_cmd_allow_fail(ignored_cmd)?.status.success())
+}
+
+pub fn should_exclude(rel: &str) -> bool {
     rel.starts_with(".git/")
         || rel.starts_with("target/")
         || rel.starts_with("node_modules/")
@@ -182,6 +343,18 @@ pub fn write_repo_tree(path: &Path, tree: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn match_files(index: &FileIndex, patterns: &[String]) -> Result<Vec<FileEntry>> {
+    let Some(set) = build_scope(patterns)? else {
+        return Ok(Vec::new());
+    };
+    Ok(index
+        .files
+        .iter()
+        .filter(|entry| set.is_match(&entry.path))
+        .cloned()
+        .collect())
+}
+
 pub fn shard_files(index: &FileIndex, shards: usize) -> Vec<Vec<FileEntry>> {
     if shards == 0 {
         return vec![];
@@ -0,0 +1,70 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::analysis::index::FileEntry;
+
+const MIN_KEYWORD_LEN: usize = 3;
+const PATH_WEIGHT: usize = 10;
+const MAX_LINE_HITS: usize = 20;
+const STOPWORDS: [&str; 24] = [
+    "the", "and", "for", "with", "that", "this", "from", "into", "how", "does", "what", "where",
+    "when", "why", "which", "who", "are", "was", "work", "works", "there", "can", "should",
+    "about",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedFile {
+    pub path: String,
+    pub score: usize,
+}
+
+pub fn keywords(question: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    question
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() >= MIN_KEYWORD_LEN && !STOPWORDS.contains(&word.as_str())
+        })
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+pub fn rank(
+    repo_root: &Path,
+    files: &[FileEntry],
+    keywords: &[String],
+    max_files: usize,
+) -> Vec<RankedFile> {
+    let mut ranked: Vec<RankedFile> = files
+        .iter()
+        .filter_map(|entry| {
+            let path = entry.path.to_lowercase();
+            let mut score = keywords
+                .iter()
+                .filter(|word| path.contains(word.as_str()))
+                .count()
+                * PATH_WEIGHT;
+            if let Ok(contents) = fs::read_to_string(repo_root.join(&entry.path)) {
+                let hits = contents
+                    .lines()
+                    .filter(|line| {
+                        let line = line.to_lowercase();
+                        keywords.iter().any(|word| line.contains(word.as_str()))
+                    })
+                    .count();
+                score += hits.min(MAX_LINE_HITS);
+            }
+            (score > 0).then(|| RankedFile {
+                path: entry.path.clone(),
+                score,
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    ranked.truncate(max_files);
+    ranked
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info_span, warn};
+
+use crate::codex::exec::{self, ExecSpec};
+use crate::core::error::ErrorKind;
+use crate::util::progress::ProgressGroup;
+use crate::util::read_to_string;
+
+const COMPLIANCE_RANK: [&str; 4] = ["missing", "partial", "unknown", "met"];
+
+pub struct DebateRun {
+    pub name: String,
+    pub output: String,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub id: String,
+    pub debater: String,
+    pub finding: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub finding: String,
+    pub agree: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ballot {
+    #[serde(default)]
+    votes: Vec<Vote>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Tally {
+    pub id: String,
+    pub debater: String,
+    pub finding: Value,
+    pub agree: usize,
+    pub disagree: usize,
+    pub reasons: Vec<String>,
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Judgment {
+    pub choice: String,
+    #[serde(default)]
+    pub rationale: String,
+}
+
+pub fn run_round(specs: Vec<(String, ExecSpec)>) -> Result<Vec<DebateRun>> {
+    let progress = ProgressGroup::new();
+    let mut handles = Vec::new();
+    for (name, spec) in specs {
+        let span = info_span!("debater", name = %name);
+        let debater_progress = progress.spinner(&name);
+        handles.push(std::thread::spawn(move || -> Result<DebateRun> {
+            let _span = span.entered();
+            let result = exec::run(&spec);
+            debater_progress.finish(match &result {
+                Ok(r) if r.status_ok => "done",
+                _ => "failed",
+            });
+            let result = result?;
+            if !result.status_ok {
+                return Err(ErrorKind::AgentFailed.err(format!("{name} agent failed")));
+            }
+            Ok(DebateRun {
+                output: read_to_string(&spec.output_path)?,
+                name,
+                duration_secs: result.duration_secs,
+            })
+        }));
+    }
+    handles
+        .into_iter()
+        .map(|handle| {
+            handle
+                .join()
+                .map_err(|_| anyhow!("debater thread failed"))?
+        })
+        .collect()
+}
+
+pub fn candidates(reviews: &[DebateRun]) -> Result<Vec<Candidate>> {
+    let mut candidates = Vec::new();
+    for review in reviews {
+        let output: Value = serde_json::from_str(review.output.trim())
+            .with_context(|| format!("parse review output of {}", review.name))?;
+        let findings = output
+            .get("findings")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for finding in findings {
+            candidates.push(Candidate {
+                id: format!("F{}", candidates.len() + 1),
+                debater: review.name.clone(),
+                finding,
+            });
+        }
+    }
+    Ok(candidates)
+}
+
+pub fn tally(candidates: &[Candidate], ballots: &[DebateRun]) -> Result<Vec<Tally>> {
+    let mut votes: BTreeMap<&str, Vec<(&str, Vote)>> = BTreeMap::new();
+    for run in ballots {
+        let ballot: Ballot = serde_json::from_str(run.output.trim())
+            .with_context(|| format!("parse votes of {}", run.name))?;
+        for vote in ballot.votes {
+            match candidates.iter().find(|c| c.id == vote.finding) {
+                Some(candidate) => votes
+                    .entry(candidate.id.as_str())
+                    .or_default()
+                    .push((run.name.as_str(), vote)),
+                None => warn!("{} voted on unknown finding {}", run.name, vote.finding),
+            }
+        }
+    }
+    Ok(candidates
+        .iter()
+        .map(|candidate| {
+            let cast = votes.remove(candidate.id.as_str()).unwrap_or_default();
+            let agree = cast.iter().filter(|(_, vote)| vote.agree).count();
+            Tally {
+                id: candidate.id.clone(),
+                debater: candidate.debater.clone(),
+                finding: candidate.finding.clone(),
+                agree,
+                disagree: cast.len() - agree,
+                reasons: cast
+                    .iter()
+                    .filter(|(_, vote)| !vote.reason.trim().is_empty())
+                    .map(|(name, vote)| format!("{name}: {}", vote.reason.trim()))
+                    .collect(),
+                accepted: agree * 2 > ballots.len(),
+            }
+        })
+        .collect())
+}
+
+pub fn review_consensus(tallies: &[Tally], reviews: &[DebateRun]) -> Result<String> {
+    let mut findings: Vec<Value> = Vec::new();
+    for tally in tallies.iter().filter(|tally| tally.accepted) {
+        if !findings.contains(&tally.finding) {
+            findings.push(tally.finding.clone());
+        }
+    }
+    let mut consensus = serde_json::json!({ "findings": findings });
+    let compliance = merge_compliance(reviews)?;
+    if !compliance.is_empty() {
+        consensus["compliance"] = Value::Array(compliance);
+    }
+    Ok(serde_json::to_string_pretty(&consensus)?)
+}
+
+fn merge_compliance(reviews: &[DebateRun]) -> Result<Vec<Value>> {
+    let mut reported: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for review in reviews {
+        let output: Value = serde_json::from_str(review.output.trim())
+            .with_context(|| format!("parse review output of {}", review.name))?;
+        for entry in output
+            .get("compliance")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(requirement) = entry.get("requirement").and_then(Value::as_str) {
+                reported
+                    .entry(requirement.to_string())
+                    .or_default()
+                    .push(entry.clone());
+            }
+        }
+    }
+    Ok(reported
+        .into_values()
+        .filter_map(|entries| {
+            let status = |entry: &Value| {
+                entry
+                    .get("status")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown")
+                    .to_lowercase()
+            };
+            let count = |s: &str| entries.iter().filter(|e| status(e) == s).count();
+            let rank = |s: &str| COMPLIANCE_RANK.iter().position(|r| *r == s);
+            entries
+                .iter()
+                .max_by(|a, b| {
+                    let (a, b) = (status(a), status(b));
+                    count(&a)
+                        .cmp(&count(&b))
+                        .then_with(|| rank(&b).cmp(&rank(&a)))
+                })
+                .cloned()
+        })
+        .collect())
+}
+
+pub fn render_review_debate(tallies: &[Tally], debaters: usize) -> String {
+    let mut out = format!(
+        "# Review Debate\n\n- debaters: {debaters}\n- accepted: {}/{}\n\n| id | debater | severity | file | agree | disagree | result |\n|---|---|---|---|---|---|---|\n",
+        tallies.iter().filter(|tally| tally.accepted).count(),
+        tallies.len()
+    );
+    for tally in tallies {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            tally.id,
+            tally.debater,
+            field(&tally.finding, "severity"),
+            field(&tally.finding, "file"),
+            tally.agree,
+            tally.disagree,
+            if tally.accepted {
+                "accepted"
+            } else {
+                "dropped"
+            }
+        ));
+    }
+    for tally in tallies.iter().filter(|tally| !tally.reasons.is_empty()) {
+        out.push_str(&format!("\n## {}\n\n", tally.id));
+        for reason in &tally.reasons {
+            out.push_str(&format!("- {reason}\n"));
+        }
+    }
+    out
+}
+
+pub fn judgments(runs: &[DebateRun], agents: &[String]) -> Result<Vec<(String, Judgment)>> {
+    runs.iter()
+        .map(|run| {
+            let judgment: Judgment = serde_json::from_str(run.output.trim())
+                .with_context(|| format!("parse judgment of {}", run.name))?;
+            if !agents.contains(&judgment.choice) {
+                warn!("{} chose unknown agent {}", run.name, judgment.choice);
+            }
+            Ok((run.name.clone(), judgment))
+        })
+        .collect()
+}
+
+pub fn select_consensus(judgments: &[(String, Judgment)], agents: &[String]) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, judgment) in judgments {
+        if agents.contains(&judgment.choice) {
+            *counts.entry(judgment.choice.as_str()).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .find(|(_, count)| count * 2 > judgments.len())
+        .map(|(choice, _)| choice.to_string())
+}
+
+pub fn render_select_debate(
+    first: &[(String, Judgment)],
+    last: &[(String, Judgment)],
+    consensus: Option<&str>,
+) -> String {
+    let mut out = format!(
+        "\n## Consensus\n\n- choice: {}\n\n| debater | initial | final | rationale |\n|---|---|---|---|\n",
+        consensus.unwrap_or("none")
+    );
+    for (name, judgment) in last {
+        let initial = first
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, j)| j.choice.as_str())
+            .unwrap_or("-");
+        out.push_str(&format!(
+            "| {name} | {initial} | {} | {} |\n",
+            judgment.choice,
+            judgment.rationale.replace('|', "\\|").replace('\n', " ")
+        ));
+    }
+    out
+}
+
+fn field<'a>(finding: &'a Value, key: &str) -> &'a str {
+    finding.get(key).and_then(Value::as_str).unwrap_or("-")
+}
@@ -1,10 +1,48 @@
 use std::env;
+use std::fs;
+use std::io::ErrorKind as IoErrorKind;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use tracing::{debug, warn};
 
-use crate::util::{run_cmd_allow_fail, write_string};
+use crate::codex::transcript::{self, Transcript};
+use crate::codex::{openai, replay};
+use crate::core::config::{Config, ExecConfig, OpenAiConfig};
+use crate::tr;
+use crate::util::{
+    crypto, ensure_dir, now_rfc3339, read_to_string, run_cmd_allow_fail, run_cmd_timeout,
+    write_string, write_string_atomic,
+};
+
+const LATEST_SUFFIX: &str = ".latest";
+
+#[derive(Clone, Debug)]
+pub enum Backend {
+    Codex,
+    OpenAi {
+        config: OpenAiConfig,
+        inputs: Vec<PathBuf>,
+    },
+}
+
+impl Backend {
+    pub fn for_stage(config: &Config, inputs: Vec<PathBuf>) -> Result<Self> {
+        match config.backend.as_str() {
+            "codex" => Ok(Self::Codex),
+            "openai" => Ok(Self::OpenAi {
+                config: config.openai.clone(),
+                inputs,
+            }),
+            other => Err(anyhow!(tr!(
+                "未対応の backend です: {other} (codex か openai)",
+                "unsupported backend: {other} (expected codex or openai)"
+            ))),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ExecSpec {
@@ -14,13 +52,46 @@ pub struct ExecSpec {
     pub json_output_path: Option<PathBuf>,
     pub sandbox: String,
     pub schema_path: Option<PathBuf>,
+    pub model: Option<String>,
+    pub env: ExecConfig,
+    pub backend: Backend,
 }
 
 pub struct ExecResult {
     pub status_ok: bool,
+    pub duration_secs: f64,
+    pub timed_out: bool,
 }
 
 pub fn run(spec: &ExecSpec) -> Result<ExecResult> {
+    if replay::active() {
+        let result = replay::run(spec)?;
+        if result.status_ok {
+            commit_latest(&spec.output_path)?;
+        }
+        return Ok(result);
+    }
+    let started_at = now_rfc3339();
+    let (result, transcript) = match &spec.backend {
+        Backend::Codex => run_codex(spec)?,
+        Backend::OpenAi { config, inputs } => openai::run(spec, config, inputs)?,
+    };
+    if spec.env.archive_prompts {
+        match transcript::archive(spec, &transcript, &result, &started_at) {
+            Ok(dir) => debug!("transcript saved to {}", dir.display()),
+            Err(err) => warn!(
+                "skip transcript for {}: {err:#}",
+                spec.output_path.display()
+            ),
+        }
+    }
+    if result.status_ok {
+        commit_latest(&spec.output_path)?;
+    }
+    Ok(result)
+}
+
+fn run_codex(spec: &ExecSpec) -> Result<(ExecResult, Transcript)> {
     let prompt_flag =
         env::var("CODEX_SDD_PROMPT_FLAG").unwrap_or_else(|_| "--prompt-file".to_string());
     let extra_args = env::var("CODEX_SDD_EXEC_ARGS").unwrap_or_default();
@@ -40,6 +111,10 @@ pub fn run(spec: &ExecSpec) -> Result<ExecResult> {
         cmd.arg("--output-schema").arg(schema);
     }
 
+    if let Some(model) = &spec.model {
+        cmd.arg("--model").arg(model);
+    }
+
     if spec.json_output_path.is_some() {
         cmd.arg("--json");
     }
@@ -50,22 +125,132 @@ pub fn run(spec: &ExecSpec) -> Result<ExecResult> {
         }
     }
 
-    let output = run_cmd_allow_fail(cmd).with_context(|| "codex exec")?;
+    spec.env.apply(&mut cmd);
+    debug!("codex exec: {:?}", cmd);
+    let started = Instant::now();
+    let (output, timed_out) = match spec.env.timeout_secs {
+        Some(secs) => run_cmd_timeout(cmd, Duration::from_secs(secs)),
+        None => run_cmd_allow_fail(cmd).map(|output| (output, false)),
+    }
+    .with_context(|| "codex exec")?;
+    debug!("codex exec exited with {}", output.status);
+    if timed_out {
+        let secs = spec.env.timeout_secs.unwrap_or_default();
+        warn!(
+            "codex exec for {} timed out after {secs}s",
+            spec.output_path.display()
+        );
+        eprintln!(
+            "{}",
+            tr!(
+                "codex exec を {secs} 秒で打ち切りました ([exec] timeout_secs)",
+                "codex exec stopped after {secs}s ([exec] timeout_secs)"
+            )
+        );
+    }
+    crypto::seal_file(&spec.output_path)?;
+    let jsonl = String::from_utf8_lossy(&output.stdout).to_string();
     if let Some(json_path) = &spec.json_output_path {
-        let jsonl = String::from_utf8_lossy(&output.stdout).to_string();
         if !jsonl.is_empty() {
             write_string(json_path, &jsonl)?;
         }
     }
 
-    Ok(ExecResult {
-        status_ok: output.status.success(),
-    })
+    let result = ExecResult {
+        status_ok: output.status.success() && !timed_out,
+        duration_secs: started.elapsed().as_secs_f64(),
+        timed_out,
+    };
+    let transcript = if spec.env.archive_prompts {
+        Transcript {
+            prompt: read_to_string(&spec.prompt_path)?,
+            raw: jsonl,
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            model: spec.model.clone(),
+        }
+    } else {
+        Transcript::default()
+    };
+    Ok((result, transcript))
 }
 
 pub fn output_paths(runs_dir: &Path, change_id: &str, name: &str) -> (PathBuf, PathBuf) {
     let change_dir = runs_dir.join(change_id);
-    let output_path = change_dir.join(format!("{name}.md"));
-    let json_path = change_dir.join(format!("{name}.jsonl"));
-    (output_path, json_path)
+    let dir = latest_run(&change_dir, name)
+        .map(|dir| change_dir.join(dir))
+        .unwrap_or(change_dir);
+    run_paths(&dir, name)
+}
+
+pub fn new_output_paths(
+    runs_dir: &Path,
+    change_id: &str,
+    name: &str,
+) -> Result<(PathBuf, PathBuf)> {
+    let change_dir = runs_dir.join(change_id);
+    ensure_dir(&change_dir)?;
+    let mut number = list_runs(&change_dir)
+        .iter()
+        .map(|(number, _, _)| number + 1)
+        .max()
+        .unwrap_or(1);
+    loop {
+        let dir_name = format!("{number:03}_{name}");
+        let dir = change_dir.join(&dir_name);
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(run_paths(&dir, name)),
+            Err(err) if err.kind() == IoErrorKind::AlreadyExists => number += 1,
+            Err(err) => return Err(err).with_context(|| format!("create {}", dir.display())),
+        }
+    }
+}
+
+pub fn commit_latest(output_path: &Path) -> Result<()> {
+    let Some(dir) = output_path.parent() else {
+        return Ok(());
+    };
+    let (Some(change_dir), Some(dir_name), Some(name)) = (
+        dir.parent(),
+        dir.file_name().and_then(|n| n.to_str()),
+        output_path.file_stem().and_then(|n| n.to_str()),
+    ) else {
+        return Ok(());
+    };
+    let is_run_dir = dir_name
+        .split_once('_')
+        .is_some_and(|(number, rest)| rest == name && number.parse::<u32>().is_ok());
+    if !is_run_dir {
+        return Ok(());
+    }
+    write_string_atomic(&change_dir.join(format!("{name}{LATEST_SUFFIX}")), dir_name)
+}
+
+pub fn list_runs(change_dir: &Path) -> Vec<(u32, String, PathBuf)> {
+    let mut runs: Vec<(u32, String, PathBuf)> = fs::read_dir(change_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let (number, name) = dir_name.split_once('_')?;
+            let number = number.parse().ok()?;
+            Some((number, name.to_string(), entry.path()))
+        })
+        .collect();
+    runs.sort();
+    runs
+}
+
+pub fn latest_run(change_dir: &Path, name: &str) -> Option<String> {
+    let pointer = read_to_string(&change_dir.join(format!("{name}{LATEST_SUFFIX}"))).ok()?;
+    let dir = pointer.trim();
+    change_dir.join(dir).is_dir().then(|| dir.to_string())
+}
+
+fn run_paths(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (
+        dir.join(format!("{name}.md")),
+        dir.join(format!("{name}.jsonl")),
+    )
 }
@@ -1 +1,6 @@
+pub mod debate;
 pub mod exec;
+pub mod openai;
+pub mod preflight;
+pub mod replay;
+pub mod transcript;
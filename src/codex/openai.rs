@@ -0,0 +1,249 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::codex::exec::{ExecResult, ExecSpec};
+use crate::codex::transcript::Transcript;
+use crate::core::config::OpenAiConfig;
+use crate::core::error::ErrorKind;
+use crate::tr;
+use crate::util::{crypto, curl_quote, read_to_string, write_string};
+
+const ERROR_PREVIEW_CHARS: usize = 500;
+
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+struct RequestFile(PathBuf);
+
+impl Drop for RequestFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+pub fn run(
+    spec: &ExecSpec,
+    config: &OpenAiConfig,
+    inputs: &[PathBuf],
+) -> Result<(ExecResult, Transcript)> {
+    let model = config
+        .model
+        .clone()
+        .or_else(|| spec.model.clone())
+        .ok_or_else(|| {
+            anyhow!(tr!(
+                "backend = \"openai\" には [openai] model の設定が必要です",
+                "backend = \"openai\" needs [openai] model"
+            ))
+        })?;
+    let mut content = read_to_string(&spec.prompt_path)?;
+    content.push_str(&attachments(&spec.cwd, inputs)?);
+    let mut body = json!({ "model": model });
+    if let Some(schema_path) = &spec.schema_path {
+        let schema: Value = serde_json::from_str(&read_to_string(schema_path)?)
+            .with_context(|| format!("parse {}", schema_path.display()))?;
+        if config.json_schema {
+            let name = schema_path
+                .file_stem()
+                .map_or("output".to_string(), |stem| {
+                    stem.to_string_lossy().to_string()
+                });
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": true },
+            });
+        } else {
+            content.push_str(&format!(
+                "\n\n{}\n\n```json\n{}\n```\n",
+                tr!(
+                    "次の JSON Schema に沿った JSON だけを返してください。",
+                    "Respond with JSON only, matching this JSON Schema."
+                ),
+                serde_json::to_string_pretty(&schema)?
+            ));
+        }
+    }
+    body["messages"] = json!([{ "role": "user", "content": &content }]);
+    if let Some(temperature) = config.temperature {
+        body["temperature"] = json!(temperature);
+    }
+
+    let started = Instant::now();
+    let timeout_secs = spec
+        .env
+        .timeout_secs
+        .map_or(config.timeout_secs, |secs| secs.min(config.timeout_secs));
+    let (status, response) = post(config, &body, timeout_secs)?;
+    let duration_secs = started.elapsed().as_secs_f64();
+    debug!("openai {model} responded with HTTP {status}");
+    if let Some(json_path) = &spec.json_output_path {
+        write_string(json_path, &format!("{}\n", response.trim()))?;
+    }
+    let parsed: Option<Value> = serde_json::from_str(&response).ok();
+    let transcript = Transcript {
+        prompt: content,
+        raw: response.clone(),
+        stderr: String::new(),
+        model: Some(model.clone()),
+    };
+    let message = parsed
+        .as_ref()
+        .and_then(|value| value.pointer("/choices/0/message/content"))
+        .and_then(Value::as_str);
+    let Some(message) = message.filter(|_| (200..300).contains(&status)) else {
+        let detail = parsed
+            .as_ref()
+            .and_then(|value| value.pointer("/error/message"))
+            .and_then(Value::as_str)
+            .map_or_else(
+                || response.chars().take(ERROR_PREVIEW_CHARS).collect(),
+                str::to_string,
+            );
+        warn!("openai {model} request failed with HTTP {status}: {detail}");
+        eprintln!(
+            "{}",
+            tr!(
+                "{} の応答がありません (HTTP {status}): {detail}",
+                "no answer from {} (HTTP {status}): {detail}",
+                config.base_url
+            )
+        );
+        return Ok((
+            ExecResult {
+                status_ok: false,
+                duration_secs,
+                timed_out: false,
+            },
+            transcript,
+        ));
+    };
+    if let Some(usage) = parsed.as_ref().and_then(|value| value.get("usage")) {
+        info!(
+            "openai {model}: {} prompt + {} completion tokens",
+            usage["prompt_tokens"], usage["completion_tokens"]
+        );
+    }
+    write_string(&spec.output_path, strip_fence(message))?;
+    Ok((
+        ExecResult {
+            status_ok: true,
+            duration_secs,
+            timed_out: false,
+        },
+        transcript,
+    ))
+}
+
+fn attachments(root: &Path, inputs: &[PathBuf]) -> Result<String> {
+    let files: Vec<&PathBuf> = inputs.iter().filter(|path| path.is_file()).collect();
+    if files.is_empty() {
+        return Ok(String::new());
+    }
+    let mut out = format!(
+        "\n\n# {}\n\n{}\n",
+        tr!("添付ファイル", "Attached files"),
+        tr!(
+            "上で参照しているファイルの内容です。ファイルを開くことはできないので、これを読んでください。",
+            "These are the files referenced above. You cannot open files, so read them here."
+        )
+    );
+    for path in files {
+        let data = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        let data = crypto::open(path, data)?;
+        let name = path.strip_prefix(root).unwrap_or(path);
+        out.push_str(&format!(
+            "\n## {}\n\n````\n{}\n````\n",
+            name.display(),
+            String::from_utf8_lossy(&data).trim_end()
+        ));
+    }
+    Ok(out)
+}
+
+fn post(config: &OpenAiConfig, body: &Value, timeout_secs: u64) -> Result<(u16, String)> {
+    let url = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+    let request = request_file(&serde_json::to_vec(body)?)?;
+    let mut curl = String::from(
+        "silent\nshow-error\nwrite-out = \"\\n%{http_code}\"\nheader = \"Content-Type: application/json\"\n",
+    );
+    let key = config
+        .api_key
+        .clone()
+        .or_else(|| env::var(&config.api_key_env).ok())
+        .filter(|key| !key.trim().is_empty());
+    if let Some(key) = key {
+        curl.push_str(&format!(
+            "header = \"Authorization: Bearer {}\"\n",
+            curl_quote(key.trim())
+        ));
+    }
+    curl.push_str(&format!("max-time = {timeout_secs}\n"));
+    curl.push_str(&format!(
+        "data-binary = \"@{}\"\n",
+        curl_quote(&request.0.display().to_string())
+    ));
+    curl.push_str(&format!("url = \"{}\"\n", curl_quote(&url)));
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| "run curl")?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("curl stdin unavailable"))?
+        .write_all(curl.as_bytes())
+        .with_context(|| "write curl config")?;
+    let output = child.wait_with_output().with_context(|| "run curl")?;
+    if !output.status.success() {
+        return Err(ErrorKind::AgentFailed.err(tr!(
+            "{url} に接続できません: {}",
+            "cannot reach {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (response, code) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    Ok((code.trim().parse().unwrap_or(0), response.to_string()))
+}
+
+fn request_file(body: &[u8]) -> Result<RequestFile> {
+    let path = env::temp_dir().join(format!(
+        "codex-sdd-request-{}-{}.json",
+        std::process::id(),
+        REQUESTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let _ = fs::remove_file(&path);
+    let request = RequestFile(path);
+    options
+        .open(&request.0)
+        .and_then(|mut file| file.write_all(body))
+        .with_context(|| format!("write {}", request.0.display()))?;
+    Ok(request)
+}
+
+fn strip_fence(message: &str) -> &str {
+    let trimmed = message.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
+
+use crate::analysis::index::{shard_files, FileEntry, FileIndex};
+use crate::core::config::{Config, ContextConfig, PlansConfig};
+use crate::core::error::ErrorKind;
+use crate::tr;
+
+pub const OVERFLOW_POLICIES: [&str; 3] = ["reshard", "error", "warn"];
+const BYTES_PER_TOKEN: u64 = 4;
+const DEFAULT_WINDOW_TOKENS: usize = 128_000;
+const READER_PROMPT_TOKENS: usize = 2_000;
+const READER_OUTPUT_TOKENS: usize = 4_000;
+const AUTO_AGENTS: (usize, usize) = (2, 16);
+const AUTO_SHARD_TOKENS: usize = 40_000;
+const AUTO_SHARD_FILES: usize = 200;
+const MODEL_WINDOWS: [(&str, usize); 7] = [
+    ("gpt-4.1", 1_047_576),
+    ("gpt-5", 272_000),
+    ("codex-mini", 200_000),
+    ("o4-mini", 200_000),
+    ("o3", 200_000),
+    ("o1", 200_000),
+    ("gpt-4o", 128_000),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentCount {
+    Auto,
+    Fixed(usize),
+}
+
+impl FromStr for AgentCount {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        match value.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(Self::Fixed(count)),
+            _ => Err("expected a number of 1 or more, or auto".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AgentCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Fixed(count) => write!(f, "{count}"),
+        }
+    }
+}
+
+pub struct AutoAgents {
+    pub count: usize,
+    pub rationale: String,
+}
+
+pub struct Budget {
+    pub model: String,
+    pub limit: usize,
+    pub policy: String,
+}
+
+impl Budget {
+    pub fn from_config(config: &Config, model: Option<&str>) -> Result<Self> {
+        let ContextConfig {
+            window_tokens,
+            reserve_tokens,
+            on_overflow,
+        } = &config.context;
+        if !OVERFLOW_POLICIES.contains(&on_overflow.as_str()) {
+            return Err(anyhow!(tr!(
+                "context.on_overflow が不正です: {on_overflow} ({} のいずれか)",
+                "invalid context.on_overflow: {on_overflow} (expected one of {})",
+                OVERFLOW_POLICIES.join(", ")
+            )));
+        }
+        let model = model.or(config.model.as_deref());
+        let window = window_tokens.unwrap_or_else(|| {
+            model
+                .and_then(|model| {
+                    MODEL_WINDOWS
+                        .iter()
+                        .find(|(prefix, _)| model.starts_with(prefix))
+                })
+                .map_or(DEFAULT_WINDOW_TOKENS, |(_, window)| *window)
+        });
+        Ok(Self {
+            model: model.unwrap_or("default").to_string(),
+            limit: window.saturating_sub(*reserve_tokens),
+            policy: on_overflow.clone(),
+        })
+    }
+
+    pub fn check(&self, stage: &str, estimate: usize) -> Result<()> {
+        info!(
+            "{stage}: estimated {estimate} tokens (limit {} for {})",
+            self.limit, self.model
+        );
+        if estimate <= self.limit {
+            return Ok(());
+        }
+        let message = tr!(
+            "{stage} のプロンプトは約 {estimate} トークンで、{} のコンテキスト上限 {} (出力用の予約を除く) を超えます。plans --scope / --since で対象を絞るか、[context] window_tokens を設定してください",
+            "{stage} prompt is about {estimate} tokens, over the {} context limit of {} (after the output reserve); narrow it with plans --scope / --since or set [context] window_tokens",
+            self.model,
+            self.limit
+        );
+        if self.policy == "warn" {
+            warn!(
+                "{stage} exceeds the context limit: {estimate} > {}",
+                self.limit
+            );
+            eprintln!("{message}");
+            return Ok(());
+        }
+        Err(ErrorKind::ContextOverflow.err(message))
+    }
+
+    pub fn fit_shards(&self, index: &FileIndex, count: usize) -> Result<Vec<Vec<FileEntry>>> {
+        let shards = shard_files(index, count);
+        let largest = largest_shard(&shards);
+        if largest <= self.limit || self.policy != "reshard" {
+            self.check("reader", largest)?;
+            return Ok(shards);
+        }
+        if let Some(file) = index
+            .files
+            .iter()
+            .find(|file| estimate_shard(std::slice::from_ref(file)) > self.limit)
+        {
+            return Err(ErrorKind::ContextOverflow.err(tr!(
+                "{} だけで {} のコンテキスト上限 {} を超えます。--scope で除外するか、[context] window_tokens を設定してください",
+                "{} alone exceeds the {} context limit of {}; exclude it with --scope or set [context] window_tokens",
+                file.path,
+                self.model,
+                self.limit
+            )));
+        }
+        let total = estimate_shard(&index.files);
+        let start = (count + 1).max(total.div_ceil(self.limit.max(1)));
+        let resharded = (start..=index.files.len())
+            .map(|n| shard_files(index, n))
+            .find(|shards| largest_shard(shards) <= self.limit)
+            .unwrap_or_else(|| shard_files(index, index.files.len()));
+        warn!(
+            "reader shards exceed the context limit ({largest} > {}); resharding {count} -> {}",
+            self.limit,
+            resharded.len()
+        );
+        eprintln!(
+            "{}",
+            tr!(
+                "reader のシャードが {} のコンテキスト上限を超えるため、{count} 個から {} 個に分割し直しました",
+                "reader shards exceeded the {} context limit; split {count} shards into {}",
+                self.model,
+                resharded.len()
+            )
+        );
+        Ok(resharded)
+    }
+
+    pub fn auto_agents(&self, index: &FileIndex, config: &PlansConfig) -> AutoAgents {
+        let (min, max) = AUTO_AGENTS;
+        let files = index.files.len();
+        let tokens = estimate_shard(&index.files).saturating_sub(READER_PROMPT_TOKENS);
+        let per_reader = READER_PROMPT_TOKENS + READER_OUTPUT_TOKENS;
+        let by_size = tokens
+            .div_ceil(AUTO_SHARD_TOKENS)
+            .max(files.div_ceil(AUTO_SHARD_FILES));
+        let by_window = tokens.div_ceil(self.limit.saturating_sub(READER_PROMPT_TOKENS).max(1));
+        let cap = config.max_parallel.clamp(min, max);
+        let by_budget = config
+            .token_budget
+            .map(|budget| budget.saturating_sub(tokens) / per_reader);
+        let count = by_size
+            .min(cap)
+            .min(by_budget.unwrap_or(max))
+            .max(min)
+            .max(by_window.min(max))
+            .min(files.max(1));
+        let estimate = tokens + count * per_reader;
+        let budget = match config.token_budget {
+            Some(budget) => {
+                if estimate > budget {
+                    warn!("auto agents: estimated {estimate} tokens exceed the plans.token_budget of {budget}");
+                }
+                format!("budget {budget} allows {}", by_budget.unwrap_or_default())
+            }
+            None => "no budget".to_string(),
+        };
+        let rationale = format!(
+            "{files} files, ~{tokens} tokens: size suggests {by_size}, the {} window ({}) needs {by_window}, {budget}, parallel cap {cap} -> {count} agents, ~{estimate} tokens in total",
+            self.model, self.limit
+        );
+        info!("auto agents: {rationale}");
+        AutoAgents { count, rationale }
+    }
+}
+
+pub fn estimate_text(text: &str) -> usize {
+    tokens(text.len() as u64)
+}
+
+pub fn estimate_files(paths: &[&Path]) -> usize {
+    paths
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| tokens(meta.len()))
+        .sum()
+}
+
+pub fn estimate_shard(shard: &[FileEntry]) -> usize {
+    READER_PROMPT_TOKENS
+        + shard
+            .iter()
+            .map(|entry| tokens(entry.size + entry.path.len() as u64 + 1))
+            .sum::<usize>()
+}
+
+fn largest_shard(shards: &[Vec<FileEntry>]) -> usize {
+    shards.iter().map(|s| estimate_shard(s)).max().unwrap_or(0)
+}
+
+fn tokens(bytes: u64) -> usize {
+    bytes.div_ceil(BYTES_PER_TOKEN) as usize
+}
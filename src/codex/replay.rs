@@ -0,0 +1,447 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::codex::exec::{list_runs, Backend, ExecResult, ExecSpec};
+use crate::codex::transcript::{Redactor, TRANSCRIPTS_DIR};
+use crate::core::error::ErrorKind;
+use crate::core::paths::RepoPaths;
+use crate::docs::templates::line_diff;
+use crate::tr;
+use crate::util::{ensure_dir, read_to_string, write_string};
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub number: u32,
+    pub name: String,
+    pub dir: PathBuf,
+    pub meta: RecordingMeta,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingMeta {
+    pub schema: Option<String>,
+    pub duration_secs: f64,
+    pub status_ok: bool,
+    pub redactions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutcome {
+    pub number: u32,
+    pub name: String,
+    pub prompt_matched: bool,
+    pub redactions: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_diff: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactOutcome {
+    pub path: String,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Readers(Vec<usize>),
+    Review {
+        against_specs: bool,
+        debaters: Option<u32>,
+    },
+    Tasks,
+    SelectDebate {
+        debaters: u32,
+    },
+    Select,
+}
+
+impl Step {
+    pub fn label(&self) -> String {
+        match self {
+            Step::Readers(shards) => format!(
+                "readers {}",
+                shards
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Step::Review {
+                against_specs,
+                debaters,
+            } => {
+                let mut label = "review".to_string();
+                if *against_specs {
+                    label.push_str(" --against-specs");
+                }
+                if let Some(debaters) = debaters {
+                    label.push_str(&format!(" --debaters {debaters}"));
+                }
+                label
+            }
+            Step::Tasks => "tasks".to_string(),
+            Step::SelectDebate { debaters } => format!("select --debaters {debaters}"),
+            Step::Select => "select".to_string(),
+        }
+    }
+
+    fn classify(recording: &Recording) -> Option<Self> {
+        let name = recording.name.as_str();
+        let against_specs = recording.meta.schema.as_deref() == Some("review_specs.json");
+        if let Some(shard) = name.strip_prefix("reader_") {
+            return shard.parse().ok().map(|shard| Step::Readers(vec![shard]));
+        }
+        if name == "review" {
+            return Some(Step::Review {
+                against_specs,
+                debaters: None,
+            });
+        }
+        if name == "tasks" {
+            return Some(Step::Tasks);
+        }
+        if let Some(rest) = name.strip_prefix("review_") {
+            let debater = rest.strip_suffix("_votes").unwrap_or(rest);
+            return debater.parse().ok().map(|debater| Step::Review {
+                against_specs,
+                debaters: Some(debater),
+            });
+        }
+        let (debater, _) = name.strip_prefix("select_")?.split_once("_round")?;
+        debater
+            .parse()
+            .ok()
+            .map(|debaters| Step::SelectDebate { debaters })
+    }
+
+    fn absorb(&mut self, other: &Step) -> bool {
+        match (self, other) {
+            (Step::Readers(shards), Step::Readers(more)) => {
+                shards.extend(more);
+                true
+            }
+            (
+                Step::Review {
+                    debaters: Some(count),
+                    ..
+                },
+                Step::Review {
+                    debaters: Some(debater),
+                    ..
+                },
+            )
+            | (Step::SelectDebate { debaters: count }, Step::SelectDebate { debaters: debater }) => {
+                *count = (*count).max(*debater);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Session {
+    sandbox: String,
+    repo_root: String,
+    queues: BTreeMap<String, VecDeque<Recording>>,
+    outcomes: Vec<RunOutcome>,
+}
+
+pub fn recordings(change_runs_dir: &Path) -> Result<Vec<Recording>> {
+    list_runs(&change_runs_dir.join(TRANSCRIPTS_DIR))
+        .into_iter()
+        .map(|(number, name, dir)| {
+            let meta_path = dir.join("meta.json");
+            let meta = serde_json::from_str(&read_to_string(&meta_path)?)
+                .with_context(|| format!("parse {}", meta_path.display()))?;
+            Ok(Recording {
+                number,
+                name,
+                dir,
+                meta,
+            })
+        })
+        .collect()
+}
+
+pub fn plan(recordings: &[Recording], selected: bool) -> Vec<Step> {
+    let mut groups: Vec<(Step, BTreeSet<&str>)> = Vec::new();
+    for recording in recordings {
+        let Some(step) = Step::classify(recording) else {
+            continue;
+        };
+        let name = recording.name.as_str();
+        if let Some((last, names)) = groups.last_mut() {
+            if !names.contains(name) && last.absorb(&step) {
+                names.insert(name);
+                continue;
+            }
+        }
+        groups.push((step, BTreeSet::from([name])));
+    }
+    let mut steps: Vec<Step> = groups.into_iter().map(|(step, _)| step).collect();
+    if selected
+        && !steps
+            .iter()
+            .any(|step| matches!(step, Step::SelectDebate { .. }))
+    {
+        steps.push(Step::Select);
+    }
+    steps
+}
+
+pub fn prepare_sandbox(
+    paths: &RepoPaths,
+    sandbox: &RepoPaths,
+    change_id: &str,
+    change_dir: &Path,
+) -> Result<()> {
+    let root = paths.replay_dir.join(change_id);
+    if root.exists() {
+        fs::remove_dir_all(&root).with_context(|| format!("remove {}", root.display()))?;
+    }
+    let name = change_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("no change directory name for {}", change_dir.display()))?;
+    copy_tree(change_dir, &sandbox.docs_changes.join(name))?;
+    copy_tree(
+        &paths.docs_sdd.join("specs"),
+        &sandbox.docs_sdd.join("specs"),
+    )?;
+    copy_tree(&paths.schemas_dir, &sandbox.schemas_dir)?;
+    ensure_dir(&sandbox.runs_dir.join(change_id))?;
+    for (from, to) in [
+        (&paths.state_path, &sandbox.state_path),
+        (
+            &paths.runs_dir.join(change_id).join("metrics.json"),
+            &sandbox.runs_dir.join(change_id).join("metrics.json"),
+        ),
+    ] {
+        if from.is_file() {
+            fs::copy(from, to)
+                .with_context(|| format!("copy {} to {}", from.display(), to.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(from) {
+        let entry = entry.with_context(|| format!("walk {}", from.display()))?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            ensure_dir(&target)?;
+        } else {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!("copy {} to {}", entry.path().display(), target.display())
+            })?;
+        }
+    }
+    Ok(())
+}
+
+pub fn compare_artifacts(
+    original: &Path,
+    replayed: &Path,
+    recursive: bool,
+    since: SystemTime,
+    repo_root: &Path,
+    sandbox: &Path,
+) -> Result<Vec<ArtifactOutcome>> {
+    let sandbox = sandbox.display().to_string();
+    let repo_root_text = repo_root.display().to_string();
+    let mut out = Vec::new();
+    let walk = WalkDir::new(replayed)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .sort_by_file_name();
+    for entry in walk.into_iter().flatten() {
+        let file_name = entry.file_name().to_string_lossy();
+        if !entry.file_type().is_file()
+            || file_name.ends_with(".latest")
+            || file_name.contains("prompt")
+        {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if modified < since {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(replayed)?;
+        let original_path = original.join(rel);
+        let path = original_path
+            .strip_prefix(repo_root)
+            .unwrap_or(&original_path)
+            .display()
+            .to_string();
+        let contents = read_to_string(entry.path())?.replace(&sandbox, &repo_root_text);
+        let outcome = if !original_path.is_file() {
+            ArtifactOutcome {
+                path,
+                status: "new",
+                diff: None,
+            }
+        } else {
+            let recorded = read_to_string(&original_path)?;
+            if recorded == contents {
+                ArtifactOutcome {
+                    path,
+                    status: "identical",
+                    diff: None,
+                }
+            } else {
+                ArtifactOutcome {
+                    path,
+                    status: "differs",
+                    diff: Some(line_diff(&recorded, &contents)),
+                }
+            }
+        };
+        out.push(outcome);
+    }
+    Ok(out)
+}
+
+pub fn start(sandbox: &Path, repo_root: &Path, recordings: &[Recording]) -> Result<()> {
+    let mut queues: BTreeMap<String, VecDeque<Recording>> = BTreeMap::new();
+    for recording in recordings {
+        queues
+            .entry(recording.name.clone())
+            .or_default()
+            .push_back(recording.clone());
+    }
+    let mut session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("replay lock poisoned"))?;
+    *session = Some(Session {
+        sandbox: sandbox.display().to_string(),
+        repo_root: repo_root.display().to_string(),
+        queues,
+        outcomes: Vec::new(),
+    });
+    Ok(())
+}
+
+pub fn active() -> bool {
+    SESSION.lock().is_ok_and(|session| session.is_some())
+}
+
+pub fn finish() -> Result<(Vec<RunOutcome>, Vec<Recording>)> {
+    let session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("replay lock poisoned"))?
+        .take()
+        .ok_or_else(|| anyhow!("replay session not started"))?;
+    let mut outcomes = session.outcomes;
+    outcomes.sort_by_key(|outcome| outcome.number);
+    let mut unused: Vec<Recording> = session.queues.into_values().flatten().collect();
+    unused.sort_by_key(|recording| recording.number);
+    Ok((outcomes, unused))
+}
+
+fn unsandbox(text: &str) -> Result<String> {
+    let session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("replay lock poisoned"))?;
+    Ok(match session.as_ref() {
+        Some(session) => text.replace(&session.sandbox, &session.repo_root),
+        None => text.to_string(),
+    })
+}
+
+pub fn run(spec: &ExecSpec) -> Result<ExecResult> {
+    let name = spec
+        .output_path
+        .parent()
+        .and_then(Path::file_name)
+        .and_then(|dir| dir.to_str())
+        .and_then(|dir| dir.split_once('_'))
+        .map(|(_, name)| name.to_string())
+        .ok_or_else(|| anyhow!("no run name for {}", spec.output_path.display()))?;
+    let recording = {
+        let mut session = SESSION
+            .lock()
+            .map_err(|_| anyhow!("replay lock poisoned"))?;
+        let session = session
+            .as_mut()
+            .ok_or_else(|| anyhow!("replay session not started"))?;
+        session
+            .queues
+            .get_mut(&name)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                ErrorKind::NotFound.err(tr!(
+                    "run {name} の記録された応答がありません",
+                    "no recorded response for run {name}"
+                ))
+            })?
+    };
+    debug!(
+        "replay {name} from {} for {}",
+        recording.dir.display(),
+        spec.output_path.display()
+    );
+
+    let (prompt, _) = Redactor::new(&spec.env.redact_patterns())?
+        .redact(&unsandbox(&read_to_string(&spec.prompt_path)?)?);
+    let recorded = read_to_string(&recording.dir.join("prompt.md"))?;
+    let prompt_matched = match spec.backend {
+        Backend::Codex => recorded == prompt,
+        Backend::OpenAi { .. } => recorded.starts_with(&prompt),
+    };
+    if !prompt_matched {
+        warn!(
+            "prompt for {name} differs from recording {}",
+            recording.number
+        );
+    }
+    if recording.meta.redactions > 0 {
+        warn!(
+            "recording {} for {name} has {} redactions; artifacts will not match",
+            recording.number, recording.meta.redactions
+        );
+    }
+
+    let response = recording.dir.join("response.md");
+    if response.is_file() {
+        write_string(&spec.output_path, &read_to_string(&response)?)?;
+    }
+    let raw = recording.dir.join("raw.jsonl");
+    if let (Some(json_path), true) = (&spec.json_output_path, raw.is_file()) {
+        write_string(json_path, &read_to_string(&raw)?)?;
+    }
+
+    let outcome = RunOutcome {
+        number: recording.number,
+        name,
+        prompt_matched,
+        redactions: recording.meta.redactions,
+        prompt_diff: (!prompt_matched).then(|| line_diff(&recorded, &prompt)),
+    };
+    if let Some(session) = SESSION
+        .lock()
+        .map_err(|_| anyhow!("replay lock poisoned"))?
+        .as_mut()
+    {
+        session.outcomes.push(outcome);
+    }
+    Ok(ExecResult {
+        status_ok: recording.meta.status_ok,
+        duration_secs: recording.meta.duration_secs,
+        timed_out: false,
+    })
+}
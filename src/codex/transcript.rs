@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use regex::{Captures, Regex};
+use serde_json::json;
+
+use crate::codex::exec::{Backend, ExecResult, ExecSpec};
+use crate::util::{ensure_dir, read_to_string, write_string};
+
+pub const TRANSCRIPTS_DIR: &str = "transcripts";
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Default)]
+pub struct Transcript {
+    pub prompt: String,
+    pub raw: String,
+    pub stderr: String,
+    pub model: Option<String>,
+}
+
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("invalid exec.redact pattern {pattern}"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    pub fn redact(&self, text: &str) -> (String, usize) {
+        let mut out = text.to_string();
+        let mut count = 0;
+        for pattern in &self.patterns {
+            out = pattern
+                .replace_all(&out, |caps: &Captures| {
+                    count += 1;
+                    let Some(secret) = caps.name("secret") else {
+                        return REDACTED.to_string();
+                    };
+                    let whole = &caps[0];
+                    let offset = caps.get(0).map_or(0, |m| m.start());
+                    format!(
+                        "{}{REDACTED}{}",
+                        &whole[..secret.start() - offset],
+                        &whole[secret.end() - offset..]
+                    )
+                })
+                .into_owned();
+        }
+        (out, count)
+    }
+}
+
+pub fn transcript_dir(output_path: &Path) -> Result<PathBuf> {
+    let run_dir = output_path
+        .parent()
+        .ok_or_else(|| anyhow!("no run directory for {}", output_path.display()))?;
+    let change_dir = run_dir
+        .parent()
+        .ok_or_else(|| anyhow!("no change directory for {}", run_dir.display()))?;
+    let name = run_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("no run name for {}", run_dir.display()))?;
+    Ok(change_dir.join(TRANSCRIPTS_DIR).join(name))
+}
+
+pub fn archive(
+    spec: &ExecSpec,
+    transcript: &Transcript,
+    result: &ExecResult,
+    started_at: &str,
+) -> Result<PathBuf> {
+    let redactor = Redactor::new(&spec.env.redact_patterns())?;
+    let dir = transcript_dir(&spec.output_path)?;
+    ensure_dir(&dir)?;
+    let response = if spec.output_path.is_file() {
+        read_to_string(&spec.output_path)?
+    } else {
+        String::new()
+    };
+    let mut redactions = 0;
+    for (file, text) in [
+        ("prompt.md", &transcript.prompt),
+        ("response.md", &response),
+        ("raw.jsonl", &transcript.raw),
+        ("stderr.txt", &transcript.stderr),
+    ] {
+        if text.is_empty() && file != "prompt.md" {
+            continue;
+        }
+        let (clean, count) = redactor.redact(text);
+        redactions += count;
+        write_string(&dir.join(file), &clean)?;
+    }
+    let meta = json!({
+        "backend": match spec.backend {
+            Backend::Codex => "codex",
+            Backend::OpenAi { .. } => "openai",
+        },
+        "model": transcript.model,
+        "sandbox": spec.sandbox,
+        "schema": spec.schema_path.as_ref().and_then(|path| path.file_name()).map(|name| name.to_string_lossy()),
+        "started_at": started_at,
+        "duration_secs": result.duration_secs,
+        "status_ok": result.status_ok,
+        "redactions": redactions,
+    });
+    write_string(
+        &dir.join("meta.json"),
+        &serde_json::to_string_pretty(&meta)?,
+    )?;
+    Ok(dir)
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::lock::RepoLock;
+use crate::tr;
+use crate::util::{current_user, ensure_dir, now_rfc3339};
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const AUDIT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub ts: String,
+    pub change_id: Option<String>,
+    pub event: String,
+    pub actor: String,
+    #[serde(default)]
+    pub details: BTreeMap<String, String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+pub fn append(
+    path: &Path,
+    change_id: Option<&str>,
+    event: &str,
+    details: &[(&str, &str)],
+) -> Result<AuditEntry> {
+    if let Some(parent) = path.parent() {
+        ensure_dir(parent)?;
+    }
+    let _lock = RepoLock::acquire_waiting(
+        &path.with_file_name("audit.lock"),
+        "audit",
+        AUDIT_LOCK_TIMEOUT,
+    )?;
+    let last = read_entries(path)?.pop();
+    let mut entry = AuditEntry {
+        seq: last.as_ref().map_or(1, |e| e.seq + 1),
+        ts: now_rfc3339(),
+        change_id: change_id.map(str::to_string),
+        event: event.to_string(),
+        actor: current_user(),
+        details: details
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        prev_hash: last.map_or_else(|| GENESIS_HASH.to_string(), |e| e.hash),
+        hash: String::new(),
+    };
+    entry.hash = entry_hash(&entry)?;
+    let line = serde_json::to_string(&entry).with_context(|| "serialize audit entry")?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    writeln!(file, "{line}").with_context(|| format!("write {}", path.display()))?;
+    file.sync_all()
+        .with_context(|| format!("sync {}", path.display()))?;
+    Ok(entry)
+}
+
+pub fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("parse {} line {}", path.display(), idx + 1))
+        })
+        .collect()
+}
+
+pub fn verify(entries: &[AuditEntry]) -> Result<()> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (idx, entry) in entries.iter().enumerate() {
+        if entry.seq != idx as u64 + 1 {
+            return Err(anyhow!(tr!(
+                "audit log seq {} が連続していません",
+                "audit log seq {} is not contiguous",
+                entry.seq
+            )));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(anyhow!(tr!(
+                "audit log seq {} の prev_hash が一致しません",
+                "audit log seq {} has a mismatched prev_hash",
+                entry.seq
+            )));
+        }
+        if entry_hash(entry)? != entry.hash {
+            return Err(anyhow!(tr!(
+                "audit log seq {} が改ざんされています",
+                "audit log seq {} has been tampered with",
+                entry.seq
+            )));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(())
+}
+
+fn entry_hash(entry: &AuditEntry) -> Result<String> {
+    let mut unsigned = entry.clone();
+    unsigned.hash = String::new();
+    let data = serde_json::to_string(&unsigned).with_context(|| "serialize audit entry")?;
+    Ok(blake3::hash(data.as_bytes()).to_hex().to_string())
+}
@@ -0,0 +1,619 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::core::paths::resolve_codex_home;
+
+pub const GLOBAL_CONFIG_PATH: &str = "sdd/config.toml";
+const DEFAULT_REDACT: &[&str] = &[
+    r"sk-[A-Za-z0-9_-]{20,}",
+    r"gh[pousr]_[A-Za-z0-9]{30,}",
+    r"github_pat_[A-Za-z0-9_]{30,}",
+    r"AKIA[0-9A-Z]{16}",
+    r"xox[abprs]-[A-Za-z0-9-]{10,}",
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    r"(?i)bearer\s+(?P<secret>[A-Za-z0-9._~+/=-]{16,})",
+    r#"(?i)(?:password|passwd|secret|token|api[_-]?key)\s*[:=]\s*["']?(?P<secret>[^\s"']{8,})"#,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub model: Option<String>,
+    pub backend: String,
+    pub max_parallel: usize,
+    pub state_backend: String,
+    pub notify: String,
+    pub language: String,
+    pub quality: QualityConfig,
+    pub check: CheckConfig,
+    pub approvals: ApprovalsConfig,
+    pub init: InitConfig,
+    pub gc: GcConfig,
+    pub exec: ExecConfig,
+    pub openai: OpenAiConfig,
+    pub cache: CacheConfig,
+    pub variation: VariationConfig,
+    pub findings: FindingsConfig,
+    pub context: ContextConfig,
+    pub plans: PlansConfig,
+    pub spike: SpikeConfig,
+    pub hooks: BTreeMap<String, StageHooks>,
+    pub issues: IssuesConfig,
+    pub worktrees: WorktreesConfig,
+    pub finalize: FinalizeConfig,
+    pub digest: DigestConfig,
+    pub risk: RiskConfig,
+    pub encryption: EncryptionConfig,
+    pub owners: OwnersConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub audit: String,
+    pub audit_ignore: Vec<String>,
+    pub bench: bool,
+    pub bench_command: String,
+    pub bench_threshold_percent: f64,
+    pub build_metrics: bool,
+    pub mutants: bool,
+    pub pre_test: Vec<String>,
+    pub post_test: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct CheckConfig {
+    pub audit_gate: bool,
+    pub subprojects: BTreeMap<String, SubprojectConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubprojectConfig {
+    pub paths: Vec<String>,
+    pub specs: String,
+    pub changes: String,
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalsConfig {
+    pub required: usize,
+    pub allowed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OwnersConfig {
+    pub enforce: bool,
+    pub codeowners: Option<String>,
+    pub paths: BTreeMap<String, Vec<String>>,
+    pub aliases: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InitConfig {
+    pub template: String,
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecConfig {
+    pub env_allow: Vec<String>,
+    pub env_deny: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub archive_prompts: bool,
+    pub redact: Vec<String>,
+    pub redact_defaults: bool,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpikeConfig {
+    pub max_files: usize,
+    pub timeout_secs: u64,
+    pub keep: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAiConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub api_key_env: String,
+    pub model: Option<String>,
+    pub timeout_secs: u64,
+    pub json_schema: bool,
+    pub temperature: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariationConfig {
+    pub strategy: String,
+    pub models: Vec<String>,
+    pub agents: BTreeMap<String, AgentVariationConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AgentVariationConfig {
+    pub emphasis: Option<String>,
+    pub model: Option<String>,
+    pub design: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub readers: bool,
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FindingsConfig {
+    pub finalize_gate: bool,
+    pub blocking_severities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextConfig {
+    pub window_tokens: Option<usize>,
+    pub reserve_tokens: usize,
+    pub on_overflow: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlansConfig {
+    pub token_budget: Option<usize>,
+    pub max_parallel: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct IssuesConfig {
+    pub jira_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WorktreesConfig {
+    pub copy: Vec<String>,
+    pub symlink: Vec<String>,
+    pub bootstrap: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FinalizeConfig {
+    pub base_branch: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub severity_weights: BTreeMap<String, f64>,
+    pub lines_per_point: f64,
+    pub churn_window: usize,
+    pub churn_per_point: f64,
+    pub uncovered_weight: f64,
+    pub max_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    pub chunked: bool,
+    pub chunk_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    pub key_env: String,
+    pub keychain: bool,
+    pub keychain_service: String,
+    pub keychain_account: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct StageHooks {
+    pub pre: Vec<String>,
+    pub post: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GcConfig {
+    pub keep_runs: usize,
+    pub delete_archived: bool,
+    pub max_size_mb: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            model: None,
+            backend: "codex".to_string(),
+            max_parallel: 2,
+            state_backend: "json".to_string(),
+            notify: "none".to_string(),
+            language: "ja".to_string(),
+            quality: QualityConfig::default(),
+            check: CheckConfig::default(),
+            approvals: ApprovalsConfig::default(),
+            init: InitConfig::default(),
+            gc: GcConfig::default(),
+            exec: ExecConfig::default(),
+            openai: OpenAiConfig::default(),
+            cache: CacheConfig::default(),
+            variation: VariationConfig::default(),
+            findings: FindingsConfig::default(),
+            context: ContextConfig::default(),
+            plans: PlansConfig::default(),
+            spike: SpikeConfig::default(),
+            hooks: BTreeMap::new(),
+            issues: IssuesConfig::default(),
+            worktrees: WorktreesConfig::default(),
+            finalize: FinalizeConfig::default(),
+            digest: DigestConfig::default(),
+            risk: RiskConfig::default(),
+            encryption: EncryptionConfig::default(),
+            owners: OwnersConfig::default(),
+        }
+    }
+}
+
+impl Default for SubprojectConfig {
+    fn default() -> Self {
+        Self {
+            paths: vec![
+                "src/**".to_string(),
+                "tests/**".to_string(),
+                "Cargo.toml".to_string(),
+                "Cargo.lock".to_string(),
+            ],
+            specs: "docs/sdd/specs".to_string(),
+            changes: "docs/sdd/changes".to_string(),
+            artifacts: vec![
+                "90_decision.md".to_string(),
+                "40_tasks.md".to_string(),
+                "50_test_plan.md".to_string(),
+            ],
+        }
+    }
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            severity_weights: [
+                ("critical", 10.0),
+                ("high", 5.0),
+                ("medium", 2.0),
+                ("low", 1.0),
+            ]
+            .into_iter()
+            .map(|(severity, weight)| (severity.to_string(), weight))
+            .collect(),
+            lines_per_point: 100.0,
+            churn_window: 200,
+            churn_per_point: 10.0,
+            uncovered_weight: 10.0,
+            max_score: None,
+        }
+    }
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_env: "CODEX_SDD_KEY".to_string(),
+            keychain: false,
+            keychain_service: "codex-sdd".to_string(),
+            keychain_account: "default".to_string(),
+        }
+    }
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            chunked: false,
+            chunk_bytes: 256 * 1024,
+        }
+    }
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            env_allow: Vec::new(),
+            env_deny: Vec::new(),
+            env: BTreeMap::new(),
+            archive_prompts: false,
+            redact: Vec::new(),
+            redact_defaults: true,
+            timeout_secs: None,
+        }
+    }
+}
+
+impl Default for SpikeConfig {
+    fn default() -> Self {
+        Self {
+            max_files: 30,
+            timeout_secs: 600,
+            keep: 20,
+        }
+    }
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:8000/v1".to_string(),
+            api_key: None,
+            api_key_env: "OPENAI_API_KEY".to_string(),
+            model: None,
+            timeout_secs: 600,
+            json_schema: true,
+            temperature: None,
+        }
+    }
+}
+
+impl Default for PlansConfig {
+    fn default() -> Self {
+        Self {
+            token_budget: None,
+            max_parallel: 16,
+        }
+    }
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            window_tokens: None,
+            reserve_tokens: 16_000,
+            on_overflow: "reshard".to_string(),
+        }
+    }
+}
+
+impl Default for FindingsConfig {
+    fn default() -> Self {
+        Self {
+            finalize_gate: true,
+            blocking_severities: vec!["critical".to_string()],
+        }
+    }
+}
+
+impl Default for VariationConfig {
+    fn default() -> Self {
+        Self {
+            strategy: "none".to_string(),
+            models: Vec::new(),
+            agents: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            readers: true,
+            dir: None,
+        }
+    }
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            keep_runs: 10,
+            delete_archived: true,
+            max_size_mb: None,
+        }
+    }
+}
+
+impl Default for InitConfig {
+    fn default() -> Self {
+        Self {
+            template: "standard".to_string(),
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Default for ApprovalsConfig {
+    fn default() -> Self {
+        Self {
+            required: 1,
+            allowed: Vec::new(),
+        }
+    }
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            audit: "none".to_string(),
+            audit_ignore: Vec::new(),
+            bench: false,
+            bench_command: "cargo bench".to_string(),
+            bench_threshold_percent: 5.0,
+            build_metrics: false,
+            mutants: false,
+            pre_test: Vec::new(),
+            post_test: Vec::new(),
+        }
+    }
+}
+
+impl ExecConfig {
+    pub fn apply(&self, cmd: &mut Command) {
+        if !self.env_allow.is_empty() || !self.env_deny.is_empty() {
+            if !self.env_allow.is_empty() {
+                cmd.env_clear();
+            }
+            for (key, value) in std::env::vars_os() {
+                let name = key.to_string_lossy();
+                if matches_any(&self.env_deny, &name) {
+                    cmd.env_remove(&key);
+                } else if !self.env_allow.is_empty() && matches_any(&self.env_allow, &name) {
+                    cmd.env(&key, value);
+                }
+            }
+        }
+        cmd.envs(&self.env);
+    }
+
+    pub fn redact_patterns(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = if self.redact_defaults {
+            DEFAULT_REDACT.iter().map(|p| p.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        patterns.extend(self.redact.iter().cloned());
+        patterns
+    }
+}
+
+fn matches_any(patterns: &[String], key: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => pattern == key,
+        })
+}
+
+pub struct ConfigLayer {
+    pub path: PathBuf,
+    pub table: Table,
+}
+
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub origin: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let layers = Self::layers(path)?;
+        let sources: Vec<String> = layers
+            .iter()
+            .map(|layer| layer.path.display().to_string())
+            .collect();
+        let mut merged = Table::new();
+        for layer in layers {
+            merge(&mut merged, layer.table);
+        }
+        Value::Table(merged)
+            .try_into()
+            .with_context(|| format!("parse {}", sources.join(" + ")))
+    }
+
+    pub fn layers(path: &Path) -> Result<Vec<ConfigLayer>> {
+        let mut layers = Vec::new();
+        let global = resolve_codex_home()
+            .ok()
+            .map(|home| home.join(GLOBAL_CONFIG_PATH));
+        for path in global.iter().map(PathBuf::as_path).chain([path]) {
+            if !path.exists() {
+                continue;
+            }
+            let data =
+                fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+            let table: Table =
+                toml::from_str(&data).with_context(|| format!("parse {}", path.display()))?;
+            layers.push(ConfigLayer {
+                path: path.to_path_buf(),
+                table,
+            });
+        }
+        Ok(layers)
+    }
+
+    pub fn entries(path: &Path) -> Result<Vec<ConfigEntry>> {
+        let config = Self::load(path)?;
+        let layers = Self::layers(path)?;
+        let mut values = Vec::new();
+        let merged = Table::try_from(&config).with_context(|| "serialize config")?;
+        flatten("", &merged, &mut values);
+        Ok(values
+            .into_iter()
+            .map(|(key, value)| {
+                let origin = layers
+                    .iter()
+                    .rev()
+                    .find(|layer| lookup(&layer.table, &key).is_some())
+                    .map(|layer| layer.path.clone());
+                ConfigEntry {
+                    key,
+                    value: value.to_string(),
+                    origin,
+                }
+            })
+            .collect())
+    }
+}
+
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn flatten(prefix: &str, table: &Table, out: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Table(table) => flatten(&key, table, out),
+            value => out.push((key, value.clone())),
+        }
+    }
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (head, rest) = match key.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (key, None),
+    };
+    match (table.get(head)?, rest) {
+        (Value::Table(table), Some(rest)) => lookup(table, rest),
+        (value, None) => Some(value),
+        (_, Some(_)) => None,
+    }
+}
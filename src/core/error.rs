@@ -0,0 +1,78 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotAGitRepo,
+    ApprovalRequired,
+    CheckViolation,
+    AgentFailed,
+    SchemaViolation,
+    MergeConflict,
+    LockBusy,
+    DependencyPending,
+    NotFound,
+    ContextOverflow,
+    HookFailed,
+}
+
+impl ErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::NotAGitRepo => "not-a-git-repo",
+            ErrorKind::ApprovalRequired => "approval-required",
+            ErrorKind::CheckViolation => "check-violation",
+            ErrorKind::AgentFailed => "agent-failed",
+            ErrorKind::SchemaViolation => "schema-violation",
+            ErrorKind::MergeConflict => "merge-conflict",
+            ErrorKind::LockBusy => "lock-busy",
+            ErrorKind::DependencyPending => "dependency-pending",
+            ErrorKind::NotFound => "not-found",
+            ErrorKind::ContextOverflow => "context-overflow",
+            ErrorKind::HookFailed => "hook-failed",
+        }
+    }
+
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::NotAGitRepo => 3,
+            ErrorKind::ApprovalRequired => 4,
+            ErrorKind::CheckViolation => 5,
+            ErrorKind::AgentFailed => 6,
+            ErrorKind::SchemaViolation => 7,
+            ErrorKind::MergeConflict => 8,
+            ErrorKind::LockBusy => 9,
+            ErrorKind::DependencyPending => 10,
+            ErrorKind::NotFound => 11,
+            ErrorKind::ContextOverflow => 12,
+            ErrorKind::HookFailed => 13,
+        }
+    }
+
+    pub fn err(self, message: impl Into<String>) -> anyhow::Error {
+        SddError {
+            kind: self,
+            message: message.into(),
+        }
+        .into()
+    }
+}
+
+#[derive(Debug)]
+pub struct SddError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for SddError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SddError {}
+
+pub fn error_kind(err: &anyhow::Error) -> Option<ErrorKind> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<SddError>())
+        .map(|err| err.kind)
+}
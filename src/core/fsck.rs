@@ -0,0 +1,160 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use anyhow::Result;
+
+use crate::core::paths::RepoPaths;
+use crate::core::state::State;
+use crate::git::worktree::{delete_branch, list_branches, prune_worktrees};
+use crate::tr;
+
+#[derive(Debug, Clone)]
+pub enum Issue {
+    MissingChangeDir(String),
+    UnknownActiveChange(String),
+    WorktreeNotInState(String),
+    MissingWorktree { change_id: String, agent: String },
+    DanglingBranch(String),
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Issue::MissingChangeDir(id) => tr!(
+                "change {id}: docs/sdd/changes と archive にディレクトリがありません",
+                "change {id}: no directory under docs/sdd/changes or archive"
+            ),
+            Issue::UnknownActiveChange(id) => tr!(
+                "active_change_id {id} が state に存在しません",
+                "active_change_id {id} is not in state"
+            ),
+            Issue::WorktreeNotInState(id) => tr!(
+                "worktrees/{id} に対応する change が state にありません",
+                "worktrees/{id} has no matching change in state"
+            ),
+            Issue::MissingWorktree { change_id, agent } => tr!(
+                "branch sdd/{change_id}/{agent} に対応する worktree がありません",
+                "branch sdd/{change_id}/{agent} has no worktree"
+            ),
+            Issue::DanglingBranch(branch) => tr!(
+                "branch {branch} に対応する change が state にありません",
+                "branch {branch} has no matching change in state"
+            ),
+        };
+        f.write_str(&message)
+    }
+}
+
+pub fn fsck(paths: &RepoPaths, state: &State) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+
+    let mut change_ids: Vec<&String> = state.changes.keys().collect();
+    change_ids.sort();
+    for change_id in &change_ids {
+        if paths.find_change_dir(change_id).is_err()
+            && paths.find_archived_change_dir(change_id).is_none()
+        {
+            issues.push(Issue::MissingChangeDir(change_id.to_string()));
+        }
+    }
+
+    if let Some(active) = &state.active_change_id {
+        if !state.changes.contains_key(active) {
+            issues.push(Issue::UnknownActiveChange(active.clone()));
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(&paths.worktrees_dir) {
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        for name in names {
+            let change_id = name.strip_suffix(".base").unwrap_or(&name);
+            if !state.changes.contains_key(change_id) {
+                issues.push(Issue::WorktreeNotInState(name.clone()));
+            }
+        }
+    }
+
+    let known: BTreeSet<&str> = state.changes.keys().map(|k| k.as_str()).collect();
+    for branch in list_branches(&paths.repo_root, "sdd/*")? {
+        let mut parts = branch.splitn(3, '/');
+        let (_, change_id, agent) = (parts.next(), parts.next(), parts.next());
+        let (Some(change_id), Some(agent)) = (change_id, agent) else {
+            continue;
+        };
+        if !known.contains(change_id) {
+            issues.push(Issue::DanglingBranch(branch.clone()));
+        } else if !paths.worktrees_dir.join(change_id).join(agent).exists()
+            && paths.find_archived_change_dir(change_id).is_none()
+        {
+            issues.push(Issue::MissingWorktree {
+                change_id: change_id.to_string(),
+                agent: agent.to_string(),
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+pub fn repair(
+    paths: &RepoPaths,
+    state: &mut State,
+    issues: &[Issue],
+    delete_branches: bool,
+) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+    for issue in issues {
+        match issue {
+            Issue::MissingChangeDir(id) => {
+                state.changes.remove(id);
+                actions.push(tr!(
+                    "state から change {id} を削除しました",
+                    "removed change {id} from state"
+                ));
+                if state.active_change_id.as_deref() == Some(id.as_str()) {
+                    state.active_change_id = None;
+                    actions.push(tr!(
+                        "active_change_id をクリアしました",
+                        "cleared active_change_id"
+                    ));
+                }
+            }
+            Issue::UnknownActiveChange(_) => {
+                if state.active_change_id.take().is_some() {
+                    actions.push(tr!(
+                        "active_change_id をクリアしました",
+                        "cleared active_change_id"
+                    ));
+                }
+            }
+            Issue::DanglingBranch(branch) if delete_branches => {
+                delete_branch(&paths.repo_root, branch)?;
+                actions.push(tr!(
+                    "branch {branch} を削除しました",
+                    "deleted branch {branch}"
+                ));
+            }
+            Issue::WorktreeNotInState(name) if paths.find_change_dir(name).is_ok() => {
+                state.change_state_mut(name);
+                actions.push(tr!(
+                    "state に change {name} を登録しました",
+                    "registered change {name} in state"
+                ));
+            }
+            Issue::WorktreeNotInState(_)
+            | Issue::MissingWorktree { .. }
+            | Issue::DanglingBranch(_) => {}
+        }
+    }
+    prune_worktrees(&paths.repo_root)?;
+    actions.push(tr!(
+        "git worktree prune を実行しました",
+        "ran git worktree prune"
+    ));
+    Ok(actions)
+}
@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use crate::codex::exec::{latest_run, list_runs};
+use crate::codex::transcript::TRANSCRIPTS_DIR;
+use crate::core::lock::RepoLock;
+use crate::core::paths::RepoPaths;
+use crate::core::state::State;
+use crate::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcReason {
+    Archived,
+    Orphaned,
+    OldSnapshot,
+    OldRun,
+    OldSpike,
+    SizeCap,
+}
+
+impl GcReason {
+    pub fn label(self) -> String {
+        match self {
+            GcReason::Archived => tr!("archive 済み", "archived"),
+            GcReason::Orphaned => tr!("state にない change", "not in state"),
+            GcReason::OldSnapshot => tr!("古い metrics スナップショット", "old metrics snapshot"),
+            GcReason::OldRun => tr!("古い実行結果", "old run output"),
+            GcReason::OldSpike => tr!("古い spike", "old spike"),
+            GcReason::SizeCap => tr!("サイズ上限超過", "over size cap"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Removal {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub reason: GcReason,
+}
+
+#[derive(Debug, Clone)]
+pub struct GcPolicy {
+    pub keep_runs: usize,
+    pub delete_archived: bool,
+    pub max_bytes: Option<u64>,
+    pub keep_spikes: usize,
+}
+
+struct RunFile {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+pub fn plan(paths: &RepoPaths, state: &State, policy: &GcPolicy) -> Result<Vec<Removal>> {
+    let mut removals = spike_removals(&paths.spikes_dir, policy.keep_spikes)?;
+    let Ok(entries) = fs::read_dir(&paths.runs_dir) else {
+        return Ok(removals);
+    };
+    let mut change_ids: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    change_ids.sort();
+
+    let mut remaining = Vec::new();
+    for change_id in change_ids {
+        let run_dir = paths.runs_dir.join(&change_id);
+        if let Some(holder) = RepoLock::holder(&paths.change_lock_path(&change_id)) {
+            warn!("skip runs of {change_id}: locked by {holder}");
+            continue;
+        }
+        let in_progress = paths.find_change_dir(&change_id).is_ok();
+        let reason = if in_progress {
+            None
+        } else if paths.find_archived_change_dir(&change_id).is_some() {
+            Some(GcReason::Archived)
+        } else if !state.changes.contains_key(&change_id) {
+            Some(GcReason::Orphaned)
+        } else {
+            None
+        };
+        if let Some(reason) = reason.filter(|_| policy.delete_archived) {
+            removals.push(Removal {
+                bytes: dir_size(&run_dir)?,
+                path: run_dir,
+                reason,
+            });
+            continue;
+        }
+
+        let mut snapshots = run_files(&run_dir.join("metrics"))?;
+        snapshots.sort_by(|a, b| a.path.cmp(&b.path));
+        let expired = snapshots.len().saturating_sub(policy.keep_runs);
+        for snapshot in snapshots.drain(..expired) {
+            removals.push(Removal {
+                path: snapshot.path,
+                bytes: snapshot.bytes,
+                reason: GcReason::OldSnapshot,
+            });
+        }
+        remaining.extend(snapshots);
+        let active = state.active_change_id.as_deref() == Some(change_id.as_str());
+        let runs = list_runs(&run_dir);
+        for (_, name, dir) in &runs {
+            let history: Vec<&PathBuf> = runs
+                .iter()
+                .filter(|(_, other, _)| other == name)
+                .map(|(_, _, dir)| dir)
+                .collect();
+            let latest = latest_run(&run_dir, name).map(|latest| run_dir.join(latest));
+            let position = history.iter().position(|other| *other == dir).unwrap_or(0);
+            if latest.as_ref() == Some(dir) {
+                continue;
+            }
+            let transcript = run_dir
+                .join(TRANSCRIPTS_DIR)
+                .join(dir.file_name().unwrap_or_default());
+            if position + policy.keep_runs < history.len() {
+                for path in [dir.clone(), transcript] {
+                    if path.exists() {
+                        removals.push(Removal {
+                            bytes: dir_size(&path)?,
+                            path,
+                            reason: GcReason::OldRun,
+                        });
+                    }
+                }
+            } else if !active {
+                remaining.extend(run_files(dir)?);
+                remaining.extend(run_files(&transcript)?);
+            }
+        }
+        if !active {
+            remaining.extend(
+                run_files(&run_dir)?
+                    .into_iter()
+                    .filter(|file| file.path.file_name().is_some_and(|n| n != "metrics.json")),
+            );
+        }
+    }
+
+    if let Some(max_bytes) = policy.max_bytes {
+        let removed: u64 = removals
+            .iter()
+            .filter(|removal| removal.path.starts_with(&paths.runs_dir))
+            .map(|removal| removal.bytes)
+            .sum();
+        let mut total = dir_size(&paths.runs_dir)?.saturating_sub(removed);
+        remaining.sort_by_key(|file| file.modified);
+        for file in remaining {
+            if total <= max_bytes {
+                break;
+            }
+            total = total.saturating_sub(file.bytes);
+            removals.push(Removal {
+                path: file.path,
+                bytes: file.bytes,
+                reason: GcReason::SizeCap,
+            });
+        }
+    }
+    Ok(removals)
+}
+
+fn spike_removals(spikes_dir: &Path, keep: usize) -> Result<Vec<Removal>> {
+    let mut spikes: Vec<PathBuf> = fs::read_dir(spikes_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    spikes.sort();
+    let expired = spikes.len().saturating_sub(keep);
+    spikes
+        .drain(..expired)
+        .map(|path| {
+            Ok(Removal {
+                bytes: dir_size(&path)?,
+                path,
+                reason: GcReason::OldSpike,
+            })
+        })
+        .collect()
+}
+
+pub fn apply(removals: &[Removal]) -> Result<u64> {
+    let mut freed = 0;
+    for removal in removals {
+        if removal.path.is_dir() {
+            fs::remove_dir_all(&removal.path)
+        } else {
+            fs::remove_file(&removal.path)
+        }
+        .with_context(|| format!("remove {}", removal.path.display()))?;
+        freed += removal.bytes;
+    }
+    Ok(freed)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn run_files(dir: &Path) -> Result<Vec<RunFile>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let metadata = entry
+            .metadata()
+            .with_context(|| format!("stat {}", entry.path().display()))?;
+        if metadata.is_file() {
+            files.push(RunFile {
+                path: entry.path(),
+                bytes: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+    Ok(files)
+}
+
+fn dir_size(dir: &Path) -> Result<u64> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(0);
+    };
+    let mut total = 0;
+    for entry in entries.flatten() {
+        let metadata = entry
+            .metadata()
+            .with_context(|| format!("stat {}", entry.path().display()))?;
+        total += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
@@ -0,0 +1,130 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::core::config::Config;
+use crate::core::error::ErrorKind;
+use crate::core::paths::RepoPaths;
+use crate::tr;
+use crate::util::shell_command;
+
+pub struct HookRunner {
+    paths: RepoPaths,
+    command: &'static str,
+    pre: Vec<String>,
+    post: Vec<String>,
+}
+
+impl HookRunner {
+    pub fn load(command: &'static str) -> Option<Self> {
+        let paths = RepoPaths::load().ok()?;
+        let config = match Config::load(&paths.config_path) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("skip hooks for {command}: {err:#}");
+                return None;
+            }
+        };
+        let hooks = config.hooks.get(command).cloned().unwrap_or_default();
+        if hooks.pre.is_empty() && hooks.post.is_empty() {
+            return None;
+        }
+        Some(Self {
+            paths,
+            command,
+            pre: hooks.pre,
+            post: hooks.post,
+        })
+    }
+
+    pub fn pre(&self, change_id: Option<&str>) -> Result<()> {
+        self.run("pre", &self.pre, change_id, None)
+    }
+
+    pub fn post(&self, change_id: Option<&str>, result: &Result<()>) -> Result<()> {
+        let status = if result.is_ok() { "ok" } else { "failed" };
+        self.run("post", &self.post, change_id, Some(status))
+    }
+
+    fn run(
+        &self,
+        phase: &str,
+        commands: &[String],
+        change_id: Option<&str>,
+        status: Option<&str>,
+    ) -> Result<()> {
+        let mut vars: Vec<(&str, String)> = vec![
+            ("CODEX_SDD_COMMAND", self.command.to_string()),
+            ("CODEX_SDD_HOOK", phase.to_string()),
+            (
+                "CODEX_SDD_REPO_ROOT",
+                self.paths.repo_root.to_string_lossy().to_string(),
+            ),
+        ];
+        if let Some(status) = status {
+            vars.push(("CODEX_SDD_STATUS", status.to_string()));
+        }
+        if let Some(change_id) = change_id {
+            vars.push(("CODEX_SDD_CHANGE", change_id.to_string()));
+            if let Some(change_dir) = self.change_dir(change_id) {
+                let artifacts: Vec<PathBuf> = stage_artifacts(self.command)
+                    .iter()
+                    .map(|name| change_dir.join(name))
+                    .filter(|path| path.exists())
+                    .collect();
+                if let Ok(joined) = std::env::join_paths(&artifacts) {
+                    vars.push(("CODEX_SDD_ARTIFACTS", joined.to_string_lossy().to_string()));
+                }
+                vars.push((
+                    "CODEX_SDD_CHANGE_DIR",
+                    change_dir.to_string_lossy().to_string(),
+                ));
+            }
+        }
+        for hook in commands {
+            info!("run {phase} hook for {}: {hook}", self.command);
+            let mut cmd = shell_command(hook);
+            cmd.current_dir(&self.paths.repo_root)
+                .envs(vars.iter().map(|(k, v)| (*k, v.as_str())))
+                .stdin(Stdio::null())
+                .stdout(Stdio::from(io::stderr()));
+            let status = cmd
+                .status()
+                .with_context(|| format!("run {phase} hook: {hook}"))?;
+            if !status.success() {
+                let code = status
+                    .code()
+                    .map_or("signal".to_string(), |code| code.to_string());
+                return Err(ErrorKind::HookFailed.err(tr!(
+                    "{} の {phase} hook が失敗しました (exit {code}): {hook}",
+                    "{phase} hook for {} failed (exit {code}): {hook}",
+                    self.command
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn change_dir(&self, change_id: &str) -> Option<PathBuf> {
+        self.paths
+            .find_change_dir(change_id)
+            .ok()
+            .or_else(|| self.paths.find_archived_change_dir(change_id))
+    }
+}
+
+fn stage_artifacts(command: &str) -> &'static [&'static str] {
+    match command {
+        "plans" | "watch" => &["10_repo_digest.md", "10_repo_digest/index.md"],
+        "review" => &["20_review.md"],
+        "tasks" => &["40_tasks.md"],
+        "approve" => &["90_decision.md"],
+        "test-plan" => &["50_test_plan.md"],
+        "select" => &["80_selection.md"],
+        "finalize" => &["90_decision.md", "80_selection.md"],
+        _ => &[],
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::core::error::ErrorKind;
+use crate::tr;
+use crate::util::{ensure_dir, now_rfc3339, run_cmd_allow_fail};
+
+const STALE_AFTER_HOURS: i64 = 12;
+
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockInfo {
+    pid: u32,
+    command: String,
+    acquired_at: String,
+}
+
+pub struct RepoLock {
+    path: PathBuf,
+}
+
+impl RepoLock {
+    pub fn acquire(path: &Path, command: &str) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            ensure_dir(parent)?;
+        }
+        let info = LockInfo {
+            pid: std::process::id(),
+            command: command.to_string(),
+            acquired_at: now_rfc3339(),
+        };
+        let data = serde_json::to_string(&info).with_context(|| "serialize lock")?;
+        let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+        let tmp = sibling(path, &format!("{}.{seq}.tmp", info.pid));
+        fs::write(&tmp, &data).with_context(|| format!("write {}", tmp.display()))?;
+        let result = Self::link(path, &tmp);
+        let _ = fs::remove_file(&tmp);
+        result
+    }
+
+    fn link(path: &Path, tmp: &Path) -> Result<Self> {
+        for _ in 0..3 {
+            match fs::hard_link(tmp, path) {
+                Ok(()) => {
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => match read_lock(path) {
+                    Holder::Gone => {}
+                    Holder::Unreadable => return Err(busy(path, None)),
+                    Holder::Held(holder) if !is_stale(&holder) => {
+                        return Err(busy(path, Some(&holder)));
+                    }
+                    Holder::Held(_) => remove_stale(path)?,
+                },
+                Err(err) => {
+                    return Err(err).with_context(|| format!("create {}", path.display()));
+                }
+            }
+        }
+        Err(anyhow!("failed to acquire lock {}", path.display()))
+    }
+
+    pub fn acquire_waiting(path: &Path, command: &str, timeout: Duration) -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match Self::acquire(path, command) {
+                Ok(lock) => return Ok(lock),
+                Err(err) if started.elapsed() >= timeout => return Err(err),
+                Err(_) => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+
+    pub fn holder(path: &Path) -> Option<String> {
+        let Holder::Held(info) = read_lock(path) else {
+            return None;
+        };
+        if is_stale(&info) {
+            return None;
+        }
+        Some(format!(
+            "pid {}, command {}, since {}",
+            info.pid, info.command, info.acquired_at
+        ))
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+enum Holder {
+    Gone,
+    Unreadable,
+    Held(LockInfo),
+}
+
+fn read_lock(path: &Path) -> Holder {
+    match fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data).map_or(Holder::Unreadable, Holder::Held),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Holder::Gone,
+        Err(_) => Holder::Unreadable,
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+fn remove_stale(path: &Path) -> Result<()> {
+    let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    let aside = sibling(path, &format!("{}.{seq}.stale", std::process::id()));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("rename {}", path.display())),
+    }
+    match read_lock(&aside) {
+        Holder::Held(holder) if !is_stale(&holder) => {
+            let _ = fs::hard_link(&aside, path);
+            let _ = fs::remove_file(&aside);
+            Err(busy(path, Some(&holder)))
+        }
+        _ => {
+            warn!("remove stale lock {}", path.display());
+            let _ = fs::remove_file(&aside);
+            Ok(())
+        }
+    }
+}
+
+fn busy(path: &Path, holder: Option<&LockInfo>) -> anyhow::Error {
+    let Some(holder) = holder else {
+        return ErrorKind::LockBusy.err(tr!(
+            "{} を読み取れません。別の codex-sdd が実行中でなければ削除してください",
+            "cannot read {}; remove it if no other codex-sdd is running",
+            path.display()
+        ));
+    };
+    ErrorKind::LockBusy.err(tr!(
+        "別の codex-sdd が実行中です (pid {}, command {}, since {})。終了を待つか、不要なら {} を削除してください",
+        "another codex-sdd is running (pid {}, command {}, since {}). Wait for it to finish, or remove {} if it is stale",
+        holder.pid,
+        holder.command,
+        holder.acquired_at,
+        path.display()
+    ))
+}
+
+fn is_stale(info: &LockInfo) -> bool {
+    if let Ok(acquired) = DateTime::parse_from_rfc3339(&info.acquired_at) {
+        let age = Utc::now().signed_duration_since(acquired.with_timezone(&Utc));
+        if age.num_hours() >= STALE_AFTER_HOURS {
+            return true;
+        }
+    }
+    !process_alive(info.pid)
+}
+
+fn process_alive(pid: u32) -> bool {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("tasklist");
+        cmd.args(["/FI", &format!("PID eq {pid}"), "/NH"]);
+        cmd
+    } else {
+        let mut cmd = Command::new("kill");
+        cmd.args(["-0", &pid.to_string()]).env("LC_ALL", "C");
+        cmd
+    };
+    cmd.current_dir(std::env::temp_dir());
+    match run_cmd_allow_fail(cmd) {
+        Ok(output) if cfg!(windows) => {
+            String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+        }
+        Ok(output) => {
+            output.status.success()
+                || !String::from_utf8_lossy(&output.stderr).contains("No such process")
+        }
+        Err(_) => true,
+    }
+}
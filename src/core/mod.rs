@@ -1,2 +1,12 @@
+pub mod audit_log;
+pub mod config;
+pub mod error;
+pub mod fsck;
+pub mod gc;
+pub mod hooks;
+pub mod lock;
+pub mod owners;
 pub mod paths;
 pub mod state;
+pub mod store;
+pub mod tracker;
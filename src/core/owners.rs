@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use tracing::warn;
+
+use crate::core::config::OwnersConfig;
+use crate::util::read_to_string;
+
+const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+const DECISION_PATHS: usize = 10;
+
+struct Rule {
+    matcher: GlobSet,
+    owners: Vec<String>,
+}
+
+pub struct Owners {
+    codeowners: Vec<Rule>,
+    configured: Vec<Rule>,
+    aliases: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedPaths {
+    pub owners: Vec<String>,
+    pub paths: Vec<String>,
+}
+
+impl OwnedPaths {
+    pub fn key(&self) -> String {
+        self.owners.join(" ")
+    }
+}
+
+impl Owners {
+    pub fn load(repo_root: &Path, config: &OwnersConfig) -> Result<Self> {
+        let file = match &config.codeowners {
+            Some(rel) => Some(repo_root.join(rel)),
+            None => CODEOWNERS_PATHS
+                .iter()
+                .map(|rel| repo_root.join(rel))
+                .find(|path| path.is_file()),
+        };
+        let mut codeowners = Vec::new();
+        if let Some(file) = file.filter(|path| path.is_file()) {
+            for (idx, line) in read_to_string(&file)?.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let mut parts = line.split_whitespace();
+                let Some(pattern) = parts.next() else {
+                    continue;
+                };
+                match compile(pattern) {
+                    Ok(matcher) => codeowners.push(Rule {
+                        matcher,
+                        owners: parts
+                            .take_while(|part| !part.starts_with('#'))
+                            .map(str::to_string)
+                            .collect(),
+                    }),
+                    Err(err) => warn!("skip {}:{}: {err:#}", file.display(), idx + 1),
+                }
+            }
+        }
+        let mut configured = Vec::new();
+        for (pattern, owners) in &config.paths {
+            configured.push(Rule {
+                matcher: compile(pattern)
+                    .with_context(|| format!("invalid owners.paths pattern {pattern}"))?,
+                owners: owners.clone(),
+            });
+        }
+        let aliases = config
+            .aliases
+            .iter()
+            .map(|(name, owners)| {
+                (
+                    normalize(name),
+                    owners.iter().map(|o| normalize(o)).collect(),
+                )
+            })
+            .collect();
+        Ok(Self {
+            codeowners,
+            configured,
+            aliases,
+        })
+    }
+
+    pub fn owners_of(&self, path: &str) -> Vec<String> {
+        let configured: BTreeSet<&String> = self
+            .configured
+            .iter()
+            .filter(|rule| rule.matcher.is_match(path))
+            .flat_map(|rule| &rule.owners)
+            .collect();
+        if !configured.is_empty() {
+            return configured.into_iter().cloned().collect();
+        }
+        self.codeowners
+            .iter()
+            .rev()
+            .find(|rule| rule.matcher.is_match(path))
+            .map(|rule| rule.owners.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn route(&self, paths: &BTreeSet<String>) -> Vec<OwnedPaths> {
+        let mut groups: BTreeMap<Vec<String>, Vec<String>> = BTreeMap::new();
+        for path in paths {
+            let owners = self.owners_of(path);
+            if !owners.is_empty() {
+                groups.entry(owners).or_default().push(path.clone());
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(owners, paths)| OwnedPaths { owners, paths })
+            .collect()
+    }
+
+    pub fn is_owner(&self, approver: &str, owners: &[String]) -> bool {
+        let approver = normalize(approver);
+        let aliases = self.aliases.get(&approver);
+        owners.iter().map(|owner| normalize(owner)).any(|owner| {
+            owner == approver || aliases.is_some_and(|aliases| aliases.contains(&owner))
+        })
+    }
+}
+
+pub fn pending<'a>(
+    owners: &Owners,
+    groups: &'a [OwnedPaths],
+    approvers: &[&str],
+    overrides: &BTreeMap<String, String>,
+) -> Vec<&'a OwnedPaths> {
+    groups
+        .iter()
+        .filter(|group| {
+            !overrides.contains_key(&group.key())
+                && !approvers
+                    .iter()
+                    .any(|by| owners.is_owner(by, &group.owners))
+        })
+        .collect()
+}
+
+pub fn render(
+    owners: &Owners,
+    groups: &[OwnedPaths],
+    approvers: &[&str],
+    overrides: &BTreeMap<String, String>,
+) -> String {
+    let mut out = String::from("\n## Owners\n\n");
+    if groups.is_empty() {
+        out.push_str("- no owned paths touched\n");
+        return out;
+    }
+    for group in groups {
+        let approved: Vec<&str> = approvers
+            .iter()
+            .copied()
+            .filter(|by| owners.is_owner(by, &group.owners))
+            .collect();
+        let status = match overrides.get(&group.key()) {
+            _ if !approved.is_empty() => format!("approved by {}", approved.join(", ")),
+            Some(by) => format!("overridden by {by}"),
+            None => "pending".to_string(),
+        };
+        out.push_str(&format!("- {}: {status}\n", group.owners.join(", ")));
+        for path in group.paths.iter().take(DECISION_PATHS) {
+            out.push_str(&format!("  - {path}\n"));
+        }
+        if group.paths.len() > DECISION_PATHS {
+            out.push_str(&format!(
+                "  - ... and {} more\n",
+                group.paths.len() - DECISION_PATHS
+            ));
+        }
+    }
+    out
+}
+
+fn compile(pattern: &str) -> Result<GlobSet> {
+    let body = pattern.trim_start_matches('/').trim_end_matches('/');
+    let anchored = pattern.starts_with('/') || body.contains('/');
+    let base = if anchored || body.starts_with("**") {
+        body.to_string()
+    } else {
+        format!("**/{body}")
+    };
+    let mut globs = Vec::new();
+    if !pattern.ends_with('/') {
+        globs.push(base.clone());
+    }
+    if pattern.ends_with('/') || !body.rsplit('/').next().unwrap_or(body).contains('*') {
+        globs.push(format!("{base}/**"));
+    }
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("invalid pattern {pattern}"))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().trim_start_matches('@').to_lowercase()
+}
@@ -1,9 +1,13 @@
 use std::env;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, Context, Result};
 
+use crate::core::config::Config;
+use crate::core::error::ErrorKind;
+use crate::tr;
 use crate::util::run_cmd_allow_fail;
 
 #[derive(Clone, Debug)]
@@ -17,9 +21,27 @@ pub struct RepoPaths {
     pub docs_sdd: PathBuf,
     pub docs_changes: PathBuf,
     pub state_path: PathBuf,
+    pub config_path: PathBuf,
+    pub lock_path: PathBuf,
+    pub audit_log_path: PathBuf,
+    pub locks_dir: PathBuf,
+    pub logs_dir: PathBuf,
     pub runs_dir: PathBuf,
+    pub spikes_dir: PathBuf,
+    pub replay_dir: PathBuf,
     pub worktrees_dir: PathBuf,
     pub schemas_dir: PathBuf,
+    pub templates_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub cwd_change_id: Option<String>,
+}
+
+static SANDBOX: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn redirect_to_sandbox(root: PathBuf) -> Result<()> {
+    SANDBOX
+        .set(root)
+        .map_err(|root| anyhow!("sandbox already set to {}", root.display()))
 }
 
 pub fn resolve_codex_home() -> Result<PathBuf> {
@@ -35,15 +57,38 @@ pub fn git_repo_root() -> Result<PathBuf> {
     cmd.args(["rev-parse", "--show-toplevel"]);
     let output = run_cmd_allow_fail(cmd)?;
     if !output.status.success() {
-        return Err(anyhow!("Gitリポジトリが必要です"));
+        return Err(ErrorKind::NotAGitRepo.err(tr!(
+            "Gitリポジトリが必要です",
+            "a git repository is required"
+        )));
     }
     let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
     if root.is_empty() {
-        return Err(anyhow!("Gitリポジトリが必要です"));
+        return Err(ErrorKind::NotAGitRepo.err(tr!(
+            "Gitリポジトリが必要です",
+            "a git repository is required"
+        )));
     }
     Ok(PathBuf::from(root))
 }
 
+fn split_worktree_root(toplevel: PathBuf) -> (PathBuf, Option<String>) {
+    let components: Vec<_> = toplevel.components().collect();
+    for idx in 0..components.len().saturating_sub(3) {
+        let names: Vec<_> = components[idx..idx + 3]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        if names == [".codex", "sdd", "worktrees"] {
+            let root: PathBuf = components[..idx].iter().collect();
+            let change_id = components[idx + 3].as_os_str().to_string_lossy();
+            let change_id = change_id.strip_suffix(".base").unwrap_or(&change_id);
+            return (root, Some(change_id.to_string()));
+        }
+    }
+    (toplevel, None)
+}
+
 impl GlobalPaths {
     pub fn load() -> Result<Self> {
         Ok(Self {
@@ -54,25 +99,98 @@ impl GlobalPaths {
 
 impl RepoPaths {
     pub fn load() -> Result<Self> {
-        let repo_root = git_repo_root()?;
+        let (repo_root, cwd_change_id) = split_worktree_root(git_repo_root()?);
         let docs_sdd = repo_root.join("docs/sdd");
         let docs_changes = docs_sdd.join("changes");
         let codex_sdd_dir = repo_root.join(".codex/sdd");
-        let state_path = codex_sdd_dir.join("state.json");
+        let config_path = codex_sdd_dir.join("config.toml");
+        let config = Config::load(&config_path)?;
+        let state_path = match config.state_backend.as_str() {
+            "json" => codex_sdd_dir.join("state.json"),
+            "sqlite" => codex_sdd_dir.join("state.db"),
+            other => {
+                return Err(anyhow!(tr!(
+                    "未対応の state_backend です: {other}",
+                    "unsupported state_backend: {other}"
+                )))
+            }
+        };
+        let lock_path = codex_sdd_dir.join("lock");
+        let locks_dir = codex_sdd_dir.join("locks");
+        let logs_dir = codex_sdd_dir.join("logs");
+        let audit_log_path = codex_sdd_dir.join("audit.jsonl");
         let runs_dir = codex_sdd_dir.join("runs");
+        let spikes_dir = codex_sdd_dir.join("spikes");
+        let replay_dir = codex_sdd_dir.join("replay");
         let worktrees_dir = codex_sdd_dir.join("worktrees");
         let schemas_dir = codex_sdd_dir.join("schemas");
-        Ok(Self {
+        let templates_dir = codex_sdd_dir.join("templates");
+        let cache_dir = match config.cache.dir {
+            Some(dir) => match dir.strip_prefix("~") {
+                Ok(rest) => dirs::home_dir()
+                    .ok_or_else(|| anyhow!("home directory not found"))?
+                    .join(rest),
+                Err(_) => repo_root.join(dir),
+            },
+            None => codex_sdd_dir.join("cache"),
+        };
+        let paths = Self {
             repo_root,
             docs_sdd,
             docs_changes,
             state_path,
+            config_path,
+            lock_path,
+            audit_log_path,
+            locks_dir,
+            logs_dir,
             runs_dir,
+            spikes_dir,
+            replay_dir,
             worktrees_dir,
             schemas_dir,
+            templates_dir,
+            cache_dir,
+            cwd_change_id,
+        };
+        Ok(match SANDBOX.get() {
+            Some(root) => paths.sandboxed(root),
+            None => paths,
         })
     }
 
+    pub fn sandboxed(&self, root: &Path) -> Self {
+        let docs_sdd = root.join("docs/sdd");
+        let codex_sdd_dir = root.join(".codex/sdd");
+        let state_file = self
+            .state_path
+            .file_name()
+            .map_or_else(|| PathBuf::from("state.json"), PathBuf::from);
+        Self {
+            docs_changes: docs_sdd.join("changes"),
+            docs_sdd,
+            state_path: codex_sdd_dir.join(state_file),
+            lock_path: codex_sdd_dir.join("lock"),
+            audit_log_path: codex_sdd_dir.join("audit.jsonl"),
+            locks_dir: codex_sdd_dir.join("locks"),
+            runs_dir: codex_sdd_dir.join("runs"),
+            spikes_dir: codex_sdd_dir.join("spikes"),
+            schemas_dir: codex_sdd_dir.join("schemas"),
+            cache_dir: codex_sdd_dir.join("cache"),
+            ..self.clone()
+        }
+    }
+
+    pub fn reader_cache_path(&self, shard_hash: &str) -> PathBuf {
+        self.cache_dir
+            .join("readers")
+            .join(format!("{shard_hash}.md"))
+    }
+
+    pub fn change_lock_path(&self, change_id: &str) -> PathBuf {
+        self.locks_dir.join(format!("{change_id}.lock"))
+    }
+
     pub fn change_dir(&self, change_id: &str, name: &str) -> PathBuf {
         let dir_name = format!("{}_{}", change_id, name);
         self.docs_changes.join(dir_name)
@@ -93,7 +211,22 @@ impl RepoPaths {
                 return Ok(entry.path());
             }
         }
-        Err(anyhow!("change workspace not found for {change_id}"))
+        Err(ErrorKind::NotFound.err(format!("change workspace not found for {change_id}")))
+    }
+
+    pub fn find_archived_change_dir(&self, change_id: &str) -> Option<PathBuf> {
+        let entries = std::fs::read_dir(self.docs_sdd.join("archive")).ok()?;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Some(rest) = name.get(11..) else {
+                continue;
+            };
+            if rest.starts_with(&format!("{change_id}_")) {
+                return Some(entry.path());
+            }
+        }
+        None
     }
 
     pub fn change_context_dir(&self, change_dir: &Path) -> PathBuf {
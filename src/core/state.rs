@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
-use crate::util::{now_rfc3339, write_string};
+use crate::core::error::ErrorKind;
+use crate::core::lock::RepoLock;
+use crate::core::paths::RepoPaths;
+use crate::core::store;
+use crate::util::{now_rfc3339, read_to_string, write_string_atomic};
 
 const SCHEMA_VERSION: u32 = 1;
+const BACKUPS_KEEP: usize = 10;
+const STATE_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct State {
@@ -20,6 +28,14 @@ pub struct State {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ChangeState {
+    #[serde(default)]
+    pub goal: Option<String>,
+    #[serde(default)]
+    pub scope: Vec<String>,
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub coverage: Option<String>,
     pub approved: bool,
     pub approved_at: Option<String>,
     pub approved_by: Option<String>,
@@ -32,7 +48,77 @@ pub struct ChangeState {
     #[serde(default)]
     pub reader_shard_hashes: HashMap<String, String>,
     #[serde(default)]
+    pub reader_shards: Option<usize>,
+    #[serde(default)]
     pub base_commit: Option<String>,
+    #[serde(default)]
+    pub variations: BTreeMap<String, AgentVariation>,
+    #[serde(default)]
+    pub selected_agent: Option<String>,
+    #[serde(default)]
+    pub selection_rationale: Option<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub approvals: Vec<Approval>,
+    #[serde(default)]
+    pub approved_artifacts_hash: Option<String>,
+    #[serde(default)]
+    pub decision_history: Vec<DecisionEvent>,
+    #[serde(default)]
+    pub owner_overrides: BTreeMap<String, String>,
+    #[serde(default)]
+    pub findings: BTreeMap<String, FindingStatus>,
+    #[serde(default)]
+    pub issue: Option<IssueLink>,
+    #[serde(default)]
+    pub pull_request: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionEvent {
+    pub action: String,
+    pub by: String,
+    pub at: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueLink {
+    pub tracker: String,
+    pub key: String,
+    pub url: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindingStatus {
+    pub status: String,
+    pub by: String,
+    pub at: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Approval {
+    pub by: String,
+    pub at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentVariation {
+    #[serde(default)]
+    pub emphasis: Option<String>,
+    #[serde(default)]
+    pub instructions: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub design: Option<String>,
+    #[serde(default)]
+    pub design_text: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,23 +126,42 @@ pub struct CodexThread {
     pub purpose: String,
     pub thread_id: String,
     pub started_at: String,
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+}
+
+impl ChangeState {
+    pub fn approval_count(&self) -> usize {
+        if self.approvals.is_empty() && self.approved {
+            1
+        } else {
+            self.approvals.len()
+        }
+    }
 }
 
 impl State {
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
+            let legacy = path.with_extension("json");
+            if store::is_sqlite(path) && legacy.exists() {
+                return Self::load(&legacy);
+            }
             return Ok(Self::new());
         }
-        let data = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
-        let mut state: State = serde_json::from_str(&data).with_context(|| "parse state.json")?;
+        let mut state = if store::is_sqlite(path) {
+            store::load(path)?
+        } else {
+            serde_json::from_str(&read_to_string(path)?).with_context(|| "parse state.json")?
+        };
         if state.schema_version == 0 {
             state.schema_version = SCHEMA_VERSION;
         }
         if state.schema_version != SCHEMA_VERSION {
-            return Err(anyhow!(
+            return Err(ErrorKind::SchemaViolation.err(format!(
                 "unsupported state schema version {}",
                 state.schema_version
-            ));
+            )));
         }
         if state.tool_version.is_empty() {
             state.tool_version = env!("CARGO_PKG_VERSION").to_string();
@@ -77,8 +182,79 @@ impl State {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
         }
+        if store::is_sqlite(path) {
+            return store::save(path, self);
+        }
+        backup_state(path)?;
         let data = serde_json::to_string_pretty(self).with_context(|| "serialize state")?;
-        write_string(path, &data)
+        write_string_atomic(path, &data)
+    }
+
+    pub fn save_change(&self, path: &Path, change_id: &str) -> Result<()> {
+        let _lock = RepoLock::acquire_waiting(&state_lock_path(path), "state", STATE_LOCK_TIMEOUT)?;
+        let mut current = Self::load(path)?;
+        match self.changes.get(change_id) {
+            Some(change) => {
+                current
+                    .changes
+                    .insert(change_id.to_string(), change.clone());
+            }
+            None => {
+                current.changes.remove(change_id);
+            }
+        }
+        current.save(path)
+    }
+
+    pub fn activate(path: &Path, change_id: &str) -> Result<()> {
+        let _lock = RepoLock::acquire_waiting(&state_lock_path(path), "state", STATE_LOCK_TIMEOUT)?;
+        let mut current = Self::load(path)?;
+        current.active_change_id = Some(change_id.to_string());
+        current.save(path)
+    }
+
+    pub fn rename_change(path: &Path, from: &str, to: &str) -> Result<()> {
+        let _lock = RepoLock::acquire_waiting(&state_lock_path(path), "state", STATE_LOCK_TIMEOUT)?;
+        let mut current = Self::load(path)?;
+        let change = current.changes.remove(from).unwrap_or_default();
+        current.changes.insert(to.to_string(), change);
+        for change in current.changes.values_mut() {
+            for dep in change.depends_on.iter_mut().filter(|dep| *dep == from) {
+                *dep = to.to_string();
+            }
+        }
+        if current.active_change_id.as_deref() == Some(from) {
+            current.active_change_id = Some(to.to_string());
+        }
+        store::rename_metrics(path, from, to)?;
+        current.save(path).inspect_err(|_| {
+            if let Err(err) = store::rename_metrics(path, to, from) {
+                warn!("restore metrics of {from}: {err:#}");
+            }
+        })
+    }
+
+    pub fn in_flight_changes(&self, paths: &RepoPaths) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .changes
+            .keys()
+            .filter(|id| paths.find_change_dir(id).is_ok())
+            .cloned()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn pending_dependencies(&self, paths: &RepoPaths, change_id: &str) -> Vec<String> {
+        self.change_state(change_id)
+            .map(|c| {
+                c.depends_on
+                    .iter()
+                    .filter(|dep| paths.find_archived_change_dir(dep).is_none())
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn change_state_mut(&mut self, change_id: &str) -> &mut ChangeState {
@@ -89,30 +265,158 @@ impl State {
         self.changes.get(change_id)
     }
 
-    pub fn require_approved(&self, change_id: &str) -> Result<()> {
+    pub fn require_approved(&self, change_id: &str, required: usize) -> Result<()> {
         let state = self
             .changes
             .get(change_id)
-            .ok_or_else(|| anyhow!("change {change_id} not found"))?;
-        if !state.approved {
-            return Err(anyhow!("approval required for change {change_id}"));
+            .ok_or_else(|| ErrorKind::NotFound.err(format!("change {change_id} not found")))?;
+        let count = state.approval_count();
+        if !state.approved || count < required {
+            return Err(ErrorKind::ApprovalRequired.err(format!(
+                "approval required for change {change_id} ({count}/{required} approvals)"
+            )));
         }
         Ok(())
     }
 
-    pub fn approve_change(&mut self, change_id: &str, approved_by: &str) {
+    pub fn approve_change(
+        &mut self,
+        change_id: &str,
+        approved_by: &str,
+        required: usize,
+        artifacts_hash: &str,
+    ) {
         let state = self.change_state_mut(change_id);
-        state.approved = true;
-        state.approved_at = Some(now_rfc3339());
+        let now = now_rfc3339();
+        if state
+            .approved_artifacts_hash
+            .as_deref()
+            .is_some_and(|hash| hash != artifacts_hash)
+        {
+            state.approvals.clear();
+            state.owner_overrides.clear();
+            state.approved = false;
+            state.approved_at = None;
+        }
+        state.approved_artifacts_hash = Some(artifacts_hash.to_string());
+        if state.approvals.is_empty() && state.approved {
+            if let Some(by) = state.approved_by.clone() {
+                state.approvals.push(Approval {
+                    by,
+                    at: state.approved_at.clone().unwrap_or_else(|| now.clone()),
+                });
+            }
+        }
+        if !state.approvals.iter().any(|a| a.by == approved_by) {
+            state.approvals.push(Approval {
+                by: approved_by.to_string(),
+                at: now.clone(),
+            });
+        }
         state.approved_by = Some(approved_by.to_string());
+        state.decision_history.push(DecisionEvent {
+            action: "approve".to_string(),
+            by: approved_by.to_string(),
+            at: now.clone(),
+            reason: None,
+        });
+        if !state.approved && state.approvals.len() >= required {
+            state.approved = true;
+            state.approved_at = Some(now);
+        }
+    }
+
+    pub fn revoke_approval(&mut self, change_id: &str, revoked_by: &str, reason: &str) {
+        let state = self.change_state_mut(change_id);
+        state.approved = false;
+        state.approved_at = None;
+        state.approved_by = None;
+        state.approvals.clear();
+        state.owner_overrides.clear();
+        state.approved_artifacts_hash = None;
+        state.decision_history.push(DecisionEvent {
+            action: "revoke".to_string(),
+            by: revoked_by.to_string(),
+            at: now_rfc3339(),
+            reason: Some(reason.to_string()),
+        });
+    }
+
+    pub fn record_owner_override(
+        &mut self,
+        change_id: &str,
+        groups: &[String],
+        by: &str,
+        reason: &str,
+    ) {
+        let state = self.change_state_mut(change_id);
+        for group in groups {
+            state.owner_overrides.insert(group.clone(), by.to_string());
+        }
+        state.decision_history.push(DecisionEvent {
+            action: "owner-override".to_string(),
+            by: by.to_string(),
+            at: now_rfc3339(),
+            reason: Some(reason.to_string()),
+        });
     }
 
-    pub fn record_thread(&mut self, change_id: &str, purpose: &str, thread_id: &str) {
+    pub fn record_selection(&mut self, change_id: &str, agent: &str, rationale: &str) {
+        let state = self.change_state_mut(change_id);
+        state.selected_agent = Some(agent.to_string());
+        state.selection_rationale = Some(rationale.to_string());
+    }
+
+    pub fn record_thread(
+        &mut self,
+        change_id: &str,
+        purpose: &str,
+        thread_id: &str,
+        duration_secs: f64,
+    ) {
         let state = self.change_state_mut(change_id);
         state.codex_threads.push(CodexThread {
             purpose: purpose.to_string(),
             thread_id: thread_id.to_string(),
             started_at: now_rfc3339(),
+            duration_secs: Some(duration_secs),
         });
     }
 }
+
+fn state_lock_path(path: &Path) -> PathBuf {
+    path.with_file_name("state.lock")
+}
+
+fn backup_state(path: &Path) -> Result<()> {
+    let Some(parent) = path.parent() else {
+        return Ok(());
+    };
+    if !path.exists() {
+        return Ok(());
+    }
+    let backups_dir = parent.join("backups");
+    fs::create_dir_all(&backups_dir)
+        .with_context(|| format!("create {}", backups_dir.display()))?;
+    let backup = backups_dir.join(format!(
+        "state-{}.json",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    fs::copy(path, &backup).with_context(|| format!("backup {}", path.display()))?;
+
+    let mut backups: Vec<_> = fs::read_dir(&backups_dir)
+        .with_context(|| format!("read {}", backups_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().starts_with("state-"))
+                .unwrap_or(false)
+        })
+        .collect();
+    backups.sort();
+    while backups.len() > BACKUPS_KEEP {
+        let oldest = backups.remove(0);
+        let _ = fs::remove_file(oldest);
+    }
+    Ok(())
+}
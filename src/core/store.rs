@@ -0,0 +1,295 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::quality::metrics::VariantMetrics;
+
+#[derive(Debug, Clone)]
+pub struct MetricsRecord {
+    pub agent: String,
+    pub measured_at: String,
+    pub tests_passed: bool,
+    pub coverage_percent: Option<f64>,
+}
+
+pub fn is_sqlite(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "db")
+}
+
+pub fn record_metrics(
+    state_path: &Path,
+    change_id: &str,
+    variants: &[VariantMetrics],
+) -> Result<()> {
+    if !is_sqlite(state_path) {
+        return Ok(());
+    }
+    backend::record_metrics(state_path, change_id, variants)
+}
+
+pub fn metrics_history(state_path: &Path, change_id: &str) -> Result<Option<Vec<MetricsRecord>>> {
+    if !is_sqlite(state_path) {
+        return Ok(None);
+    }
+    backend::metrics_history(state_path, change_id).map(Some)
+}
+
+pub fn rename_metrics(state_path: &Path, from: &str, to: &str) -> Result<()> {
+    if !is_sqlite(state_path) {
+        return Ok(());
+    }
+    backend::rename_metrics(state_path, from, to)
+}
+
+pub use backend::{load, save};
+
+#[cfg(feature = "sqlite")]
+mod backend {
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use super::MetricsRecord;
+    use crate::core::state::{ChangeState, CodexThread, State};
+    use crate::quality::metrics::VariantMetrics;
+
+    fn open(path: &Path) -> Result<Connection> {
+        if let Some(parent) = path.parent() {
+            crate::util::ensure_dir(parent)?;
+        }
+        let conn = Connection::open(path).with_context(|| format!("open {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT);
+             CREATE TABLE IF NOT EXISTS changes (change_id TEXT PRIMARY KEY, data TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS threads (
+                 change_id TEXT NOT NULL,
+                 purpose TEXT NOT NULL,
+                 thread_id TEXT NOT NULL,
+                 started_at TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS metrics (
+                 change_id TEXT NOT NULL,
+                 agent TEXT NOT NULL,
+                 measured_at TEXT NOT NULL,
+                 tests_passed INTEGER NOT NULL,
+                 coverage_percent REAL,
+                 data TEXT NOT NULL,
+                 PRIMARY KEY (change_id, agent, measured_at)
+             );",
+        )
+        .with_context(|| "create sqlite schema")?;
+        let has_duration = conn
+            .prepare("SELECT 1 FROM pragma_table_info('threads') WHERE name = 'duration_secs'")?
+            .exists([])?;
+        if !has_duration {
+            conn.execute("ALTER TABLE threads ADD COLUMN duration_secs REAL", [])
+                .with_context(|| "migrate sqlite threads table")?;
+        }
+        Ok(conn)
+    }
+
+    fn meta(conn: &Connection, key: &str) -> Result<Option<String>> {
+        Ok(conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    pub fn load(path: &Path) -> Result<State> {
+        if !path.exists() {
+            return Ok(State::new());
+        }
+        let conn = open(path)?;
+        let mut state = State::new();
+        if let Some(version) = meta(&conn, "schema_version")? {
+            state.schema_version = version.parse().unwrap_or(0);
+        }
+        if let Some(version) = meta(&conn, "tool_version")? {
+            state.tool_version = version;
+        }
+        state.active_change_id = meta(&conn, "active_change_id")?;
+
+        let mut stmt = conn.prepare("SELECT change_id, data FROM changes ORDER BY change_id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (change_id, data) = row?;
+            let change: ChangeState =
+                serde_json::from_str(&data).with_context(|| format!("parse change {change_id}"))?;
+            state.changes.insert(change_id, change);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT change_id, purpose, thread_id, started_at, duration_secs
+             FROM threads ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                CodexThread {
+                    purpose: row.get(1)?,
+                    thread_id: row.get(2)?,
+                    started_at: row.get(3)?,
+                    duration_secs: row.get(4)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (change_id, thread) = row?;
+            state
+                .change_state_mut(&change_id)
+                .codex_threads
+                .push(thread);
+        }
+        Ok(state)
+    }
+
+    pub fn save(path: &Path, state: &State) -> Result<()> {
+        let mut conn = open(path)?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+            [state.schema_version.to_string()],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('tool_version', ?1)",
+            [&state.tool_version],
+        )?;
+        match &state.active_change_id {
+            Some(id) => tx.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('active_change_id', ?1)",
+                [id],
+            )?,
+            None => tx.execute("DELETE FROM meta WHERE key = 'active_change_id'", [])?,
+        };
+        tx.execute("DELETE FROM changes", [])?;
+        tx.execute("DELETE FROM threads", [])?;
+        let mut change_ids: Vec<&String> = state.changes.keys().collect();
+        change_ids.sort();
+        for change_id in change_ids {
+            let change = &state.changes[change_id];
+            let mut stored = change.clone();
+            stored.codex_threads.clear();
+            tx.execute(
+                "INSERT INTO changes (change_id, data) VALUES (?1, ?2)",
+                params![change_id, serde_json::to_string(&stored)?],
+            )?;
+            for thread in &change.codex_threads {
+                tx.execute(
+                    "INSERT INTO threads (change_id, purpose, thread_id, started_at, duration_secs)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        change_id,
+                        thread.purpose,
+                        thread.thread_id,
+                        thread.started_at,
+                        thread.duration_secs
+                    ],
+                )?;
+            }
+        }
+        tx.commit()
+            .with_context(|| format!("write {}", path.display()))
+    }
+
+    pub fn record_metrics(path: &Path, change_id: &str, variants: &[VariantMetrics]) -> Result<()> {
+        let mut conn = open(path)?;
+        let tx = conn.transaction()?;
+        for variant in variants {
+            tx.execute(
+                "INSERT OR REPLACE INTO metrics
+                 (change_id, agent, measured_at, tests_passed, coverage_percent, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    change_id,
+                    variant.agent,
+                    variant.measured_at,
+                    variant.tests_passed,
+                    variant.coverage_percent,
+                    serde_json::to_string(variant)?
+                ],
+            )?;
+        }
+        tx.commit()
+            .with_context(|| format!("write {}", path.display()))
+    }
+
+    pub fn rename_metrics(path: &Path, from: &str, to: &str) -> Result<()> {
+        let conn = open(path)?;
+        conn.execute(
+            "UPDATE metrics SET change_id = ?2 WHERE change_id = ?1",
+            params![from, to],
+        )
+        .with_context(|| format!("write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn metrics_history(path: &Path, change_id: &str) -> Result<Vec<MetricsRecord>> {
+        let conn = open(path)?;
+        let mut stmt = conn.prepare(
+            "SELECT agent, measured_at, tests_passed, coverage_percent FROM metrics
+             WHERE change_id = ?1 ORDER BY measured_at, agent",
+        )?;
+        let rows = stmt.query_map([change_id], |row| {
+            Ok(MetricsRecord {
+                agent: row.get(0)?,
+                measured_at: row.get(1)?,
+                tests_passed: row.get(2)?,
+                coverage_percent: row.get(3)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+mod backend {
+    use std::path::Path;
+
+    use anyhow::{anyhow, Result};
+
+    use super::MetricsRecord;
+    use crate::core::state::State;
+    use crate::quality::metrics::VariantMetrics;
+    use crate::tr;
+
+    fn unsupported() -> anyhow::Error {
+        anyhow!(tr!(
+            "state_backend = \"sqlite\" には sqlite feature 付きのビルドが必要です (cargo install codex-sdd --features sqlite)",
+            "state_backend = \"sqlite\" needs a build with the sqlite feature (cargo install codex-sdd --features sqlite)"
+        ))
+    }
+
+    pub fn load(_path: &Path) -> Result<State> {
+        Err(unsupported())
+    }
+
+    pub fn save(_path: &Path, _state: &State) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn record_metrics(
+        _path: &Path,
+        _change_id: &str,
+        _variants: &[VariantMetrics],
+    ) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn rename_metrics(_path: &Path, _from: &str, _to: &str) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn metrics_history(_path: &Path, _change_id: &str) -> Result<Vec<MetricsRecord>> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,269 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::core::config::IssuesConfig;
+use crate::core::error::ErrorKind;
+use crate::core::state::IssueLink;
+use crate::tr;
+use crate::util::{curl_quote, run_cmd_allow_fail, slugify};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueRef {
+    GitHub {
+        repo: Option<String>,
+        number: u64,
+    },
+    Jira {
+        base_url: Option<String>,
+        key: String,
+    },
+}
+
+pub struct Issue {
+    pub link: IssueLink,
+    pub body: String,
+}
+
+impl IssueRef {
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim().trim_end_matches('/');
+        if let Some(rest) = input
+            .strip_prefix("https://github.com/")
+            .or_else(|| input.strip_prefix("http://github.com/"))
+        {
+            let parts: Vec<&str> = rest.split('/').collect();
+            if let [owner, repo, "issues" | "pull", number, ..] = parts.as_slice() {
+                if let Ok(number) = number.parse() {
+                    return Ok(Self::GitHub {
+                        repo: Some(format!("{owner}/{repo}")),
+                        number,
+                    });
+                }
+            }
+        }
+        if let Some((base_url, key)) = input.split_once("/browse/") {
+            if is_jira_key(key) {
+                return Ok(Self::Jira {
+                    base_url: Some(base_url.to_string()),
+                    key: key.to_string(),
+                });
+            }
+        }
+        let (repo, number) = match input.rsplit_once('#') {
+            Some((repo, number)) if !repo.is_empty() => (Some(repo.to_string()), number),
+            Some((_, number)) => (None, number),
+            None => (None, input),
+        };
+        if let Ok(number) = number.parse() {
+            return Ok(Self::GitHub { repo, number });
+        }
+        if is_jira_key(input) {
+            return Ok(Self::Jira {
+                base_url: None,
+                key: input.to_string(),
+            });
+        }
+        Err(anyhow!(tr!(
+            "issue を解釈できません: {input} (GitHub の URL / #123 / owner/repo#123、Jira の URL / ABC-123 のいずれか)",
+            "cannot parse issue: {input} (expected a GitHub URL, #123, owner/repo#123, a Jira URL, or ABC-123)"
+        )))
+    }
+
+    pub fn slug(&self) -> String {
+        match self {
+            Self::GitHub { number, .. } => format!("issue-{number}"),
+            Self::Jira { key, .. } => slugify(key),
+        }
+    }
+}
+
+pub fn fetch_issue(repo_root: &Path, issue: &IssueRef, config: &IssuesConfig) -> Result<Issue> {
+    match issue {
+        IssueRef::GitHub { repo, number } => fetch_github(repo_root, repo.as_deref(), *number),
+        IssueRef::Jira { base_url, key } => {
+            let base_url = base_url
+                .clone()
+                .or_else(|| config.jira_url.clone())
+                .or_else(|| std::env::var("JIRA_BASE_URL").ok())
+                .ok_or_else(|| {
+                    anyhow!(tr!(
+                        "Jira の URL がありません。[issues] jira_url か JIRA_BASE_URL を設定してください",
+                        "no Jira URL; set [issues] jira_url or JIRA_BASE_URL"
+                    ))
+                })?;
+            fetch_jira(base_url.trim_end_matches('/'), key)
+        }
+    }
+}
+
+pub fn closing_reference(link: &IssueLink) -> String {
+    match link.tracker.as_str() {
+        "github" => format!("Closes {}", link.url),
+        _ => format!("Resolves {}", link.key),
+    }
+}
+
+pub fn create_pull_request(
+    repo_root: &Path,
+    base: &str,
+    branch: &str,
+    title: &str,
+    body: &str,
+) -> Result<String> {
+    let mut cmd = Command::new("gh");
+    cmd.current_dir(repo_root).args([
+        "pr", "create", "--base", base, "--head", branch, "--title", title, "--body", body,
+    ]);
+    let output = run_gh(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh pr create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+pub fn pull_request_state(repo_root: &Path, url: &str) -> Result<String> {
+    let mut cmd = Command::new("gh");
+    cmd.current_dir(repo_root)
+        .args(["pr", "view", url, "--json", "state", "--jq", ".state"]);
+    let output = run_gh(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh pr view failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn fetch_github(repo_root: &Path, repo: Option<&str>, number: u64) -> Result<Issue> {
+    let mut cmd = Command::new("gh");
+    cmd.current_dir(repo_root).args([
+        "issue",
+        "view",
+        &number.to_string(),
+        "--json",
+        "number,title,body,url",
+    ]);
+    if let Some(repo) = repo {
+        cmd.args(["--repo", repo]);
+    }
+    let output = run_gh(cmd)?;
+    if !output.status.success() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "GitHub issue #{number} を取得できません: {}",
+            "cannot fetch GitHub issue #{number}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let value: Value =
+        serde_json::from_slice(&output.stdout).with_context(|| "parse gh issue view output")?;
+    Ok(Issue {
+        link: IssueLink {
+            tracker: "github".to_string(),
+            key: format!("#{number}"),
+            url: text(&value, "url"),
+            title: text(&value, "title"),
+        },
+        body: text(&value, "body"),
+    })
+}
+
+fn fetch_jira(base_url: &str, key: &str) -> Result<Issue> {
+    let mut config =
+        String::from("silent\nshow-error\nfail\nheader = \"Accept: application/json\"\n");
+    match (
+        std::env::var("JIRA_USER"),
+        std::env::var("JIRA_API_TOKEN"),
+        std::env::var("JIRA_TOKEN"),
+    ) {
+        (Ok(user), Ok(token), _) => config.push_str(&format!(
+            "user = \"{}\"\n",
+            curl_quote(&format!("{user}:{token}"))
+        )),
+        (_, _, Ok(token)) => config.push_str(&format!(
+            "header = \"{}\"\n",
+            curl_quote(&format!("Authorization: Bearer {token}"))
+        )),
+        _ => {}
+    }
+    config.push_str(&format!(
+        "url = \"{}\"\n",
+        curl_quote(&format!(
+            "{base_url}/rest/api/2/issue/{key}?fields=summary,description"
+        ))
+    ));
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| "run curl")?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("curl stdin unavailable"))?
+        .write_all(config.as_bytes())
+        .with_context(|| "write curl config")?;
+    let output = child.wait_with_output().with_context(|| "run curl")?;
+    if !output.status.success() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "Jira issue {key} を取得できません: {}",
+            "cannot fetch Jira issue {key}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let value: Value =
+        serde_json::from_slice(&output.stdout).with_context(|| "parse Jira issue response")?;
+    let fields = value.get("fields").cloned().unwrap_or_default();
+    Ok(Issue {
+        link: IssueLink {
+            tracker: "jira".to_string(),
+            key: key.to_string(),
+            url: format!("{base_url}/browse/{key}"),
+            title: text(&fields, "summary"),
+        },
+        body: text(&fields, "description"),
+    })
+}
+
+fn run_gh(cmd: Command) -> Result<std::process::Output> {
+    run_cmd_allow_fail(cmd).map_err(|_| {
+        ErrorKind::NotFound.err(tr!(
+            "gh コマンドが見つかりません。GitHub CLI をインストールして gh auth login を実行してください",
+            "gh not found; install the GitHub CLI and run gh auth login"
+        ))
+    })
+}
+
+fn is_jira_key(value: &str) -> bool {
+    let Some((project, number)) = value.split_once('-') else {
+        return false;
+    };
+    project.starts_with(|c: char| c.is_ascii_uppercase())
+        && project
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::core::config::DigestConfig;
+use crate::util::{ensure_dir, write_file};
+
+pub const DIGEST_FILE: &str = "10_repo_digest.md";
+pub const DIGEST_DIR: &str = "10_repo_digest";
+pub const DIGEST_INDEX: &str = "10_repo_digest/index.md";
+const LEGACY_DIGEST_FILE: &str = "repo_digest.md";
+const DIR_DEPTH: usize = 2;
+
+pub struct DigestSection {
+    pub title: String,
+    pub body: String,
+    pub files: Vec<String>,
+}
+
+pub fn digest_entry(change_dir: &Path) -> &'static str {
+    if change_dir.join(DIGEST_INDEX).exists() {
+        DIGEST_INDEX
+    } else {
+        DIGEST_FILE
+    }
+}
+
+pub fn digest_files(change_dir: &Path) -> Vec<PathBuf> {
+    if digest_entry(change_dir) == DIGEST_FILE {
+        return vec![change_dir.join(DIGEST_FILE)];
+    }
+    let dir = change_dir.join(DIGEST_DIR);
+    let mut parts: Vec<PathBuf> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("part_"))
+        })
+        .collect();
+    parts.sort();
+    let mut files = vec![change_dir.join(DIGEST_INDEX)];
+    files.extend(parts);
+    files
+}
+
+pub fn write_digest(
+    change_dir: &Path,
+    header: &str,
+    sections: &[DigestSection],
+    config: &DigestConfig,
+) -> Result<PathBuf> {
+    let dir = change_dir.join(DIGEST_DIR);
+    if !config.chunked {
+        if dir.exists() {
+            fs::remove_dir_all(&dir).with_context(|| format!("remove {}", dir.display()))?;
+        }
+        let mut out = String::from(header);
+        for section in sections {
+            out.push_str(&render_section(section));
+        }
+        write_file(&change_dir.join(LEGACY_DIGEST_FILE), &out)?;
+        write_file(&change_dir.join(DIGEST_FILE), &out)?;
+        return Ok(change_dir.join(DIGEST_FILE));
+    }
+
+    let mut parts: Vec<Vec<&DigestSection>> = Vec::new();
+    let mut size = 0;
+    for section in sections {
+        let len = render_section(section).len();
+        match parts.last_mut() {
+            Some(part) if size + len <= config.chunk_bytes => {
+                part.push(section);
+                size += len;
+            }
+            _ => {
+                parts.push(vec![section]);
+                size = len;
+            }
+        }
+    }
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("remove {}", dir.display()))?;
+    }
+    ensure_dir(&dir)?;
+    let total = parts.len();
+    let mut toc = String::from(header);
+    toc.push_str(&format!(
+        "The digest is split into {total} parts. Use the directory table to find the parts to read.\n\n## Parts\n\n"
+    ));
+    let mut by_dir: BTreeMap<String, (usize, BTreeSet<usize>)> = BTreeMap::new();
+    for (idx, part) in parts.iter().enumerate() {
+        let number = idx + 1;
+        let name = part_name(number);
+        let mut out = format!("# Repo Digest (part {number}/{total})\n\n");
+        let mut dirs = BTreeSet::new();
+        for section in part {
+            out.push_str(&render_section(section));
+            for file in &section.files {
+                let dir = dir_key(file);
+                let entry = by_dir.entry(dir.clone()).or_default();
+                entry.0 += 1;
+                entry.1.insert(number);
+                dirs.insert(dir);
+            }
+        }
+        write_file(&dir.join(&name), &out)?;
+        let titles: Vec<&str> = part.iter().map(|s| s.title.as_str()).collect();
+        toc.push_str(&format!(
+            "- [{name}]({name}): {} ({})\n",
+            titles.join(", "),
+            dirs.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    toc.push_str("\n## Directories\n\n| directory | files | parts |\n| --- | --- | --- |\n");
+    for (dir, (count, numbers)) in &by_dir {
+        let links: Vec<String> = numbers
+            .iter()
+            .map(|number| format!("[{}]({})", number, part_name(*number)))
+            .collect();
+        toc.push_str(&format!("| `{dir}` | {count} | {} |\n", links.join(", ")));
+    }
+    let index = change_dir.join(DIGEST_INDEX);
+    write_file(&index, &toc)?;
+    for name in [DIGEST_FILE, LEGACY_DIGEST_FILE] {
+        let path = change_dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+        }
+    }
+    Ok(index)
+}
+
+fn render_section(section: &DigestSection) -> String {
+    format!("## {}\n\n{}\n", section.title, section.body)
+}
+
+fn part_name(number: usize) -> String {
+    format!("part_{number:02}.md")
+}
+
+fn dir_key(file: &str) -> String {
+    let parts: Vec<&str> = file.split('/').collect();
+    let dirs = &parts[..parts.len() - 1];
+    if dirs.is_empty() {
+        return ".".to_string();
+    }
+    dirs[..dirs.len().min(DIR_DEPTH)].join("/")
+}
@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use globset::Glob;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::core::error::ErrorKind;
+use crate::tr;
+use crate::util::{read_to_string, write_string};
+
+const FACTS_FILE: &str = "file_facts.json";
+const FACT_FIELDS: [&str; 4] = ["role", "public_api", "risks", "test_notes"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileFact {
+    pub shard: usize,
+    pub role: String,
+    pub public_api: String,
+    pub risks: String,
+    pub test_notes: String,
+}
+
+impl FileFact {
+    pub fn fields(&self) -> [(&'static str, &str); 4] {
+        [
+            ("role", &self.role),
+            ("public_api", &self.public_api),
+            ("risks", &self.risks),
+            ("test_notes", &self.test_notes),
+        ]
+    }
+}
+
+pub fn facts_path(context_dir: &Path) -> PathBuf {
+    context_dir.join(FACTS_FILE)
+}
+
+pub fn parse_reader_output(
+    contents: &str,
+    shard: usize,
+    files: &BTreeSet<&str>,
+) -> Option<Vec<(String, FileFact)>> {
+    let output = serde_json::Deserializer::from_str(contents.trim_start())
+        .into_iter::<Value>()
+        .next()
+        .and_then(|value| value.ok())?;
+    let entries = output.get("files").and_then(Value::as_array)?;
+    let mut facts = Vec::new();
+    for entry in entries {
+        let Some(path) = entry.get("path").and_then(Value::as_str) else {
+            warn!("skip reader_{shard} fact without a path");
+            continue;
+        };
+        let path = path.trim_start_matches("./");
+        if !files.contains(path) {
+            warn!("skip reader_{shard} fact for {path}: not in the shard");
+            continue;
+        }
+        let text = |key: &str| {
+            entry
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let [role, public_api, risks, test_notes] = FACT_FIELDS.map(text);
+        facts.push((
+            path.to_string(),
+            FileFact {
+                shard,
+                role,
+                public_api,
+                risks,
+                test_notes,
+            },
+        ));
+    }
+    Some(facts)
+}
+
+pub fn write_facts(context_dir: &Path, facts: &BTreeMap<String, FileFact>) -> Result<()> {
+    write_string(
+        &facts_path(context_dir),
+        &serde_json::to_string_pretty(facts)?,
+    )
+}
+
+pub fn load_facts(context_dir: &Path) -> Result<BTreeMap<String, FileFact>> {
+    let path = facts_path(context_dir);
+    if !path.exists() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{} がありません。plans を実行してください",
+            "{} not found; run plans first",
+            path.display()
+        )));
+    }
+    serde_json::from_str(&read_to_string(&path)?)
+        .with_context(|| format!("parse {}", path.display()))
+}
+
+pub fn query<'a>(
+    facts: &'a BTreeMap<String, FileFact>,
+    pattern: &str,
+) -> Result<Vec<(&'a String, &'a FileFact)>> {
+    let pattern = pattern.trim_start_matches("./");
+    let matcher = Glob::new(pattern)
+        .with_context(|| format!("invalid path glob {pattern}"))?
+        .compile_matcher();
+    Ok(facts
+        .iter()
+        .filter(|(path, _)| path.as_str() == pattern || matcher.is_match(path.as_str()))
+        .collect())
+}
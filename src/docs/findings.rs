@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use blake3::Hasher;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::error::ErrorKind;
+use crate::core::state::FindingStatus;
+use crate::docs::templates::REVIEW_PLACEHOLDER;
+use crate::tr;
+use crate::util::read_to_string;
+
+pub const FINDING_STATUSES: [&str; 3] = ["open", "acknowledged", "resolved"];
+pub const SCOPED_REVIEW_HEADING: &str = "## Scoped review:";
+const FINDING_ID_LEN: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub id: String,
+    pub severity: String,
+    pub file: String,
+    #[serde(default)]
+    pub rationale: String,
+    #[serde(default)]
+    pub suggestion: String,
+    #[serde(default)]
+    pub requirement: Option<String>,
+}
+
+pub fn load_findings(review_path: &Path) -> Result<Vec<Finding>> {
+    if !review_path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = read_to_string(review_path)?;
+    if contents.trim() == REVIEW_PLACEHOLDER.trim() {
+        return Ok(Vec::new());
+    }
+    let mut outputs: Vec<Value> = first_json(&contents).into_iter().collect();
+    for (start, _) in contents.match_indices(SCOPED_REVIEW_HEADING) {
+        let section = &contents[start..];
+        let body = section.split_once('\n').map_or("", |(_, body)| body);
+        let output = first_json(body).ok_or_else(|| {
+            ErrorKind::SchemaViolation.err(tr!(
+                "{} の scoped review に JSON の findings がありません",
+                "a scoped review in {} has no JSON findings",
+                review_path.display()
+            ))
+        })?;
+        outputs.push(output);
+    }
+    if outputs.is_empty() {
+        return Err(ErrorKind::SchemaViolation.err(tr!(
+            "{} の先頭に JSON の findings がありません",
+            "{} does not start with JSON findings",
+            review_path.display()
+        )));
+    }
+    let mut findings = Vec::new();
+    for value in outputs
+        .iter()
+        .filter_map(|output| output.get("findings").and_then(Value::as_array))
+        .flatten()
+    {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let finding = Finding {
+            id: finding_id(&text("severity"), &text("file"), &text("rationale")),
+            severity: text("severity"),
+            file: text("file"),
+            rationale: text("rationale"),
+            suggestion: text("suggestion"),
+            requirement: value
+                .get("requirement")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        };
+        if !findings.iter().any(|f: &Finding| f.id == finding.id) {
+            findings.push(finding);
+        }
+    }
+    Ok(findings)
+}
+
+fn first_json(text: &str) -> Option<Value> {
+    serde_json::Deserializer::from_str(text.trim_start())
+        .into_iter::<Value>()
+        .next()
+        .and_then(|value| value.ok())
+}
+
+pub fn status<'a>(statuses: &'a BTreeMap<String, FindingStatus>, finding: &Finding) -> &'a str {
+    statuses
+        .get(&finding.id)
+        .map(|s| s.status.as_str())
+        .unwrap_or("open")
+}
+
+pub fn blocking<'a>(
+    findings: &'a [Finding],
+    statuses: &BTreeMap<String, FindingStatus>,
+    severities: &[String],
+) -> Vec<&'a Finding> {
+    findings
+        .iter()
+        .filter(|finding| {
+            severities
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(finding.severity.trim()))
+        })
+        .filter(|finding| status(statuses, finding) != "resolved")
+        .collect()
+}
+
+pub fn find<'a>(findings: &'a [Finding], id: &str) -> Result<&'a Finding> {
+    let matches: Vec<&Finding> = findings
+        .iter()
+        .filter(|finding| finding.id.starts_with(id))
+        .collect();
+    match matches.as_slice() {
+        [finding] => Ok(finding),
+        [] => Err(ErrorKind::NotFound.err(tr!(
+            "finding が見つかりません: {id}",
+            "finding not found: {id}"
+        ))),
+        _ => Err(anyhow!(tr!(
+            "finding id が曖昧です: {id}",
+            "ambiguous finding id: {id}"
+        ))),
+    }
+}
+
+fn finding_id(severity: &str, file: &str, rationale: &str) -> String {
+    let mut hasher = Hasher::new();
+    for part in [severity, file, rationale] {
+        hasher.update(part.trim().as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex()[..FINDING_ID_LEN].to_string()
+}
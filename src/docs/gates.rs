@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::core::config::{CheckConfig, SubprojectConfig};
+use crate::tr;
+
+const UNCLAIMED_GATE: &str = "default";
+
+pub struct SpecGate {
+    pub name: Option<String>,
+    code: GlobSet,
+    claimed: Option<GlobSet>,
+    specs: String,
+    changes: String,
+    artifacts: Vec<String>,
+}
+
+pub enum GateOutcome {
+    NoCode,
+    Passed,
+    Failed(String),
+}
+
+impl SpecGate {
+    pub fn from_config(config: &CheckConfig) -> Result<Vec<Self>> {
+        if config.subprojects.is_empty() {
+            return Ok(vec![Self::new(None, &SubprojectConfig::default())?]);
+        }
+        let mut gates = config
+            .subprojects
+            .iter()
+            .map(|(name, subproject)| Self::new(Some(name.clone()), subproject))
+            .collect::<Result<Vec<_>>>()?;
+        let mut claimed = GlobSetBuilder::new();
+        for (name, subproject) in &config.subprojects {
+            for pattern in &subproject.paths {
+                claimed.add(
+                    Glob::new(pattern).with_context(|| {
+                        format!("invalid check.subprojects.{name} glob {pattern}")
+                    })?,
+                );
+            }
+        }
+        let mut unclaimed = Self::new(
+            Some(UNCLAIMED_GATE.to_string()),
+            &SubprojectConfig::default(),
+        )?;
+        unclaimed.claimed = Some(
+            claimed
+                .build()
+                .with_context(|| "build check.subprojects globs")?,
+        );
+        gates.push(unclaimed);
+        Ok(gates)
+    }
+
+    fn new(name: Option<String>, config: &SubprojectConfig) -> Result<Self> {
+        let label = name.as_deref().unwrap_or("default");
+        if config.paths.is_empty() {
+            return Err(anyhow!(tr!(
+                "check.subprojects.{label}.paths が空です",
+                "check.subprojects.{label}.paths is empty"
+            )));
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &config.paths {
+            builder
+                .add(Glob::new(pattern).with_context(|| {
+                    format!("invalid check.subprojects.{label} glob {pattern}")
+                })?);
+        }
+        Ok(Self {
+            code: builder
+                .build()
+                .with_context(|| format!("build check.subprojects.{label} globs"))?,
+            claimed: None,
+            specs: config.specs.trim_end_matches('/').to_string(),
+            changes: config.changes.trim_end_matches('/').to_string(),
+            artifacts: config.artifacts.clone(),
+            name,
+        })
+    }
+
+    pub fn evaluate(&self, changed: &[String]) -> GateOutcome {
+        let touches_code = changed.iter().any(|path| {
+            self.code.is_match(path)
+                && !self
+                    .claimed
+                    .as_ref()
+                    .is_some_and(|claimed| claimed.is_match(path))
+        });
+        if !touches_code {
+            return GateOutcome::NoCode;
+        }
+        let specs_prefix = format!("{}/", self.specs);
+        let specs_updated = changed
+            .iter()
+            .any(|path| path.starts_with(&specs_prefix) && path.ends_with(".md"));
+        if !specs_updated {
+            return GateOutcome::Failed(tr!(
+                "code変更には {}/<spec>.md の更新が必要です",
+                "code changes require an update to {}/<spec>.md",
+                self.specs
+            ));
+        }
+        if !required_artifacts(changed, &self.changes, &self.artifacts) {
+            return GateOutcome::Failed(tr!(
+                "code変更には {}/<id>_<name>/{} が必要です",
+                "code changes require {}/<id>_<name>/{}",
+                self.changes,
+                self.artifacts.join(", ")
+            ));
+        }
+        GateOutcome::Passed
+    }
+}
+
+fn required_artifacts(changed: &[String], changes_dir: &str, artifacts: &[String]) -> bool {
+    if artifacts.is_empty() {
+        return true;
+    }
+    let prefix = format!("{changes_dir}/");
+    let mut by_change: HashMap<&str, Vec<bool>> = HashMap::new();
+    for path in changed {
+        let Some((change_dir, rest)) = path
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            continue;
+        };
+        let found = by_change
+            .entry(change_dir)
+            .or_insert_with(|| vec![false; artifacts.len()]);
+        for (idx, artifact) in artifacts.iter().enumerate() {
+            if rest == artifact || rest.ends_with(&format!("/{artifact}")) {
+                found[idx] = true;
+            }
+        }
+    }
+    by_change
+        .values()
+        .any(|found| found.iter().all(|found| *found))
+}
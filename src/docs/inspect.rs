@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use serde_json::Value;
+
+use crate::core::state::FindingStatus;
+use crate::docs::findings::{self, Finding};
+use crate::docs::tasks::{self, TaskList};
+use crate::quality::metrics::{MetricsFile, VariantMetrics};
+use crate::tr;
+
+pub const ARTIFACTS: [&str; 5] = ["digest", "review", "tasks", "selection", "metrics"];
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+pub struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    pub fn detect() -> Self {
+        Self {
+            color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity(&self, severity: &str) -> String {
+        let style = match severity.trim().to_ascii_lowercase().as_str() {
+            "critical" | "high" => RED,
+            "medium" => YELLOW,
+            "low" => CYAN,
+            _ => DIM,
+        };
+        self.paint(style, severity)
+    }
+
+    fn status(&self, status: &str) -> String {
+        let style = match status {
+            "resolved" => GREEN,
+            "acknowledged" => CYAN,
+            _ => YELLOW,
+        };
+        self.paint(style, status)
+    }
+
+    fn pass(&self, ok: bool, ok_label: &str, ng_label: &str) -> String {
+        if ok {
+            self.paint(GREEN, ok_label)
+        } else {
+            self.paint(RED, ng_label)
+        }
+    }
+}
+
+pub fn render_markdown(painter: &Painter, contents: &str) -> String {
+    let mut out = String::new();
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        let styled = if trimmed.starts_with('#') {
+            painter.paint(BOLD, line)
+        } else if trimmed.starts_with("- [x]") || trimmed.starts_with("- [X]") {
+            painter.paint(GREEN, line)
+        } else if trimmed.starts_with("- [ ]") {
+            painter.paint(YELLOW, line)
+        } else if let Some((before, after)) = line.split_once("verdict=") {
+            let (verdict, rest) = after.split_at(after.find(',').unwrap_or(after.len()));
+            format!(
+                "{before}verdict={}{rest}",
+                painter.pass(verdict == "ok", verdict, verdict)
+            )
+        } else {
+            line.to_string()
+        };
+        out.push_str(&styled);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn review_appendix(contents: &str) -> &str {
+    let trimmed = contents.trim_start();
+    let mut stream = serde_json::Deserializer::from_str(trimmed).into_iter::<Value>();
+    match stream.next() {
+        Some(Ok(_)) => trimmed[stream.byte_offset()..].trim(),
+        _ => contents.trim(),
+    }
+}
+
+pub fn render_findings(
+    painter: &Painter,
+    found: &[Finding],
+    statuses: &BTreeMap<String, FindingStatus>,
+) -> String {
+    if found.is_empty() {
+        return format!("{}\n", tr!("findings はありません", "no findings"));
+    }
+    let mut by_severity: BTreeMap<String, usize> = BTreeMap::new();
+    for finding in found {
+        *by_severity
+            .entry(finding.severity.trim().to_ascii_lowercase())
+            .or_default() += 1;
+    }
+    let open = found
+        .iter()
+        .filter(|finding| findings::status(statuses, finding) == "open")
+        .count();
+    let counts: Vec<String> = by_severity
+        .iter()
+        .map(|(severity, count)| format!("{} {count}", painter.severity(severity)))
+        .collect();
+    let mut out = painter.paint(BOLD, &format!("## Findings ({}, {open} open)", found.len()));
+    out.push_str(&format!("\n\n{}\n\n", counts.join(", ")));
+    for finding in found {
+        let status = findings::status(statuses, finding);
+        out.push_str(&format!(
+            "{} {} [{}] {}\n",
+            painter.paint(DIM, &finding.id),
+            painter.severity(&finding.severity),
+            painter.status(status),
+            finding.file
+        ));
+        if let Some(requirement) = &finding.requirement {
+            out.push_str(&format!("    requirement: {requirement}\n"));
+        }
+        for (label, text) in [("", &finding.rationale), ("-> ", &finding.suggestion)] {
+            let text = text.trim();
+            if !text.is_empty() {
+                out.push_str(&format!("    {label}{}\n", text.replace('\n', "\n    ")));
+            }
+        }
+        if let Some(entry) = statuses.get(&finding.id) {
+            let note = entry
+                .note
+                .as_deref()
+                .map(|note| format!(": {note}"))
+                .unwrap_or_default();
+            out.push_str(&painter.paint(
+                DIM,
+                &format!("    {} by {} at {}{note}", entry.status, entry.by, entry.at),
+            ));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+pub fn render_tasks(painter: &Painter, contents: &str) -> String {
+    let Ok(list) = tasks::parse_tasks(contents) else {
+        let total = contents.matches("- [").count();
+        let done = contents.matches("- [x]").count() + contents.matches("- [X]").count();
+        let mut out = painter.paint(BOLD, &format!("## Tasks ({done}/{total} done)"));
+        out.push_str("\n\n");
+        out.push_str(&render_markdown(painter, contents));
+        return out;
+    };
+    render_task_list(painter, &list)
+}
+
+fn render_task_list(painter: &Painter, list: &TaskList) -> String {
+    let mut out = painter.paint(BOLD, &format!("## Tasks (0/{} done)", list.tasks.len()));
+    out.push_str("\n\n");
+    for task in &list.tasks {
+        out.push_str(&format!(
+            "{} {} {}\n",
+            painter.paint(YELLOW, "[ ]"),
+            painter.paint(BOLD, &task.id),
+            task.summary
+        ));
+        if !task.deps.is_empty() {
+            out.push_str(&format!("    deps: {}\n", task.deps.join(", ")));
+        }
+        if !task.files.is_empty() {
+            out.push_str(&format!("    files: {}\n", task.files.join(", ")));
+        }
+        for (label, items) in [
+            ("acceptance", &task.acceptance_criteria),
+            ("tests", &task.tests),
+        ] {
+            if !items.is_empty() {
+                out.push_str(&format!("    {label}:\n"));
+                for item in items {
+                    out.push_str(&format!("      - {item}\n"));
+                }
+            }
+        }
+    }
+    out
+}
+
+pub fn render_metrics(painter: &Painter, metrics: &MetricsFile, selected: Option<&str>) -> String {
+    let headers = [
+        "agent",
+        "build",
+        "tests",
+        "coverage",
+        "changed",
+        "advisories",
+        "mutation",
+        "measured_at",
+    ];
+    let rows: Vec<[String; 8]> = metrics
+        .variants
+        .iter()
+        .map(|metric| metric_row(metric, selected))
+        .collect();
+    let mut widths = headers.map(|header| header.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = painter.paint(
+        BOLD,
+        &format!("## Metrics (updated {})", metrics.updated_at),
+    );
+    out.push_str("\n\n");
+    let header: Vec<String> = headers
+        .iter()
+        .zip(widths)
+        .map(|(header, width)| format!("{header:<width$}"))
+        .collect();
+    out.push_str(&painter.paint(BOLD, header.join("  ").trim_end()));
+    out.push('\n');
+    for (row, metric) in rows.iter().zip(&metrics.variants) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(idx, (cell, width))| {
+                let padded = format!("{cell:<width$}");
+                match idx {
+                    1 => painter.pass(metric.build_passed, &padded, &padded),
+                    2 => painter.pass(metric.build_passed && metric.tests_passed, &padded, &padded),
+                    5 if !metric.new_advisories.is_empty() => painter.paint(RED, &padded),
+                    _ => padded,
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    if metrics.variants.is_empty() {
+        out.push_str(&format!(
+            "{}\n",
+            tr!("計測結果はありません", "no measurements")
+        ));
+    }
+    for metric in &metrics.variants {
+        if let Some(environment) = &metric.environment {
+            out.push_str(&painter.paint(
+                DIM,
+                &format!("\n{}: {}", metric.agent, environment.summary()),
+            ));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+fn metric_row(metric: &VariantMetrics, selected: Option<&str>) -> [String; 8] {
+    let percent = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{v:.1}%"));
+    let marker = if selected == Some(metric.agent.as_str()) {
+        "*"
+    } else {
+        ""
+    };
+    let tests = match metric.test_counts {
+        _ if !metric.build_passed => "-".to_string(),
+        Some(counts) => format!("{}/{}", counts.passed, counts.passed + counts.failed),
+        None if metric.tests_passed => "pass".to_string(),
+        None => "fail".to_string(),
+    };
+    [
+        format!("{}{marker}", metric.agent),
+        if metric.build_passed { "ok" } else { "failed" }.to_string(),
+        tests,
+        percent(metric.coverage_percent),
+        percent(metric.changed_line_coverage_percent),
+        metric.new_advisories.len().to_string(),
+        percent(
+            metric
+                .mutants
+                .as_ref()
+                .and_then(|mutants| mutants.score_percent),
+        ),
+        metric.measured_at.clone(),
+    ]
+}
+
+pub fn render_selection(
+    painter: &Painter,
+    selected: Option<&str>,
+    rationale: Option<&str>,
+    contents: &str,
+) -> String {
+    let mut out = match selected {
+        Some(agent) => format!("selected: {}\n", painter.paint(GREEN, agent)),
+        None => format!("selected: {}\n", painter.paint(DIM, "-")),
+    };
+    if let Some(rationale) = rationale.filter(|r| !r.trim().is_empty()) {
+        out.push_str(&format!("rationale: {}\n", rationale.trim()));
+    }
+    out.push('\n');
+    out.push_str(&render_markdown(painter, contents));
+    out
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::docs::templates::{
+    render_prompt_plans, render_prompt_review, render_prompt_select, render_prompt_tasks,
+    render_skill_md,
+};
+use crate::util::{write_file, write_string_atomic};
+
+const MANIFEST_PATH: &str = "codex-sdd/installed.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetStatus {
+    Missing,
+    Current,
+    Outdated,
+    Modified,
+}
+
+impl AssetStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            AssetStatus::Missing => "missing",
+            AssetStatus::Current => "current",
+            AssetStatus::Outdated => "outdated",
+            AssetStatus::Modified => "modified",
+        }
+    }
+
+    pub fn should_write(self, update: bool, force: bool) -> bool {
+        match self {
+            AssetStatus::Missing => true,
+            AssetStatus::Current => false,
+            AssetStatus::Outdated => update || force,
+            AssetStatus::Modified => force,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub rel_path: &'static str,
+    pub contents: String,
+    pub status: AssetStatus,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+pub fn assets() -> Vec<(&'static str, String)> {
+    vec![
+        ("prompts/plans.md", render_prompt_plans()),
+        ("prompts/review.md", render_prompt_review()),
+        ("prompts/tasks.md", render_prompt_tasks()),
+        ("prompts/select.md", render_prompt_select()),
+        ("skills/codex-sdd/SKILL.md", render_skill_md()),
+    ]
+}
+
+pub fn inspect(codex_home: &Path) -> Result<Vec<Asset>> {
+    let manifest = load_manifest(codex_home)?;
+    assets()
+        .into_iter()
+        .map(|(rel_path, contents)| {
+            let path = codex_home.join(rel_path);
+            let status = if !path.exists() {
+                AssetStatus::Missing
+            } else {
+                let existing = fs::read_to_string(&path)
+                    .with_context(|| format!("read {}", path.display()))?;
+                if existing == contents {
+                    AssetStatus::Current
+                } else if manifest.files.get(rel_path) == Some(&hash(&existing)) {
+                    AssetStatus::Outdated
+                } else {
+                    AssetStatus::Modified
+                }
+            };
+            Ok(Asset {
+                rel_path,
+                contents,
+                status,
+            })
+        })
+        .collect()
+}
+
+pub fn install(
+    codex_home: &Path,
+    assets: &[Asset],
+    update: bool,
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut manifest = load_manifest(codex_home)?;
+    let mut written = Vec::new();
+    for asset in assets {
+        let write = asset.status.should_write(update, force);
+        let path = codex_home.join(asset.rel_path);
+        if write {
+            write_file(&path, &asset.contents)?;
+            written.push(path);
+        }
+        if write || asset.status == AssetStatus::Current {
+            manifest
+                .files
+                .insert(asset.rel_path.to_string(), hash(&asset.contents));
+        }
+    }
+    let manifest_path = codex_home.join(MANIFEST_PATH);
+    let data =
+        serde_json::to_string_pretty(&manifest).with_context(|| "serialize install manifest")?;
+    write_string_atomic(&manifest_path, &data)?;
+    Ok(written)
+}
+
+fn load_manifest(codex_home: &Path) -> Result<Manifest> {
+    let path = codex_home.join(MANIFEST_PATH);
+    if !path.exists() {
+        return Ok(Manifest::default());
+    }
+    let data = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parse {}", path.display()))
+}
+
+fn hash(contents: &str) -> String {
+    blake3::hash(contents.as_bytes()).to_hex().to_string()
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::index::{shard_hash, FileEntry};
+use crate::codex::exec::output_paths;
+use crate::codex::preflight;
+use crate::util::{now_rfc3339, write_string_atomic};
+
+pub const MANIFEST_FILE: &str = "plan_manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanManifest {
+    pub change_id: String,
+    pub command: String,
+    pub generated_at: String,
+    pub agents: Option<String>,
+    pub agents_rationale: Option<String>,
+    pub index_hash: Option<String>,
+    pub total_files: usize,
+    pub total_tokens: usize,
+    pub readers_secs: f64,
+    pub duration_secs: f64,
+    pub shards: Vec<ShardEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardEntry {
+    pub name: String,
+    pub hash: String,
+    pub status: String,
+    pub tokens: usize,
+    pub duration_secs: Option<f64>,
+    pub output: Option<String>,
+    pub files: Vec<String>,
+}
+
+pub struct ManifestRun<'a> {
+    pub change_id: &'a str,
+    pub command: &'a str,
+    pub shards: &'a [Vec<FileEntry>],
+    pub runs: &'a BTreeMap<usize, Option<f64>>,
+    pub readers_secs: f64,
+    pub duration_secs: f64,
+}
+
+impl PlanManifest {
+    pub fn build(repo_root: &Path, runs_dir: &Path, run: &ManifestRun) -> Self {
+        let shards: Vec<ShardEntry> = run
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| {
+                let name = format!("reader_{idx}");
+                let (status, duration_secs) = match run.runs.get(&idx) {
+                    _ if shard.is_empty() => ("empty", None),
+                    Some(Some(secs)) => ("regenerated", Some(*secs)),
+                    Some(None) => ("cached", None),
+                    None => ("reused", None),
+                };
+                let output = Some(output_paths(runs_dir, run.change_id, &name).0)
+                    .filter(|path| !shard.is_empty() && path.exists())
+                    .map(|path| {
+                        path.strip_prefix(repo_root)
+                            .map_or(path.clone(), Path::to_path_buf)
+                            .display()
+                            .to_string()
+                    });
+                ShardEntry {
+                    hash: shard_hash(shard),
+                    status: status.to_string(),
+                    tokens: if shard.is_empty() {
+                        0
+                    } else {
+                        preflight::estimate_shard(shard)
+                    },
+                    duration_secs,
+                    output,
+                    files: shard.iter().map(|entry| entry.path.clone()).collect(),
+                    name,
+                }
+            })
+            .collect();
+        Self {
+            change_id: run.change_id.to_string(),
+            command: run.command.to_string(),
+            generated_at: now_rfc3339(),
+            agents: None,
+            agents_rationale: None,
+            index_hash: None,
+            total_files: shards.iter().map(|shard| shard.files.len()).sum(),
+            total_tokens: shards.iter().map(|shard| shard.tokens).sum(),
+            readers_secs: run.readers_secs,
+            duration_secs: run.duration_secs,
+            shards,
+        }
+    }
+
+    pub fn save(&self, runs_dir: &Path) -> Result<PathBuf> {
+        let path = runs_dir.join(&self.change_id).join(MANIFEST_FILE);
+        let data = serde_json::to_string_pretty(self).with_context(|| "serialize plan manifest")?;
+        write_string_atomic(&path, &data)?;
+        Ok(path)
+    }
+}
@@ -1 +1,14 @@
+pub mod digest;
+pub mod facts;
+pub mod findings;
+pub mod gates;
+pub mod inspect;
+pub mod install;
+pub mod manifest;
+pub mod prompts;
+pub mod refs;
+pub mod report;
+pub mod specs;
+pub mod tasks;
 pub mod templates;
+pub mod variation;
@@ -0,0 +1,642 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use minijinja::{context, Environment, ErrorKind, Value};
+use serde::Serialize;
+
+use crate::core::paths::RepoPaths;
+use crate::core::state::{AgentVariation, ChangeState};
+use crate::docs::digest::digest_entry;
+use crate::docs::refs::list_refs;
+use crate::util::i18n::{lang, Lang};
+
+const SPEC_EXCERPT_LINES: usize = 40;
+const AGENTS_MD_MAX_LINES: usize = 200;
+const GENERIC_SPEC_WORDS: [&str; 4] = ["readme", "index", "spec", "specs"];
+
+const CONTEXT_JA: &str = r#"{% if issue %}
+## Issue
+
+{{ issue.key }}: {{ issue.title }} ({{ issue.url }})。本文は {{ change_dir }}/00_issue.md にあります。
+{% endif %}{% if refs %}
+## 参考資料
+
+必要に応じて次の資料を読んでください:
+{% for ref in refs %}- {{ ref }}
+{% endfor %}{% endif %}{% if agents_md %}
+## プロジェクト規約 (AGENTS.md)
+
+{{ agents_md }}
+{% endif %}{% if relevant_specs %}
+## 関連する spec
+{% for spec in relevant_specs %}
+### {{ spec.path }}
+
+{{ spec.excerpt }}
+{% endfor %}{% endif %}"#;
+
+const CONTEXT_EN: &str = r#"{% if issue %}
+## Issue
+
+{{ issue.key }}: {{ issue.title }} ({{ issue.url }}). The description is in {{ change_dir }}/00_issue.md.
+{% endif %}{% if refs %}
+## Reference documents
+
+Read these documents as needed:
+{% for ref in refs %}- {{ ref }}
+{% endfor %}{% endif %}{% if agents_md %}
+## Project conventions (AGENTS.md)
+
+{{ agents_md }}
+{% endif %}{% if relevant_specs %}
+## Related specs
+{% for spec in relevant_specs %}
+### {{ spec.path }}
+
+{{ spec.excerpt }}
+{% endfor %}{% endif %}"#;
+
+const READER_JA: &str = r#"# Reader
+
+change_id: {{ change_id }}
+shard: {{ shard }}
+
+対象ファイル:
+{% for file in files %}- {{ file }}
+{% endfor %}
+以下を日本語で簡潔にまとめてください:
+- 役割
+- 公開API
+- リスク
+- テスト観点
+{% include "context.md" %}
+"#;
+
+const READER_EN: &str = r#"# Reader
+
+change_id: {{ change_id }}
+shard: {{ shard }}
+
+Files:
+{% for file in files %}- {{ file }}
+{% endfor %}
+Summarize the following concisely in English:
+- Responsibilities
+- Public API
+- Risks
+- Test considerations
+{% include "context.md" %}
+"#;
+
+const REVIEW_JA: &str = r#"# Review
+
+change_id: {{ change_id }}
+
+次のドキュメントを読み、レビュー観点を整理してください:
+- {{ change_dir }}/{{ digest }}
+
+出力は JSON スキーマに沿って作成してください。
+{% if against_specs %}
+## spec 準拠レビュー
+
+変更を以下の要件と照合してください。各 finding の `requirement` には関連する要件 id (該当なしは "none") を設定し、要件ごとに `compliance` を 1 件 (status: met / partial / missing / unknown) 出力してください。
+{% for req in requirements %}
+### {{ req.id }} ({{ req.spec }}) {{ req.title }}
+
+{{ req.text }}
+{% endfor %}{% endif %}{% include "context.md" %}
+"#;
+
+const REVIEW_EN: &str = r#"# Review
+
+change_id: {{ change_id }}
+
+Read the following document and organize the review points in English:
+- {{ change_dir }}/{{ digest }}
+
+Follow the JSON schema for the output.
+{% if against_specs %}
+## Spec compliance
+
+Check the change against the requirements below. Set `requirement` on every finding to the id it relates to ("none" if it relates to none), and emit one `compliance` entry per requirement with status met / partial / missing / unknown.
+{% for req in requirements %}
+### {{ req.id }} ({{ req.spec }}) {{ req.title }}
+
+{{ req.text }}
+{% endfor %}{% endif %}{% include "context.md" %}
+"#;
+
+const REVIEW_FILES_JA: &str = r#"# Scoped Review
+
+change_id: {{ change_id }}
+
+次のファイルに絞ってレビューしてください ({{ patterns | join(", ") }}):
+{% for file in files %}- {{ file }}
+{% endfor %}{% if readers %}
+各ファイルの要約は reader の出力にあります:
+{% for reader in readers %}- {{ reader }}
+{% endfor %}{% endif %}
+ファイルの中身を読み、finding は上記のファイルに関するものだけを挙げてください。
+出力は JSON スキーマに沿って作成してください。
+{% include "context.md" %}
+"#;
+
+const REVIEW_FILES_EN: &str = r#"# Scoped Review
+
+change_id: {{ change_id }}
+
+Review only the following files ({{ patterns | join(", ") }}) in English:
+{% for file in files %}- {{ file }}
+{% endfor %}{% if readers %}
+The reader summaries covering them are:
+{% for reader in readers %}- {{ reader }}
+{% endfor %}{% endif %}
+Read the file contents and report findings about these files only.
+Follow the JSON schema for the output.
+{% include "context.md" %}
+"#;
+
+const TASKS_JA: &str = r#"# Tasks
+
+change_id: {{ change_id }}
+
+次のドキュメントを読み、実装タスクを整理してください:
+- {{ change_dir }}/{{ digest }}
+- {{ change_dir }}/20_review.md
+
+出力は JSON スキーマに沿って作成してください。
+{% include "context.md" %}
+"#;
+
+const TASKS_EN: &str = r#"# Tasks
+
+change_id: {{ change_id }}
+
+Read the following documents and organize the implementation tasks in English:
+- {{ change_dir }}/{{ digest }}
+- {{ change_dir }}/20_review.md
+
+Follow the JSON schema for the output.
+{% include "context.md" %}
+"#;
+
+const TASKS_SPLIT_JA: &str = r#"# Tasks Split
+
+change_id: {{ change_id }}
+
+次の task は 1 回の作業には大きすぎます。それぞれを、順に実装できる 2 件以上の小さなサブタスクに分割してください。全体の task 一覧は {{ change_dir }}/40_tasks.md にあります。
+{% for task in tasks %}
+### {{ task.id }}: {{ task.summary }}
+
+{% if task.files %}- files: {{ task.files | join(", ") }}
+{% endif %}{% for c in task.acceptance_criteria %}- acceptance: {{ c }}
+{% endfor %}{% for t in task.tests %}- test: {{ t }}
+{% endfor %}{% if task.deps %}- deps: {{ task.deps | join(", ") }}
+{% endif %}{% endfor %}
+- サブタスクの id は `<親 id>.1`, `<親 id>.2`, ... としてください。
+- 親の files / acceptance_criteria / tests をサブタスクに漏れなく振り分けてください。
+- `deps` には同じ親のサブタスクか、既存の task の id を指定してください。同じ親のサブタスクに依存しないサブタスクは親の deps を引き継ぎます。
+- 分割対象の task のサブタスクだけを出力してください。
+
+出力は JSON スキーマに沿って作成してください。
+{% include "context.md" %}
+"#;
+
+const TASKS_SPLIT_EN: &str = r#"# Tasks Split
+
+change_id: {{ change_id }}
+
+The tasks below are too large for one unit of work. Split each into two or more smaller subtasks that can be implemented in order. The full task list is in {{ change_dir }}/40_tasks.md.
+{% for task in tasks %}
+### {{ task.id }}: {{ task.summary }}
+
+{% if task.files %}- files: {{ task.files | join(", ") }}
+{% endif %}{% for c in task.acceptance_criteria %}- acceptance: {{ c }}
+{% endfor %}{% for t in task.tests %}- test: {{ t }}
+{% endfor %}{% if task.deps %}- deps: {{ task.deps | join(", ") }}
+{% endif %}{% endfor %}
+- Name subtasks `<parent id>.1`, `<parent id>.2`, and so on.
+- Distribute every file, acceptance criterion, and test of the parent across its subtasks.
+- `deps` may name subtasks of the same parent or existing task ids. Subtasks without a sibling dependency inherit the parent's deps.
+- Output only the subtasks of the tasks being split.
+
+Follow the JSON schema for the output.
+{% include "context.md" %}
+"#;
+
+const TEST_PLAN_JA: &str = r#"# Test Plan
+
+change_id: {{ change_id }}
+agent: {{ agent }}
+
+対象ブランチのテスト計画を日本語で整理してください。
+{% if variation %}
+## この agent の方針
+
+{% if variation.instructions %}{{ variation.instructions }}
+{% endif %}{% if variation.design %}
+### 設計上の制約: {{ variation.design }}
+
+{{ variation.design_text }}
+{% endif %}{% endif %}{% include "context.md" %}
+"#;
+
+const TEST_PLAN_EN: &str = r#"# Test Plan
+
+change_id: {{ change_id }}
+agent: {{ agent }}
+
+Write the test plan for the target branch in English.
+{% if variation %}
+## Approach for this agent
+
+{% if variation.instructions %}{{ variation.instructions }}
+{% endif %}{% if variation.design %}
+### Design constraint: {{ variation.design }}
+
+{{ variation.design_text }}
+{% endif %}{% endif %}{% include "context.md" %}
+"#;
+
+const IMPLEMENT_JA: &str = r#"# Implement
+
+change_id: {{ change_id }}
+agent: {{ agent }}
+
+この worktree (ブランチ sdd/{{ change_id }}/{{ agent }}) で {{ change_dir }}/40_tasks.md のタスクを実装してください。{{ change_dir }}/20_review.md の指摘も踏まえ、変更した振る舞いのテストと docs/sdd/specs/ の spec を更新してください。
+{% if variation %}
+## この agent の方針
+
+同じタスクを他の agent も並行して実装しています。実装が似通わないよう、次の方針に従ってください。
+{% if variation.model %}
+この agent はモデル `{{ variation.model }}` で実行してください。
+{% endif %}
+{% if variation.instructions %}{{ variation.instructions }}
+{% endif %}{% if variation.design %}
+### 設計上の制約: {{ variation.design }}
+
+{{ variation.design_text }}
+{% endif %}{% endif %}{% include "context.md" %}
+"#;
+
+const IMPLEMENT_EN: &str = r#"# Implement
+
+change_id: {{ change_id }}
+agent: {{ agent }}
+
+Implement the tasks in {{ change_dir }}/40_tasks.md in this worktree (branch sdd/{{ change_id }}/{{ agent }}). Take the findings in {{ change_dir }}/20_review.md into account, and update the tests for the changed behavior and the specs under docs/sdd/specs/.
+{% if variation %}
+## Approach for this agent
+
+Other agents implement the same tasks in parallel. Follow this approach so the variants do not converge on the same solution.
+{% if variation.model %}
+Run this agent with the model `{{ variation.model }}`.
+{% endif %}
+{% if variation.instructions %}{{ variation.instructions }}
+{% endif %}{% if variation.design %}
+### Design constraint: {{ variation.design }}
+
+{{ variation.design_text }}
+{% endif %}{% endif %}{% include "context.md" %}
+"#;
+
+const REVIEW_DEBATE_JA: &str = r#"# Review Debate
+
+change_id: {{ change_id }}
+debater: {{ debater }}
+
+複数のレビュアーが独立に挙げた指摘です。変更内容 ({{ change_dir }}/{{ digest }}) と照らし、各指摘に同意するかを判断してください。自分の指摘も見直してください。先に挙がった指摘と重複するものには同意しないでください。
+{% for f in findings %}
+### {{ f.id }} ({{ f.debater }})
+
+- severity: {{ f.finding.severity }}
+- file: {{ f.finding.file }}
+{% if f.finding.requirement %}- requirement: {{ f.finding.requirement }}
+{% endif %}{% if f.finding.rationale %}- rationale: {{ f.finding.rationale }}
+{% endif %}{% if f.finding.suggestion %}- suggestion: {{ f.finding.suggestion }}
+{% endif %}{% endfor %}
+すべての指摘について `votes` に 1 件ずつ (finding: 指摘 id, agree: true/false, reason) 出力してください。出力は JSON スキーマに沿って作成してください。
+{% include "context.md" %}
+"#;
+
+const REVIEW_DEBATE_EN: &str = r#"# Review Debate
+
+change_id: {{ change_id }}
+debater: {{ debater }}
+
+Several reviewers raised the findings below independently. Check each one against the change ({{ change_dir }}/{{ digest }}) and decide whether you agree. Reconsider your own findings as well. Disagree with a finding that duplicates an earlier one.
+{% for f in findings %}
+### {{ f.id }} ({{ f.debater }})
+
+- severity: {{ f.finding.severity }}
+- file: {{ f.finding.file }}
+{% if f.finding.requirement %}- requirement: {{ f.finding.requirement }}
+{% endif %}{% if f.finding.rationale %}- rationale: {{ f.finding.rationale }}
+{% endif %}{% if f.finding.suggestion %}- suggestion: {{ f.finding.suggestion }}
+{% endif %}{% endfor %}
+Emit one `votes` entry per finding (finding: the finding id, agree: true/false, reason). Follow the JSON schema for the output.
+{% include "context.md" %}
+"#;
+
+const SELECT_JA: &str = r#"# Select
+
+change_id: {{ change_id }}
+debater: {{ debater }}
+
+次の候補から採用すべき実装を 1 つ選んでください。各候補の worktree は {{ worktree_root }}/<agent> にあります。
+
+候補: {{ agents | join(", ") }}
+
+{{ selection }}
+{% if judgments %}
+## 他の判定
+
+他の判定者の結論です。根拠を検討し、必要なら判断を改めてください。
+{% for j in judgments %}
+- {{ j.debater }}: {{ j.choice }} ({{ j.rationale }})
+{% endfor %}{% endif %}
+`choice` には候補の agent 名を、`rationale` には理由を記載してください。出力は JSON スキーマに沿って作成してください。
+{% include "context.md" %}
+"#;
+
+const SELECT_EN: &str = r#"# Select
+
+change_id: {{ change_id }}
+debater: {{ debater }}
+
+Pick the one implementation to adopt from the candidates below. Each candidate's worktree is at {{ worktree_root }}/<agent>.
+
+Candidates: {{ agents | join(", ") }}
+
+{{ selection }}
+{% if judgments %}
+## Other judgments
+
+These are the conclusions of the other judges. Weigh their reasons and revise your decision if needed.
+{% for j in judgments %}
+- {{ j.debater }}: {{ j.choice }} ({{ j.rationale }})
+{% endfor %}{% endif %}
+Set `choice` to the candidate's agent name and `rationale` to your reasons. Follow the JSON schema for the output.
+{% include "context.md" %}
+"#;
+
+const SPIKE_JA: &str = r#"# Spike
+
+質問: {{ question }}
+
+次のファイルから調べ始め、必要なら他のファイルも読んで質問に日本語で答えてください。これは調査だけなので、ファイルは変更しないでください。
+{% for file in files %}- {{ file }}
+{% endfor %}
+- 結論を最初に短く書いてください。
+- 根拠となる箇所を `path:line` で示してください。
+- 確認できなかった点や推測は、その旨を明記してください。
+{% include "context.md" %}
+"#;
+
+const SPIKE_EN: &str = r#"# Spike
+
+Question: {{ question }}
+
+Start from the files below, read others as needed, and answer the question in English. This is an investigation only; do not modify any files.
+{% for file in files %}- {{ file }}
+{% endfor %}
+- Lead with a short conclusion.
+- Cite the supporting code as `path:line`.
+- Mark anything you could not confirm or are guessing.
+{% include "context.md" %}
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpecExcerpt {
+    pub path: String,
+    pub excerpt: String,
+}
+
+pub struct PromptRenderer {
+    env: Environment<'static>,
+    base: Value,
+    specs: Vec<SpecExcerpt>,
+    keywords: String,
+    variations: BTreeMap<String, AgentVariation>,
+    refs: Vec<PathBuf>,
+    pub change_dir: PathBuf,
+}
+
+impl PromptRenderer {
+    pub fn new(
+        paths: &RepoPaths,
+        change_id: &str,
+        change_dir: &Path,
+        change: Option<&ChangeState>,
+    ) -> Result<Self> {
+        let goal = change.and_then(|c| c.goal.as_deref()).unwrap_or(change_id);
+        let scope = change.map(|c| c.scope.as_slice()).unwrap_or_default();
+        let keywords = format!("{goal} {}", scope.join(" "));
+        let specs = spec_excerpts(&paths.docs_sdd.join("specs"))?;
+        let refs = list_refs(&paths.change_context_dir(change_dir));
+        let mut env = Environment::new();
+        env.set_keep_trailing_newline(true);
+        let templates_dir = paths.templates_dir.clone();
+        let repo_root = paths.repo_root.clone();
+        env.set_loader(move |name| load_source(&templates_dir, &repo_root, name));
+        let base = context! {
+            change_id => change_id,
+            change_dir => change_dir.display().to_string(),
+            goal => goal,
+            digest => digest_entry(change_dir),
+            issue => change.and_then(|c| c.issue.as_ref()),
+            language => match lang() {
+                Lang::Ja => "ja",
+                Lang::En => "en",
+            },
+            specs => &specs,
+            relevant_specs => relevant_specs(&specs, &keywords),
+            agents_md => agents_md(&paths.repo_root)?,
+            refs => refs.iter().map(|path| path.display().to_string()).collect::<Vec<_>>(),
+        };
+        Ok(Self {
+            env,
+            base,
+            specs,
+            keywords,
+            variations: change.map(|c| c.variations.clone()).unwrap_or_default(),
+            refs,
+            change_dir: change_dir.to_path_buf(),
+        })
+    }
+
+    pub fn variation(&self, agent: &str) -> Option<&AgentVariation> {
+        self.variations.get(agent)
+    }
+
+    pub fn refs(&self) -> &[PathBuf] {
+        &self.refs
+    }
+
+    pub fn specs(&self) -> &[SpecExcerpt] {
+        &self.specs
+    }
+
+    pub fn relevant_specs(&self, files: &[&str]) -> Vec<SpecExcerpt> {
+        relevant_specs(
+            &self.specs,
+            &format!("{} {}", self.keywords, files.join(" ")),
+        )
+    }
+
+    pub fn template_source(&self, name: &str) -> Result<String> {
+        let template = self
+            .env
+            .get_template(name)
+            .with_context(|| format!("load template {name}"))?;
+        Ok(template.source().to_string())
+    }
+
+    pub fn render(&self, name: &str, vars: Value) -> Result<String> {
+        let template = self
+            .env
+            .get_template(name)
+            .with_context(|| format!("load template {name}"))?;
+        template
+            .render(context! { ..vars, ..self.base.clone() })
+            .with_context(|| format!("render template {name}"))
+    }
+}
+
+fn builtin(name: &str) -> Option<&'static str> {
+    let ja = lang() == Lang::Ja;
+    match name {
+        "reader.md" => Some(if ja { READER_JA } else { READER_EN }),
+        "review.md" => Some(if ja { REVIEW_JA } else { REVIEW_EN }),
+        "review_files.md" => Some(if ja { REVIEW_FILES_JA } else { REVIEW_FILES_EN }),
+        "tasks.md" => Some(if ja { TASKS_JA } else { TASKS_EN }),
+        "tasks_split.md" => Some(if ja { TASKS_SPLIT_JA } else { TASKS_SPLIT_EN }),
+        "test_plan.md" => Some(if ja { TEST_PLAN_JA } else { TEST_PLAN_EN }),
+        "implement.md" => Some(if ja { IMPLEMENT_JA } else { IMPLEMENT_EN }),
+        "review_debate.md" => Some(if ja {
+            REVIEW_DEBATE_JA
+        } else {
+            REVIEW_DEBATE_EN
+        }),
+        "select.md" => Some(if ja { SELECT_JA } else { SELECT_EN }),
+        "spike.md" => Some(if ja { SPIKE_JA } else { SPIKE_EN }),
+        "context.md" => Some(if ja { CONTEXT_JA } else { CONTEXT_EN }),
+        _ => None,
+    }
+}
+
+fn load_source(
+    templates_dir: &Path,
+    repo_root: &Path,
+    name: &str,
+) -> Result<Option<String>, minijinja::Error> {
+    let rel = Path::new(name);
+    if !rel
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("template path must be relative to the repository: {name}"),
+        ));
+    }
+    let override_path = templates_dir.join(rel);
+    if override_path.is_file() {
+        return read_source(&override_path).map(Some);
+    }
+    if let Some(source) = builtin(name) {
+        return Ok(Some(source.to_string()));
+    }
+    let repo_path = repo_root.join(rel);
+    if repo_path.is_file() {
+        return read_source(&repo_path).map(Some);
+    }
+    Ok(None)
+}
+
+fn read_source(path: &Path) -> Result<String, minijinja::Error> {
+    fs::read_to_string(path).map_err(|err| {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("read {}", path.display()),
+        )
+        .with_source(err)
+    })
+}
+
+fn agents_md(repo_root: &Path) -> Result<Option<String>> {
+    let path = repo_root.join("AGENTS.md");
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    Ok(Some(
+        contents
+            .lines()
+            .take(AGENTS_MD_MAX_LINES)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    ))
+}
+
+fn relevant_specs(specs: &[SpecExcerpt], haystack: &str) -> Vec<SpecExcerpt> {
+    let words: Vec<String> = haystack
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    specs
+        .iter()
+        .filter(|spec| {
+            spec.path
+                .trim_start_matches("docs/sdd/specs/")
+                .trim_end_matches(".md")
+                .split(|c: char| !c.is_alphanumeric())
+                .map(str::to_lowercase)
+                .filter(|token| token.len() >= 3 && !GENERIC_SPEC_WORDS.contains(&token.as_str()))
+                .any(|token| words.contains(&token))
+        })
+        .cloned()
+        .collect()
+}
+
+fn spec_excerpts(specs_dir: &Path) -> Result<Vec<SpecExcerpt>> {
+    let mut paths = Vec::new();
+    collect_specs(specs_dir, &mut paths);
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents =
+                fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+            let excerpt = contents
+                .lines()
+                .take(SPEC_EXCERPT_LINES)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let rel = path.strip_prefix(specs_dir).unwrap_or(&path);
+            Ok(SpecExcerpt {
+                path: format!(
+                    "docs/sdd/specs/{}",
+                    rel.to_string_lossy().replace('\\', "/")
+                ),
+                excerpt,
+            })
+        })
+        .collect()
+}
+
+fn collect_specs(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            collect_specs(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            out.push(path);
+        }
+    }
+}
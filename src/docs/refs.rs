@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+use tracing::info;
+
+use crate::core::error::ErrorKind;
+use crate::tr;
+use crate::util::{ensure_dir, run_cmd_allow_fail, slugify};
+
+pub fn refs_dir(context_dir: &Path) -> PathBuf {
+    context_dir.join("refs")
+}
+
+pub fn list_refs(context_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(refs_dir(context_dir)) else {
+        return Vec::new();
+    };
+    let mut refs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    refs.sort();
+    refs
+}
+
+pub fn add_ref(context_dir: &Path, source: &str, name: Option<&str>) -> Result<PathBuf> {
+    let dir = refs_dir(context_dir);
+    ensure_dir(&dir)?;
+    let is_url = source.starts_with("http://") || source.starts_with("https://");
+    let file_name = match name {
+        Some(name) => name.to_string(),
+        None if is_url => url_file_name(source),
+        None => Path::new(source)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("invalid reference path: {source}"))?,
+    };
+    if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+        return Err(anyhow!(tr!(
+            "参照ファイル名が不正です: {file_name}",
+            "invalid reference file name: {file_name}"
+        )));
+    }
+    let target = dir.join(&file_name);
+    if is_url {
+        download(source, &target)?;
+    } else {
+        if !Path::new(source).is_file() {
+            return Err(ErrorKind::NotFound.err(tr!(
+                "参照ファイルが見つかりません: {source}",
+                "reference file not found: {source}"
+            )));
+        }
+        fs::copy(source, &target).with_context(|| format!("copy {source}"))?;
+    }
+    if !is_pdf(&target) {
+        return Ok(target);
+    }
+    let text_path = target.with_extension("txt");
+    let converted = pdf_to_text(&target, &text_path);
+    fs::remove_file(&target).with_context(|| format!("remove {}", target.display()))?;
+    converted?;
+    Ok(text_path)
+}
+
+fn download(url: &str, target: &Path) -> Result<()> {
+    info!("download reference {url}");
+    let mut cmd = Command::new("curl");
+    cmd.args([
+        "--fail",
+        "--silent",
+        "--show-error",
+        "--location",
+        "--output",
+    ])
+    .arg(target)
+    .arg(url);
+    let output = run_cmd_allow_fail(cmd).map_err(|_| {
+        ErrorKind::NotFound.err(tr!(
+            "curl が見つかりません。URL の参照には curl が必要です",
+            "curl not found; it is required to add a URL reference"
+        ))
+    })?;
+    if !output.status.success() {
+        let _ = fs::remove_file(target);
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{url} を取得できません: {}",
+            "cannot fetch {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+fn pdf_to_text(pdf: &Path, text_path: &Path) -> Result<()> {
+    let mut cmd = Command::new("pdftotext");
+    cmd.args(["-layout", "-enc", "UTF-8"])
+        .arg(pdf)
+        .arg(text_path);
+    let output = run_cmd_allow_fail(cmd).map_err(|_| {
+        ErrorKind::NotFound.err(tr!(
+            "pdftotext が見つかりません。PDF の参照には poppler-utils が必要です",
+            "pdftotext not found; install poppler-utils to add PDF references"
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "pdftotext failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+fn url_file_name(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let path = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .trim_end_matches('/');
+    let last = path.rsplit('/').next().unwrap_or(path);
+    match last.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && path.contains('/') => {
+            format!("{}.{}", slugify(stem), ext.to_ascii_lowercase())
+        }
+        _ => format!("{}.txt", slugify(path)),
+    }
+}
@@ -0,0 +1,120 @@
+pub struct HtmlVariant {
+    pub agent: String,
+    pub build_passed: bool,
+    pub tests_passed: bool,
+    pub coverage_percent: Option<f64>,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    pub new_advisories: usize,
+    pub diff: String,
+}
+
+pub fn render_selection_html(
+    change_id: &str,
+    generated_at: &str,
+    variants: &[HtmlVariant],
+) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!(
+        "<title>Selection report: {}</title>\n",
+        escape_html(change_id)
+    ));
+    out.push_str(
+        r#"<style>
+body { font-family: -apple-system, "Segoe UI", sans-serif; margin: 2rem; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2rem; }
+th, td { border: 1px solid #ccc; padding: 0.4rem 0.8rem; text-align: left; }
+th { background: #f4f4f4; }
+.ok { color: #1a7f37; }
+.ng { color: #cf222e; }
+.bar { background: #eee; width: 160px; height: 0.8rem; display: inline-block; vertical-align: middle; }
+.bar > span { background: #2da44e; height: 100%; display: block; }
+pre { background: #f6f8fa; padding: 1rem; overflow-x: auto; font-size: 0.85rem; }
+.add { color: #1a7f37; }
+.del { color: #cf222e; }
+</style>
+</head>
+<body>
+"#,
+    );
+    out.push_str(&format!(
+        "<h1>Selection report: {}</h1>\n<p>generated_at: {}</p>\n",
+        escape_html(change_id),
+        escape_html(generated_at)
+    ));
+    out.push_str("<table>\n<tr><th>agent</th><th>build</th><th>tests</th><th>coverage</th><th>diff</th><th>new advisories</th></tr>\n");
+    for v in variants {
+        let coverage = match v.coverage_percent {
+            Some(percent) => format!(
+                "<span class=\"bar\"><span style=\"width: {:.1}%\"></span></span> {percent:.1}%",
+                percent.clamp(0.0, 100.0)
+            ),
+            None => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><span class=\"add\">+{}</span> <span class=\"del\">-{}</span></td><td>{}</td></tr>\n",
+            escape_html(&v.agent),
+            status_cell(v.build_passed, "ok", "failed"),
+            status_cell(v.tests_passed, "pass", "fail"),
+            coverage,
+            v.lines_added,
+            v.lines_removed,
+            v.new_advisories
+        ));
+    }
+    out.push_str("</table>\n<h2>Diffs</h2>\n");
+    for v in variants {
+        out.push_str(&format!(
+            "<details>\n<summary>{} (+{} -{})</summary>\n<pre>",
+            escape_html(&v.agent),
+            v.lines_added,
+            v.lines_removed
+        ));
+        for line in v.diff.lines() {
+            let class = if line.starts_with('+') && !line.starts_with("+++") {
+                Some("add")
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                Some("del")
+            } else {
+                None
+            };
+            match class {
+                Some(class) => out.push_str(&format!(
+                    "<span class=\"{class}\">{}</span>\n",
+                    escape_html(line)
+                )),
+                None => {
+                    out.push_str(&escape_html(line));
+                    out.push('\n');
+                }
+            }
+        }
+        out.push_str("</pre>\n</details>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn status_cell(ok: bool, ok_label: &str, ng_label: &str) -> String {
+    if ok {
+        format!("<span class=\"ok\">{ok_label}</span>")
+    } else {
+        format!("<span class=\"ng\">{ng_label}</span>")
+    }
+}
+
+fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::util::slugify;
+
+const REQUIREMENT_TEXT_LINES: usize = 20;
+const COMPLIANCE_STATUSES: [&str; 4] = ["met", "partial", "missing", "unknown"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    pub id: String,
+    pub spec: String,
+    pub title: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceEntry {
+    pub requirement: String,
+    pub spec: String,
+    pub status: String,
+    #[serde(default)]
+    pub findings: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewOutput {
+    #[serde(default)]
+    findings: Vec<ReviewFinding>,
+    #[serde(default)]
+    compliance: Vec<ComplianceStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewFinding {
+    #[serde(default)]
+    severity: String,
+    #[serde(default)]
+    file: String,
+    #[serde(default)]
+    requirement: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComplianceStatus {
+    requirement: String,
+    status: String,
+    #[serde(default)]
+    notes: String,
+}
+
+pub fn load_requirements(repo_root: &Path, spec_paths: &[String]) -> Result<Vec<Requirement>> {
+    let mut requirements = Vec::new();
+    for spec in spec_paths {
+        let path = repo_root.join(spec);
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        requirements.extend(extract_requirements(spec, &contents));
+    }
+    Ok(requirements)
+}
+
+pub fn extract_requirements(spec: &str, contents: &str) -> Vec<Requirement> {
+    let stem = Path::new(spec)
+        .with_extension("")
+        .to_string_lossy()
+        .trim_start_matches("docs/sdd/specs/")
+        .to_string();
+    let mut requirements: Vec<Requirement> = Vec::new();
+    let mut current: Option<(usize, Requirement)> = None;
+    for line in contents.lines() {
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let is_heading = level > 0 && line[level..].starts_with(' ');
+        if is_heading {
+            if let Some((open_level, _)) = &current {
+                if level <= *open_level {
+                    requirements.extend(current.take().map(|(_, req)| req));
+                }
+            }
+            if let Some((id, title)) = requirement_heading(&stem, line[level..].trim()) {
+                requirements.extend(current.take().map(|(_, req)| req));
+                current = Some((
+                    level,
+                    Requirement {
+                        id,
+                        spec: spec.to_string(),
+                        title,
+                        text: String::new(),
+                    },
+                ));
+                continue;
+            }
+        }
+        if let Some((_, req)) = &mut current {
+            if req.text.lines().count() < REQUIREMENT_TEXT_LINES
+                && (!req.text.is_empty() || !line.trim().is_empty())
+            {
+                req.text.push_str(line);
+                req.text.push('\n');
+            }
+        }
+    }
+    requirements.extend(current.map(|(_, req)| req));
+    for req in &mut requirements {
+        req.text = req.text.trim_end().to_string();
+    }
+    requirements
+}
+
+fn requirement_heading(stem: &str, heading: &str) -> Option<(String, String)> {
+    if let Some(title) = heading.strip_prefix("Requirement:") {
+        let title = title.trim().to_string();
+        return Some((format!("{stem}#{}", slugify(&title)), title));
+    }
+    let (token, rest) = heading.split_once(' ').unwrap_or((heading, ""));
+    let token = token.trim_end_matches(':');
+    let (prefix, number) = token.split_once('-')?;
+    let is_id = !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && prefix.starts_with(|c: char| c.is_ascii_uppercase())
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit());
+    is_id.then(|| (token.to_string(), rest.trim().to_string()))
+}
+
+pub fn build_compliance(
+    requirements: &[Requirement],
+    review_json: &str,
+) -> Result<Vec<ComplianceEntry>> {
+    let output: ReviewOutput =
+        serde_json::from_str(review_json.trim()).with_context(|| "parse review output")?;
+    let mut findings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for finding in &output.findings {
+        let requirement = finding
+            .requirement
+            .clone()
+            .filter(|id| requirements.iter().any(|req| &req.id == id))
+            .unwrap_or_else(|| "none".to_string());
+        findings
+            .entry(requirement)
+            .or_default()
+            .push(format!("{}: {}", finding.severity, finding.file));
+    }
+    let mut entries: Vec<ComplianceEntry> = requirements
+        .iter()
+        .map(|req| {
+            let reported = output
+                .compliance
+                .iter()
+                .find(|status| status.requirement == req.id);
+            let status = reported
+                .map(|status| status.status.to_lowercase())
+                .filter(|status| COMPLIANCE_STATUSES.contains(&status.as_str()))
+                .unwrap_or_else(|| "unknown".to_string());
+            ComplianceEntry {
+                requirement: req.id.clone(),
+                spec: req.spec.clone(),
+                status,
+                findings: findings.remove(&req.id).unwrap_or_default(),
+                notes: reported
+                    .map(|status| status.notes.clone())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+    if let Some(unmapped) = findings.remove("none") {
+        entries.push(ComplianceEntry {
+            requirement: "none".to_string(),
+            spec: String::new(),
+            status: "unmapped".to_string(),
+            findings: unmapped,
+            notes: String::new(),
+        });
+    }
+    Ok(entries)
+}
+
+pub fn render_compliance(entries: &[ComplianceEntry]) -> String {
+    let mut out = String::from("## Compliance\n\n| requirement | spec | status | findings | notes |\n|---|---|---|---|---|\n");
+    for entry in entries {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            entry.requirement,
+            entry.spec,
+            entry.status,
+            if entry.findings.is_empty() {
+                "-".to_string()
+            } else {
+                entry.findings.join("<br>")
+            },
+            entry.notes.replace('|', "\\|").replace('\n', " ")
+        ));
+    }
+    out
+}
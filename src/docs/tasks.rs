@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::ErrorKind;
+use crate::tr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskList {
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub summary: String,
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub acceptance_criteria: Vec<String>,
+    #[serde(default)]
+    pub tests: Vec<String>,
+    #[serde(default)]
+    pub deps: Vec<String>,
+}
+
+pub fn parse_tasks(contents: &str) -> Result<TaskList> {
+    serde_json::from_str(contents.trim()).with_context(|| "parse tasks output")
+}
+
+pub fn select<'a>(list: &'a TaskList, ids: &[String]) -> Result<Vec<&'a Task>> {
+    ids.iter()
+        .map(|id| {
+            list.tasks
+                .iter()
+                .find(|task| &task.id == id)
+                .ok_or_else(|| {
+                    ErrorKind::NotFound
+                        .err(tr!("task が見つかりません: {id}", "task not found: {id}"))
+                })
+        })
+        .collect()
+}
+
+pub fn merge_split(list: &TaskList, parents: &[String], output: &TaskList) -> Result<TaskList> {
+    let mut children: BTreeMap<&str, Vec<Task>> = BTreeMap::new();
+    let mut new_ids = BTreeSet::new();
+    for task in &output.tasks {
+        let parent = parents
+            .iter()
+            .filter(|parent| {
+                task.id
+                    .strip_prefix(parent.as_str())
+                    .is_some_and(|rest| rest.starts_with('.') && rest.len() > 1)
+            })
+            .max_by_key(|parent| parent.len())
+            .ok_or_else(|| {
+                ErrorKind::SchemaViolation.err(tr!(
+                    "サブタスク {} の id が <親 id>.<n> の形式ではありません",
+                    "subtask {} is not named <parent id>.<n>",
+                    task.id
+                ))
+            })?;
+        if !new_ids.insert(task.id.as_str()) || list.tasks.iter().any(|t| t.id == task.id) {
+            return Err(ErrorKind::SchemaViolation.err(tr!(
+                "サブタスク id が重複しています: {}",
+                "duplicate subtask id: {}",
+                task.id
+            )));
+        }
+        children
+            .entry(parent.as_str())
+            .or_default()
+            .push(task.clone());
+    }
+    for parent in parents {
+        let count = children.get(parent.as_str()).map_or(0, Vec::len);
+        if count < 2 {
+            return Err(ErrorKind::SchemaViolation.err(tr!(
+                "{parent} が 2 件以上のサブタスクに分割されていません ({count} 件)",
+                "{parent} was not split into at least two subtasks ({count})"
+            )));
+        }
+    }
+
+    let replace_deps = |deps: &[String]| -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for dep in deps {
+            let expanded = match children.get(dep.as_str()) {
+                Some(subtasks) => subtasks.iter().map(|t| t.id.clone()).collect(),
+                None => vec![dep.clone()],
+            };
+            for id in expanded {
+                if !out.contains(&id) {
+                    out.push(id);
+                }
+            }
+        }
+        out
+    };
+    let mut merged = Vec::new();
+    for task in &list.tasks {
+        match children.get(task.id.as_str()) {
+            Some(subtasks) => {
+                for subtask in subtasks {
+                    let mut subtask = subtask.clone();
+                    let mut deps: Vec<String> = subtask
+                        .deps
+                        .iter()
+                        .filter(|dep| *dep != &task.id && *dep != &subtask.id)
+                        .cloned()
+                        .collect();
+                    if !deps.iter().any(|dep| subtasks.iter().any(|s| &s.id == dep)) {
+                        deps.extend(task.deps.iter().cloned());
+                    }
+                    subtask.deps = replace_deps(&deps);
+                    merged.push(subtask);
+                }
+            }
+            None => {
+                let mut task = task.clone();
+                task.deps = replace_deps(&task.deps);
+                merged.push(task);
+            }
+        }
+    }
+
+    let known: BTreeSet<&str> = merged.iter().map(|t| t.id.as_str()).collect();
+    for task in &merged {
+        if let Some(dep) = task.deps.iter().find(|dep| !known.contains(dep.as_str())) {
+            return Err(ErrorKind::SchemaViolation.err(tr!(
+                "{} が存在しない task {dep} に依存しています",
+                "{} depends on unknown task {dep}",
+                task.id
+            )));
+        }
+    }
+    Ok(TaskList { tasks: merged })
+}
@@ -1,13 +1,31 @@
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use tracing::info;
 
+use crate::tr;
 use crate::util::{ensure_dir, write_file, write_file_if_missing};
 
-pub const PROMPT_PLANS_FILENAME: &str = "plans.md";
+pub const INIT_TEMPLATES: [&str; 4] = ["minimal", "standard", "full", "monorepo"];
+pub const REVIEW_PLACEHOLDER: &str = "# Review\n\n(auto-generated)\n";
 
-pub fn render_agents_md() -> String {
-    let contents = r#"<!-- OPENSPEC:START -->
+pub fn load_override(templates_dir: &Path, name: &str) -> Result<Option<String>> {
+    let path = templates_dir.join(name);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    info!("template override {}", path.display());
+    let contents =
+        std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    Ok(Some(contents))
+}
+
+pub struct GitignoreUpdate {
+    pub appended: Vec<String>,
+    pub hides_skills: bool,
+}
+
+const OPENSPEC_BLOCK: &str = r#"<!-- OPENSPEC:START -->
 
 # OpenSpec Instructions
 
@@ -29,7 +47,9 @@ Keep this managed block so 'openspec update' can refresh the instructions.
 
 <!-- OPENSPEC:END -->
 
-# Project Agent Instructions (codex-sdd)
+"#;
+
+const AGENTS_BODY: &str = r#"# Project Agent Instructions (codex-sdd)
 
 ## 1) Language selection
 - Start by asking the user which language to use (e.g., English / Japanese).
@@ -48,7 +68,8 @@ Keep this managed block so 'openspec update' can refresh the instructions.
 - Optional: `cargo llvm-cov` or `cargo tarpaulin` for coverage
 
 ### Setup
-1. `codex-sdd install` to create `CODEX_HOME/prompts/plans.md`.
+1. `codex-sdd install` to create the `plans`/`review`/`tasks`/`select` prompts and the
+   `codex-sdd` skill under `CODEX_HOME`.
 2. `codex-sdd init` to scaffold `docs/sdd` and `AGENTS.md`.
 
 ### End-to-end workflow
@@ -93,7 +114,146 @@ Keep this managed block so 'openspec update' can refresh the instructions.
 - `CODEX_SDD_PROMPT_FLAG`: Override the prompt flag (default: `--prompt-file`).
 - `CODEX_SDD_EXEC_ARGS`: Extra args passed to `codex exec`.
 "#;
-    contents.to_string()
+
+const MANAGED_START: &str = "<!-- CODEX-SDD:START -->";
+const MANAGED_END: &str = "<!-- CODEX-SDD:END -->";
+const LEGACY_HEADING: &str = "# Project Agent Instructions (codex-sdd)";
+const INSTALL_STEP: &str = "1. `codex-sdd install` to create the `plans`/`review`/`tasks`/`select` prompts and the\n   `codex-sdd` skill under `CODEX_HOME`.\n";
+const LEGACY_INSTALL_STEP: &str =
+    "1. `codex-sdd install` to create `CODEX_HOME/prompts/plans.md`.\n";
+
+pub struct AgentsUpdate {
+    pub contents: String,
+    pub old_block: String,
+    pub new_block: String,
+    pub adopted: bool,
+}
+
+impl AgentsUpdate {
+    pub fn changed(&self) -> bool {
+        self.old_block != self.new_block
+    }
+}
+
+fn agents_managed_block() -> String {
+    format!(
+        "{MANAGED_START}\n\n{AGENTS_BODY}\nKeep this managed block so `codex-sdd init --update-agents` can refresh the instructions.\n\n{MANAGED_END}\n"
+    )
+}
+
+pub fn render_agents_md() -> String {
+    format!("{OPENSPEC_BLOCK}{}", agents_managed_block())
+}
+
+pub fn render_agents_md_minimal() -> String {
+    agents_managed_block()
+}
+
+pub fn has_agents_block(contents: &str) -> bool {
+    contents.contains(MANAGED_START) && contents.contains(MANAGED_END)
+}
+
+pub fn update_agents_md(contents: &str) -> Result<AgentsUpdate> {
+    let new_block = agents_managed_block();
+    let (start, end, adopted) = match managed_range(contents) {
+        Some((start, end)) => (start, end, false),
+        None => match legacy_range(contents)? {
+            Some((start, end)) => (start, end, true),
+            None => (contents.len(), contents.len(), true),
+        },
+    };
+    let mut updated = contents[..start].to_string();
+    if start == contents.len() && !updated.is_empty() {
+        if !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push('\n');
+    }
+    updated.push_str(&new_block);
+    updated.push_str(&contents[end..]);
+    Ok(AgentsUpdate {
+        contents: updated,
+        old_block: contents[start..end].to_string(),
+        new_block,
+        adopted,
+    })
+}
+
+fn managed_range(contents: &str) -> Option<(usize, usize)> {
+    let start = contents.find(MANAGED_START)?;
+    let end = start + contents[start..].find(MANAGED_END)? + MANAGED_END.len();
+    let end = if contents[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    Some((start, end))
+}
+
+fn legacy_range(contents: &str) -> Result<Option<(usize, usize)>> {
+    let Some((start, _)) =
+        line_offsets(contents).find(|(_, line)| line.trim_end() == LEGACY_HEADING)
+    else {
+        return Ok(None);
+    };
+    let section: Vec<(usize, &str)> = line_offsets(&contents[start..]).collect();
+    for body in [
+        AGENTS_BODY.to_string(),
+        AGENTS_BODY.replace(INSTALL_STEP, LEGACY_INSTALL_STEP),
+    ] {
+        let lines: Vec<&str> = body.lines().collect();
+        let matches = section.len() >= lines.len()
+            && lines
+                .iter()
+                .zip(&section)
+                .all(|(expected, (_, line))| expected.trim_end() == line.trim_end());
+        if matches {
+            let (offset, line) = section[lines.len() - 1];
+            return Ok(Some((start, start + offset + line.len())));
+        }
+    }
+    Err(anyhow!(tr!(
+        "AGENTS.md の codex-sdd セクションが編集されています。内容を失わないよう、{MANAGED_START} と {MANAGED_END} の行で囲んでから再実行してください",
+        "the codex-sdd section of AGENTS.md has been edited; wrap it in {MANAGED_START} and {MANAGED_END} lines and rerun so nothing is lost"
+    )))
+}
+
+fn line_offsets(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+pub fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+    out
 }
 
 pub fn render_prompt_plans() -> String {
@@ -111,9 +271,86 @@ Please follow the steps below to understand the repository overview and update
 2. Read `docs/sdd/changes/<change_id>_.../context/repo_tree.txt` to understand the directory structure.
 3. Summarize key areas, public APIs, risks, and test considerations.
 
-Please provide a concise summary in English.
 "#;
-    contents.to_string()
+    format!("{contents}{}\n", summary_language())
+}
+
+pub fn render_prompt_review() -> String {
+    let contents = r#"---
+name: review
+argument-hint: change-id
+---
+
+# Codex SDD Review
+
+1. Run `codex-sdd review` (add `--id <change_id>` when several changes are in flight).
+2. Read `docs/sdd/changes/<change_id>_.../20_review.md` and check each finding against the code.
+3. Point out findings that are wrong or missing, and what should change in `10_repo_digest.md`.
+
+"#;
+    format!("{contents}{}\n", summary_language())
+}
+
+pub fn render_prompt_tasks() -> String {
+    let contents = r#"---
+name: tasks
+argument-hint: change-id
+---
+
+# Codex SDD Tasks
+
+1. Run `codex-sdd tasks` (add `--id <change_id>` when several changes are in flight).
+2. Read `docs/sdd/changes/<change_id>_.../40_tasks.md` alongside `20_review.md`.
+3. Check that every review finding is covered by a task and that each task is small enough
+   for one agent worktree, then suggest edits before `codex-sdd approve`.
+
+"#;
+    format!("{contents}{}\n", summary_language())
+}
+
+pub fn render_prompt_select() -> String {
+    let contents = r#"---
+name: select
+argument-hint: change-id
+---
+
+# Codex SDD Select
+
+1. Run `codex-sdd select` (add `--id <change_id>` when several changes are in flight).
+2. Read `docs/sdd/changes/<change_id>_.../80_selection.md` and the output of
+   `codex-sdd inspect metrics --id <change_id> --format json`.
+3. Compare the agent variants on tests, coverage, advisories, and diff size, and recommend one
+   with a short rationale for `codex-sdd finalize --agent <agent>`.
+
+"#;
+    format!("{contents}{}\n", summary_language())
+}
+
+pub fn render_skill_md() -> String {
+    r#"---
+name: codex-sdd
+description: Spec-driven development workflow with codex-sdd (plans, review, tasks, approve, worktrees, test-plan, select, finalize).
+---
+
+# codex-sdd
+
+Use this skill when the user wants to plan, review, or land a change with `codex-sdd`.
+
+- Artifacts live in `docs/sdd/changes/<change_id>_<name>/` (`10_repo_digest.md`, `20_review.md`,
+  `40_tasks.md`, `50_test_plan.md`, `80_selection.md`, `90_decision.md`).
+- Runtime state lives in `.codex/sdd/`; do not edit it by hand (`codex-sdd state fsck` checks it).
+- `worktrees`, `test-plan`, and `finalize` require `codex-sdd approve` first.
+- Code changes need a `docs/sdd/specs/<spec>.md` update before `finalize` and `check` pass.
+- `codex-sdd list` shows every change and which one is active.
+"#
+    .to_string()
+}
+
+fn summary_language() -> String {
+    tr!(
+        "日本語で簡潔にまとめてください。",
+        "Please provide a concise summary in English."
+    )
 }
 
 pub fn render_docs_readme() -> String {
@@ -132,10 +369,7 @@ pub fn render_change_placeholders() -> Vec<(String, String)> {
             "10_repo_digest.md".to_string(),
             "# Repo Digest\n\n(auto-generated)\n".to_string(),
         ),
-        (
-            "20_review.md".to_string(),
-            "# Review\n\n(auto-generated)\n".to_string(),
-        ),
+        ("20_review.md".to_string(), REVIEW_PLACEHOLDER.to_string()),
         (
             "40_tasks.md".to_string(),
             "# Tasks\n\n(auto-generated)\n".to_string(),
@@ -173,24 +407,191 @@ pub fn ensure_repo_scaffold(repo_root: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn ensure_agents_md(repo_root: &Path) -> Result<bool> {
-    let path = repo_root.join("AGENTS.md");
-    write_file_if_missing(&path, &render_agents_md())
+pub fn apply_init_template(
+    repo_root: &Path,
+    templates_dir: &Path,
+    template: &str,
+    packages: &[String],
+) -> Result<Vec<PathBuf>> {
+    if !INIT_TEMPLATES.contains(&template) {
+        return Err(anyhow!(tr!(
+            "未対応の init テンプレートです: {template} ({})",
+            "unknown init template: {template} ({})",
+            INIT_TEMPLATES.join("|")
+        )));
+    }
+    let mut created = Vec::new();
+    let mut create = |path: PathBuf, contents: String| -> Result<()> {
+        if write_file_if_missing(&path, &contents)? {
+            created.push(path);
+        }
+        Ok(())
+    };
+    let agents_md = match template {
+        "minimal" => render_agents_md_minimal,
+        _ => render_agents_md,
+    };
+    create(
+        repo_root.join("AGENTS.md"),
+        load_override(templates_dir, "AGENTS.md")?.unwrap_or_else(agents_md),
+    )?;
+    match template {
+        "full" => {
+            create(
+                repo_root.join("docs/sdd/specs/example.md"),
+                render_example_spec(),
+            )?;
+            create(
+                repo_root.join(".github/workflows/codex-sdd-check.yml"),
+                render_check_workflow(),
+            )?;
+        }
+        "monorepo" => {
+            let packages = if packages.is_empty() {
+                detect_packages(repo_root)
+            } else {
+                packages.to_vec()
+            };
+            for package in packages {
+                create(
+                    repo_root
+                        .join("docs/sdd/specs")
+                        .join(&package)
+                        .join("README.md"),
+                    render_package_spec_readme(&package),
+                )?;
+            }
+        }
+        _ => {}
+    }
+    Ok(created)
+}
+
+fn detect_packages(repo_root: &Path) -> Vec<String> {
+    const PACKAGE_ROOTS: [&str; 5] = ["crates", "packages", "apps", "libs", "services"];
+    const MANIFESTS: [&str; 4] = ["Cargo.toml", "package.json", "go.mod", "pyproject.toml"];
+    let mut packages = Vec::new();
+    for root in PACKAGE_ROOTS {
+        let Ok(entries) = std::fs::read_dir(repo_root.join(root)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if MANIFESTS.iter().any(|m| path.join(m).is_file()) {
+                packages.push(format!("{root}/{}", entry.file_name().to_string_lossy()));
+            }
+        }
+    }
+    packages.sort();
+    packages
 }
 
-pub fn write_prompt(codex_home: &Path) -> Result<PathBuf> {
-    let prompts_dir = codex_home.join("prompts");
-    ensure_dir(&prompts_dir)?;
-    let prompt_path = prompts_dir.join(PROMPT_PLANS_FILENAME);
-    write_file(&prompt_path, &render_prompt_plans())?;
-    Ok(prompt_path)
+fn render_example_spec() -> String {
+    r#"# Example Spec
+
+Specs describe how the system behaves today. `codex-sdd finalize` and `codex-sdd check`
+require a spec under `docs/sdd/specs/` to change whenever code changes.
+
+## Scope
+
+- What this component is responsible for.
+
+## Behavior
+
+- Inputs, outputs, and error cases.
+
+## Non-goals
+
+- What this component deliberately does not do.
+"#
+    .to_string()
+}
+
+fn render_check_workflow() -> String {
+    r#"name: codex-sdd check
+
+on:
+  pull_request:
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+        with:
+          fetch-depth: 0
+      - uses: actions/setup-node@v4
+        with:
+          node-version: 20
+      - run: npm install -g codex-sdd
+      - run: codex-sdd check --base "origin/${{ github.base_ref }}"
+"#
+    .to_string()
+}
+
+fn render_package_spec_readme(package: &str) -> String {
+    format!(
+        "# {package}\n\nSpecs for `{package}`. Add one `<spec>.md` per component next to this file.\n"
+    )
+}
+
+pub fn ensure_gitignore(repo_root: &Path) -> Result<GitignoreUpdate> {
+    let path = repo_root.join(".gitignore");
+    let existing = if path.exists() {
+        std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let rules: Vec<&str> = existing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.strip_prefix('/').unwrap_or(line))
+        .collect();
+    let has = |candidates: &[&str]| rules.iter().any(|rule| candidates.contains(rule));
+
+    let mut appended = Vec::new();
+    let whole_dir = has(&[".codex/sdd", ".codex/sdd/"]);
+    if !whole_dir && !has(&[".codex/sdd/*"]) {
+        appended.push(".codex/sdd/*");
+    }
+    if !whole_dir && !has(&["!.codex/sdd/templates", "!.codex/sdd/templates/"]) {
+        appended.push("!.codex/sdd/templates/");
+    }
+    let hides_skills = has(&[
+        ".codex",
+        ".codex/",
+        ".codex/*",
+        ".codex/skills",
+        ".codex/skills/",
+    ]);
+
+    if !appended.is_empty() {
+        let mut contents = existing.clone();
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents
+            .push_str("# codex-sdd runtime state (keep .codex/skills and .codex/sdd/templates)\n");
+        for line in &appended {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        write_file(&path, &contents)?;
+    }
+    Ok(GitignoreUpdate {
+        appended: appended.into_iter().map(str::to_string).collect(),
+        hides_skills,
+    })
 }
 
-pub fn ensure_change_scaffold(change_dir: &Path) -> Result<()> {
+pub fn ensure_change_scaffold(change_dir: &Path, templates_dir: &Path) -> Result<()> {
     ensure_dir(change_dir)?;
     for (name, contents) in render_change_placeholders() {
-        let path = change_dir.join(name);
+        let path = change_dir.join(&name);
         if !path.exists() {
+            let contents =
+                load_override(&templates_dir.join("changes"), &name)?.unwrap_or(contents);
             write_file(&path, &contents)?;
         }
     }
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::core::config::VariationConfig;
+use crate::core::error::ErrorKind;
+use crate::core::state::AgentVariation;
+use crate::tr;
+
+pub const VARIATION_STRATEGIES: [&str; 2] = ["none", "auto"];
+const EMPHASES: [&str; 4] = ["minimal", "robust", "performance", "readability"];
+const ALTERNATIVE_PREFIXES: [&str; 3] = ["alternative", "option", "案"];
+
+pub struct DesignAlternative {
+    pub title: String,
+    pub text: String,
+}
+
+pub fn resolve(
+    config: &VariationConfig,
+    strategy: &str,
+    agents: &[String],
+    change_dir: &Path,
+) -> Result<BTreeMap<String, AgentVariation>> {
+    let alternatives = design_alternatives(change_dir);
+    let auto = strategy == "auto";
+    let mut variations = BTreeMap::new();
+    for (idx, agent) in agents.iter().enumerate() {
+        let overrides = config.agents.get(agent).cloned().unwrap_or_default();
+        let emphasis = overrides
+            .emphasis
+            .or_else(|| auto.then(|| EMPHASES[idx % EMPHASES.len()].to_string()));
+        let model = overrides.model.or_else(|| {
+            (auto && !config.models.is_empty())
+                .then(|| config.models[idx % config.models.len()].clone())
+        });
+        let design = match overrides.design {
+            Some(0) => None,
+            Some(n) => Some(alternatives.get(n - 1).ok_or_else(|| {
+                ErrorKind::NotFound.err(tr!(
+                    "30_design.md に案 {n} がありません ({agent})",
+                    "30_design.md has no alternative {n} (for {agent})"
+                ))
+            })?),
+            None if auto && !alternatives.is_empty() => {
+                Some(&alternatives[idx % alternatives.len()])
+            }
+            None => None,
+        };
+        if emphasis.is_none() && model.is_none() && design.is_none() {
+            continue;
+        }
+        variations.insert(
+            agent.clone(),
+            AgentVariation {
+                instructions: emphasis.as_deref().map(instructions).unwrap_or_default(),
+                emphasis,
+                model,
+                design: design.map(|d| d.title.clone()),
+                design_text: design.map(|d| d.text.clone()),
+            },
+        );
+    }
+    Ok(variations)
+}
+
+pub fn design_alternatives(change_dir: &Path) -> Vec<DesignAlternative> {
+    let Ok(contents) = std::fs::read_to_string(change_dir.join("30_design.md")) else {
+        return Vec::new();
+    };
+    let mut alternatives: Vec<DesignAlternative> = Vec::new();
+    let mut open: Option<usize> = None;
+    for line in contents.lines() {
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            let title = line[level..].trim();
+            let lower = title.to_lowercase();
+            if (2..=3).contains(&level) && ALTERNATIVE_PREFIXES.iter().any(|p| lower.starts_with(p))
+            {
+                alternatives.push(DesignAlternative {
+                    title: title.to_string(),
+                    text: String::new(),
+                });
+                open = Some(level);
+                continue;
+            }
+            if open.is_some_and(|open| level <= open) {
+                open = None;
+            }
+        }
+        if let (Some(_), Some(alternative)) = (open, alternatives.last_mut()) {
+            alternative.text.push_str(line);
+            alternative.text.push('\n');
+        }
+    }
+    for alternative in &mut alternatives {
+        alternative.text = alternative.text.trim().to_string();
+    }
+    alternatives
+}
+
+pub fn describe(variation: &AgentVariation) -> String {
+    let mut parts = Vec::new();
+    if let Some(emphasis) = &variation.emphasis {
+        parts.push(format!(
+            "emphasis={}",
+            if EMPHASES.contains(&emphasis.as_str()) {
+                emphasis.as_str()
+            } else {
+                "custom"
+            }
+        ));
+    }
+    if let Some(model) = &variation.model {
+        parts.push(format!("model={model}"));
+    }
+    if let Some(design) = &variation.design {
+        parts.push(format!("design={design}"));
+    }
+    parts.join(", ")
+}
+
+fn instructions(emphasis: &str) -> String {
+    match emphasis {
+        "minimal" => tr!(
+            "差分を最小限に抑えてください。既存のコードを再利用し、新しい抽象化は避けてください。",
+            "Keep the diff as small as possible. Reuse existing code and avoid new abstractions."
+        ),
+        "robust" => tr!(
+            "正しさとエラー処理を最優先してください。境界条件をテストで網羅してください。",
+            "Put correctness and error handling first. Cover edge cases with tests."
+        ),
+        "performance" => tr!(
+            "効率的なアルゴリズムを選び、不要なアロケーションや I/O を避けてください。",
+            "Choose efficient algorithms and avoid unnecessary allocations and I/O."
+        ),
+        "readability" => tr!(
+            "読みやすさを優先してください。素直な構造と説明的な名前を選んでください。",
+            "Optimize for readability. Prefer straightforward structure and descriptive names."
+        ),
+        custom => custom.to_string(),
+    }
+}
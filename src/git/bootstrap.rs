@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Component, Path};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::{info, warn};
+
+use crate::core::config::{ExecConfig, WorktreesConfig};
+use crate::core::error::ErrorKind;
+use crate::core::paths::RepoPaths;
+use crate::quality::setup::run_hooks;
+use crate::tr;
+use crate::util::{ensure_dir, write_string};
+
+pub fn bootstrap_worktree(
+    paths: &RepoPaths,
+    config: &WorktreesConfig,
+    exec: &ExecConfig,
+    change_id: &str,
+    agent: &str,
+    worktree_path: &Path,
+) -> Result<()> {
+    if config.copy.is_empty() && config.symlink.is_empty() && config.bootstrap.is_empty() {
+        return Ok(());
+    }
+    let mut log = String::new();
+    for rel in &config.copy {
+        if let Some(source) = source_path(&paths.repo_root, rel)? {
+            copy_recursive(&source, &worktree_path.join(rel))?;
+            log.push_str(&format!("copy {rel}\n"));
+        }
+    }
+    for rel in &config.symlink {
+        if let Some(source) = source_path(&paths.repo_root, rel)? {
+            let target = worktree_path.join(rel);
+            if target.symlink_metadata().is_ok() {
+                warn!("skip symlink {rel}: already exists in {agent}");
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                ensure_dir(parent)?;
+            }
+            symlink(&source, &target).with_context(|| format!("symlink {rel}"))?;
+            log.push_str(&format!("symlink {rel}\n"));
+        }
+    }
+    let worktree = worktree_path.to_string_lossy();
+    let repo_root = paths.repo_root.to_string_lossy();
+    let vars = [
+        ("CODEX_SDD_CHANGE", change_id),
+        ("CODEX_SDD_AGENT", agent),
+        ("CODEX_SDD_WORKTREE", worktree.as_ref()),
+        ("CODEX_SDD_REPO_ROOT", repo_root.as_ref()),
+    ];
+    let run = run_hooks(worktree_path, &config.bootstrap, exec, &vars)?;
+    log.push_str(&run.stdout);
+    let log_path = paths
+        .runs_dir
+        .join(change_id)
+        .join(format!("bootstrap_{agent}.txt"));
+    write_string(&log_path, &log)?;
+    if !run.success {
+        return Err(ErrorKind::HookFailed.err(tr!(
+            "{agent} の worktree bootstrap が失敗しました ({})",
+            "worktree bootstrap failed for {agent} ({})",
+            log_path.display()
+        )));
+    }
+    info!("bootstrapped worktree for {agent}");
+    Ok(())
+}
+
+fn source_path(repo_root: &Path, rel: &str) -> Result<Option<std::path::PathBuf>> {
+    if !Path::new(rel)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!(tr!(
+            "worktrees の copy / symlink にはリポジトリからの相対パスを指定してください: {rel}",
+            "worktrees copy / symlink paths must be relative to the repository: {rel}"
+        )));
+    }
+    let source = repo_root.join(rel);
+    if source.symlink_metadata().is_err() {
+        warn!("skip bootstrap path {rel}: not found in the main checkout");
+        return Ok(None);
+    }
+    Ok(Some(source))
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        ensure_dir(to)?;
+        for entry in fs::read_dir(from).with_context(|| format!("read {}", from.display()))? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        ensure_dir(parent)?;
+    }
+    fs::copy(from, to).with_context(|| format!("copy {}", from.display()))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    }
+}
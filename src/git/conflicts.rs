@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::git::worktree::{git_diff_names, git_untracked_paths, list_branches};
+use crate::tr;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeFiles {
+    pub change_id: String,
+    pub base_commit: String,
+    pub agents: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Overlap {
+    pub a: String,
+    pub b: String,
+    pub files: Vec<OverlapFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlapFile {
+    pub path: String,
+    pub a_agents: Vec<String>,
+    pub b_agents: Vec<String>,
+}
+
+impl ChangeFiles {
+    pub fn collect(
+        repo_root: &Path,
+        worktrees_dir: &Path,
+        change_id: &str,
+        base_commit: &str,
+    ) -> Result<Self> {
+        let mut agents = BTreeMap::new();
+        for entry in fs::read_dir(worktrees_dir.join(change_id))
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let agent = entry.file_name().to_string_lossy().to_string();
+            let files = git_diff_names(&path, base_commit).and_then(|mut files| {
+                files.extend(git_untracked_paths(&path)?);
+                Ok(files)
+            });
+            match files {
+                Ok(files) => {
+                    agents.insert(agent, files.into_iter().collect());
+                }
+                Err(err) => warn!("skip worktree {}: {err:#}", path.display()),
+            }
+        }
+        let prefix = format!("sdd/{change_id}/");
+        for branch in list_branches(repo_root, &format!("{prefix}*"))? {
+            let Some(agent) = branch.strip_prefix(&prefix) else {
+                continue;
+            };
+            if agents.contains_key(agent) {
+                continue;
+            }
+            match git_diff_names(repo_root, &format!("{base_commit}...{branch}")) {
+                Ok(files) => {
+                    agents.insert(agent.to_string(), files.into_iter().collect());
+                }
+                Err(err) => warn!("skip branch {branch}: {err:#}"),
+            }
+        }
+        Ok(Self {
+            change_id: change_id.to_string(),
+            base_commit: base_commit.to_string(),
+            agents,
+        })
+    }
+
+    fn agents_touching(&self, path: &str) -> Vec<String> {
+        self.agents
+            .iter()
+            .filter(|(_, files)| files.contains(path))
+            .map(|(agent, _)| agent.clone())
+            .collect()
+    }
+
+    fn files(&self) -> BTreeSet<&String> {
+        self.agents.values().flatten().collect()
+    }
+}
+
+pub fn overlaps(changes: &[ChangeFiles]) -> Vec<Overlap> {
+    let mut out = Vec::new();
+    for (idx, a) in changes.iter().enumerate() {
+        let a_files = a.files();
+        for b in &changes[idx + 1..] {
+            let files: Vec<OverlapFile> = b
+                .files()
+                .into_iter()
+                .filter(|path| a_files.contains(path))
+                .map(|path| OverlapFile {
+                    path: path.clone(),
+                    a_agents: a.agents_touching(path),
+                    b_agents: b.agents_touching(path),
+                })
+                .collect();
+            if !files.is_empty() {
+                out.push(Overlap {
+                    a: a.change_id.clone(),
+                    b: b.change_id.clone(),
+                    files,
+                });
+            }
+        }
+    }
+    out
+}
+
+pub fn render(changes: &[ChangeFiles], overlaps: &[Overlap]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        out.push_str(&format!(
+            "{}  {} files  ({})\n",
+            change.change_id,
+            change.files().len(),
+            change
+                .agents
+                .iter()
+                .map(|(agent, files)| format!("{agent}: {}", files.len()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    out.push('\n');
+    if overlaps.is_empty() {
+        out.push_str(&tr!(
+            "change 間で重なっているファイルはありません\n",
+            "no files overlap between changes\n"
+        ));
+        return out;
+    }
+    for overlap in overlaps {
+        out.push_str(&format!(
+            "{} <-> {}: {}\n",
+            overlap.a,
+            overlap.b,
+            tr!("{} ファイル", "{} files", overlap.files.len())
+        ));
+        for file in &overlap.files {
+            out.push_str(&format!(
+                "  {}  ({}: {}; {}: {})\n",
+                file.path,
+                overlap.a,
+                file.a_agents.join(", "),
+                overlap.b,
+                file.b_agents.join(", ")
+            ));
+        }
+    }
+    out
+}
@@ -1 +1,3 @@
+pub mod bootstrap;
+pub mod conflicts;
 pub mod worktree;
@@ -1,8 +1,10 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 use std::process::Command;
 
 use anyhow::{anyhow, Context, Result};
 
+use crate::core::error::ErrorKind;
 use crate::util::run_cmd_allow_fail;
 
 pub fn current_commit(repo_root: &Path) -> Result<String> {
@@ -15,13 +17,75 @@ pub fn current_commit(repo_root: &Path) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-pub fn create_worktree(repo_root: &Path, branch: &str, path: &Path) -> Result<()> {
+pub fn current_branch(repo_root: &Path) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["symbolic-ref", "--short", "HEAD"]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("HEAD is not on a branch"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn push_branch(repo_root: &Path, remote: &str, branch: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["push", "--set-upstream", remote, branch]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git push failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+pub fn resolve_commit(repo_root: &Path, rev: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["rev-parse", "--verify", &format!("{rev}^{{commit}}")]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("failed to resolve {rev}"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn create_worktree(
+    repo_root: &Path,
+    branch: &str,
+    path: &Path,
+    start_point: Option<&str>,
+) -> Result<()> {
     if path.exists() {
         return Ok(());
     }
     let mut cmd = Command::new("git");
     cmd.current_dir(repo_root)
         .args(["worktree", "add", "-b", branch, path.to_str().unwrap()]);
+    if let Some(start_point) = start_point {
+        cmd.arg(start_point);
+    }
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git worktree failed: {stderr}"));
+    }
+    Ok(())
+}
+
+pub fn create_detached_worktree(repo_root: &Path, path: &Path, commit: &str) -> Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args([
+        "worktree",
+        "add",
+        "--detach",
+        path.to_str().unwrap(),
+        commit,
+    ]);
     let output = run_cmd_allow_fail(cmd)?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -50,6 +114,29 @@ pub fn git_diff_numstat(repo_root: &Path, base: &str) -> Result<(u64, u64)> {
     Ok((added, removed))
 }
 
+pub fn git_diff_text(repo_root: &Path, base: &str, branch: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["diff", &format!("{base}..{branch}")]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git diff failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn show_diff_paged(repo_root: &Path, base: &str, branch: &str) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(repo_root)
+        .args(["--paginate", "diff", &format!("{base}..{branch}")])
+        .status()
+        .with_context(|| "run git diff")?;
+    if !status.success() {
+        return Err(anyhow!("git diff failed"));
+    }
+    Ok(())
+}
+
 pub fn ensure_base_ref(repo_root: &Path, base_ref: &str) -> Result<String> {
     let mut cmd = Command::new("git");
     cmd.current_dir(repo_root)
@@ -74,6 +161,156 @@ pub fn git_diff_names(repo_root: &Path, base_ref: &str) -> Result<Vec<String>> {
     Ok(stdout.lines().map(|s| s.to_string()).collect())
 }
 
+pub fn git_diff_names_since(repo_root: &Path, base_ref: &str) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["diff", "--name-only", "-z", &format!("{base_ref}...HEAD")]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git diff failed"));
+    }
+    Ok(output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect())
+}
+
+pub fn git_dirty_paths(repo_root: &Path) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["status", "--porcelain", "--untracked-files=no"]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git status failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(str::to_string)
+        .collect())
+}
+
+pub fn git_untracked_paths(repo_root: &Path) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["ls-files", "--others", "--exclude-standard", "-z"]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git ls-files failed"));
+    }
+    Ok(output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect())
+}
+
+pub fn is_ancestor(repo_root: &Path, ancestor: &str, rev: &str) -> Result<bool> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["merge-base", "--is-ancestor", ancestor, rev]);
+    let output = run_cmd_allow_fail(cmd)?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(anyhow!(
+            "git merge-base failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+pub fn git_log_oneline(repo_root: &Path, range: &str) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["log", "--oneline", "--no-decorate", range]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git log failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+pub fn git_diff_stat(repo_root: &Path, range: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args(["diff", "--stat", range]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git diff failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn git_changed_lines(repo_root: &Path, base: &str) -> Result<BTreeMap<String, BTreeSet<u32>>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["diff", "--unified=0", "--no-color", "--no-ext-diff", base]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git diff failed"));
+    }
+    let mut lines: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+    let mut file = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").map(str::to_string);
+            continue;
+        }
+        let (Some(file), Some(hunk)) = (&file, line.strip_prefix("@@ ")) else {
+            continue;
+        };
+        let Some(added) = hunk
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix('+'))
+        else {
+            continue;
+        };
+        let (start, count) = added.split_once(',').unwrap_or((added, "1"));
+        let (Ok(start), Ok(count)) = (start.parse::<u32>(), count.parse::<u32>()) else {
+            continue;
+        };
+        lines
+            .entry(file.clone())
+            .or_default()
+            .extend(start..start + count);
+    }
+    lines.retain(|_, changed| !changed.is_empty());
+    Ok(lines)
+}
+
+pub fn git_file_churn(
+    repo_root: &Path,
+    rev: &str,
+    window: usize,
+    files: &BTreeSet<String>,
+) -> Result<u64> {
+    if files.is_empty() || window == 0 {
+        return Ok(0);
+    }
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args([
+        "log",
+        "--format=",
+        "--name-only",
+        &format!("--max-count={window}"),
+        rev,
+    ]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git log failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|path| files.contains(*path))
+        .count() as u64)
+}
+
 pub fn merge_branch(repo_root: &Path, branch: &str, no_ff: bool) -> Result<()> {
     let mut cmd = Command::new("git");
     cmd.current_dir(repo_root).arg("merge");
@@ -84,7 +321,7 @@ pub fn merge_branch(repo_root: &Path, branch: &str, no_ff: bool) -> Result<()> {
     let output = run_cmd_allow_fail(cmd)?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("git merge failed: {stderr}"));
+        return Err(ErrorKind::MergeConflict.err(format!("git merge failed: {stderr}")));
     }
     Ok(())
 }
@@ -96,11 +333,108 @@ pub fn cherry_pick(repo_root: &Path, branch: &str) -> Result<()> {
     let output = run_cmd_allow_fail(cmd)?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("git cherry-pick failed: {stderr}"));
+        return Err(ErrorKind::MergeConflict.err(format!("git cherry-pick failed: {stderr}")));
+    }
+    Ok(())
+}
+
+pub fn list_branches(repo_root: &Path, pattern: &str) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["branch", "--list", "--format=%(refname:short)", pattern]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("git branch failed"));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().map(|s| s.trim().to_string()).collect())
+}
+
+pub fn delete_branch(repo_root: &Path, branch: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args(["branch", "-D", branch]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git branch -D failed: {stderr}"));
+    }
+    Ok(())
+}
+
+pub fn rename_branch(repo_root: &Path, from: &str, to: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args(["branch", "-m", from, to]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git branch -m failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+pub fn move_worktree(repo_root: &Path, from: &Path, to: &Path) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["worktree", "move"])
+        .arg(from)
+        .arg(to);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git worktree move failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+pub fn remove_worktree(repo_root: &Path, path: &Path) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["worktree", "remove", "--force"])
+        .arg(path);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git worktree remove failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+pub fn prune_worktrees(repo_root: &Path) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root).args(["worktree", "prune"]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git worktree prune failed: {stderr}"));
     }
     Ok(())
 }
 
+pub fn exclude_path(repo_root: &Path, pattern: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_root)
+        .args(["rev-parse", "--git-path", "info/exclude"]);
+    let output = run_cmd_allow_fail(cmd)?;
+    if !output.status.success() {
+        return Err(anyhow!("failed to locate info/exclude"));
+    }
+    let exclude = repo_root.join(String::from_utf8_lossy(&output.stdout).trim());
+    let contents = std::fs::read_to_string(&exclude).unwrap_or_default();
+    if contents.lines().any(|line| line.trim() == pattern) {
+        return Ok(());
+    }
+    if let Some(parent) = exclude.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let separator = if contents.is_empty() || contents.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    std::fs::write(&exclude, format!("{contents}{separator}{pattern}\n"))
+        .with_context(|| format!("write {}", exclude.display()))
+}
+
 pub fn move_dir(from: &Path, to: &Path) -> Result<()> {
     std::fs::create_dir_all(to.parent().unwrap()).with_context(|| "create archive dir")?;
     std::fs::rename(from, to).with_context(|| "move change dir")?;
@@ -6,77 +6,413 @@ mod git;
 mod quality;
 mod util;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{anyhow, Result};
-use clap::{Args, Parser, Subcommand};
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::{CompleteEnv, Shells};
+use minijinja::context;
+use notify::{EventKind, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use tracing::{error, info, info_span, warn};
+use walkdir::WalkDir;
 
-use crate::analysis::index::{build_index, shard_files, shard_hash, FileEntry};
-use crate::codex::exec::{output_paths, ExecSpec};
-use crate::core::paths::{GlobalPaths, RepoPaths};
-use crate::core::state::State;
+use crate::analysis::index::{
+    build_index, index_result, match_files, read_index, shard_files, shard_hash, should_exclude,
+    update_index, with_dependents, write_index, write_repo_tree, FileEntry,
+};
+use crate::analysis::spike;
+use crate::codex::debate;
+use crate::codex::exec::{commit_latest, new_output_paths, output_paths, Backend, ExecSpec};
+use crate::codex::preflight::{self, AgentCount, Budget};
+use crate::codex::replay::{self, Step};
+use crate::core::audit_log;
+use crate::core::config::{Config, RiskConfig};
+use crate::core::error::{error_kind, ErrorKind};
+use crate::core::fsck::{fsck, repair};
+use crate::core::gc::{self, format_bytes, GcPolicy};
+use crate::core::hooks::HookRunner;
+use crate::core::lock::RepoLock;
+use crate::core::owners::{self, OwnedPaths, Owners};
+use crate::core::paths::{self, GlobalPaths, RepoPaths};
+use crate::core::state::{ChangeState, FindingStatus, State};
+use crate::core::store::{self, metrics_history, record_metrics, MetricsRecord};
+use crate::core::tracker::{self, fetch_issue, IssueRef};
+use crate::docs::digest::{self, DigestSection, DIGEST_FILE};
+use crate::docs::facts;
+use crate::docs::findings::{self, Finding, FINDING_STATUSES};
+use crate::docs::gates::{GateOutcome, SpecGate};
+use crate::docs::inspect::{self, Painter};
+use crate::docs::install::{self, AssetStatus};
+use crate::docs::manifest::{ManifestRun, PlanManifest};
+use crate::docs::prompts::{PromptRenderer, SpecExcerpt};
+use crate::docs::refs;
+use crate::docs::report::{render_selection_html, HtmlVariant};
+use crate::docs::specs::{build_compliance, load_requirements, render_compliance};
+use crate::docs::tasks;
 use crate::docs::templates::{
-    ensure_agents_md, ensure_change_scaffold, ensure_repo_scaffold, write_prompt,
+    apply_init_template, ensure_change_scaffold, ensure_gitignore, ensure_repo_scaffold,
+    has_agents_block, line_diff, update_agents_md, INIT_TEMPLATES,
 };
+use crate::docs::variation::{self, VARIATION_STRATEGIES};
+use crate::git::bootstrap::bootstrap_worktree;
+use crate::git::conflicts;
 use crate::git::worktree::{
-    cherry_pick, create_worktree, current_commit, git_diff_names, git_diff_numstat, merge_branch,
-    move_dir,
+    cherry_pick, create_detached_worktree, create_worktree, current_branch, current_commit,
+    exclude_path, git_diff_names, git_diff_names_since, git_diff_numstat, git_diff_stat,
+    git_diff_text, git_dirty_paths, git_file_churn, git_log_oneline, is_ancestor, list_branches,
+    merge_branch, move_dir, move_worktree, push_branch, remove_worktree, rename_branch,
+    resolve_commit, show_diff_paged,
 };
-use crate::quality::coverage::{run_llvm_cov, run_tarpaulin};
-use crate::quality::tests::run_tests;
+use crate::quality::audit::{self, run_audit};
+use crate::quality::build::ReleaseMetrics;
+use crate::quality::environment::EnvFingerprint;
+use crate::quality::metrics::{MeasureContext, MeasureOptions, MetricsFile, VariantMetrics};
+use crate::quality::risk::{self, RiskInputs, RiskScore};
+use crate::quality::trend::TrendFile;
+use crate::util::i18n::{self, Lang};
+use crate::util::logging;
+use crate::util::notify::notify;
+use crate::util::progress::{Progress, ProgressGroup};
 use crate::util::{
-    ensure_dir, log_event, now_rfc3339, read_to_string, slugify, write_file, write_string,
+    crypto, current_user, ensure_dir, normalize_path, now_rfc3339, prompt_line, read_to_string,
+    slugify, write_file, write_string, write_string_atomic,
 };
 
+const LANG_ENV: &str = "CODEX_SDD_LANG";
+const CHANGE_ENV: &str = "CODEX_SDD_CHANGE";
+const APPROVED_ARTIFACTS: [&str; 2] = [DIGEST_FILE, "40_tasks.md"];
+const COVERAGE_TOOLS: [&str; 3] = ["llvm-cov", "tarpaulin", "none"];
+const IMPLEMENT_PROMPT: &str = ".codex/sdd/implement.md";
+const IMPLEMENT_PROMPT_EXCLUDE: &str = "/.codex/sdd/";
+const WATCH_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Parser)]
 #[command(name = "codex-sdd", version, propagate_version = true)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[arg(long, global = true, value_parser = ["none", "bell", "desktop"])]
+    notify: Option<String>,
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    #[arg(long, global = true, value_parser = ["trace", "debug", "info", "warn", "error"])]
+    log_level: Option<String>,
+    #[arg(long, global = true, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Install,
-    Init,
+    Install(InstallArgs),
+    Completions(CompletionsArgs),
+    Config(ConfigArgs),
+    Init(InitArgs),
+    New,
     Plans(PlansArgs),
-    Review(ChangeArgs),
-    Tasks(ChangeArgs),
+    Watch(WatchArgs),
+    Review(ReviewArgs),
+    Tasks(TasksArgs),
     Approve(ApproveArgs),
     Check(CheckArgs),
     Worktrees(WorktreesArgs),
     TestPlan(TestPlanArgs),
-    Select(ChangeArgs),
+    Select(SelectArgs),
     Finalize(FinalizeArgs),
+    #[command(subcommand)]
+    State(StateCommands),
+    List,
+    Stats(ChangeArgs),
+    Gc(GcArgs),
+    Switch(SwitchArgs),
+    Rename(RenameArgs),
+    #[command(subcommand)]
+    Audit(AuditCommands),
+    #[command(subcommand)]
+    Findings(FindingsCommands),
+    #[command(subcommand)]
+    Context(ContextCommands),
+    #[command(subcommand)]
+    Facts(FactsCommands),
+    #[command(subcommand)]
+    Encryption(EncryptionCommands),
+    Inspect(InspectArgs),
+    Conflicts(ConflictsArgs),
+    Spike(SpikeArgs),
+    Replay(ReplayArgs),
+}
+
+#[derive(Subcommand)]
+enum AuditCommands {
+    Show(ChangeArgs),
+    Verify,
+}
+
+#[derive(Subcommand)]
+enum FindingsCommands {
+    List(FindingsListArgs),
+    Ack(FindingArgs),
+    Resolve(FindingArgs),
+}
+
+#[derive(Args)]
+struct FindingsListArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long, value_parser = FINDING_STATUSES)]
+    status: Option<String>,
+}
+
+#[derive(Args)]
+struct FindingArgs {
+    finding: String,
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long)]
+    by: Option<String>,
+    #[arg(long)]
+    note: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum ContextCommands {
+    Add(ContextAddArgs),
+    List(ContextListArgs),
+}
+
+#[derive(Args)]
+struct ContextAddArgs {
+    source: String,
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long)]
+    name: Option<String>,
+}
+
+#[derive(Args)]
+struct ContextListArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum FactsCommands {
+    Query(FactsQueryArgs),
+}
+
+#[derive(Args)]
+struct FactsQueryArgs {
+    #[arg(long)]
+    path: String,
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Args)]
+struct InspectArgs {
+    #[arg(value_parser = inspect::ARTIFACTS)]
+    artifact: String,
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Args)]
+struct SpikeArgs {
+    #[arg(long)]
+    question: String,
+    #[arg(long = "scope")]
+    scope: Vec<String>,
+    #[arg(long)]
+    max_files: Option<usize>,
+    #[arg(long)]
+    timeout_secs: Option<u64>,
+    #[arg(long)]
+    include_untracked: bool,
+}
+
+#[derive(Args)]
+struct ConflictsArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum EncryptionCommands {
+    Seal,
+    Cat(EncryptionCatArgs),
+}
+
+#[derive(Args)]
+struct EncryptionCatArgs {
+    path: PathBuf,
+}
+
+#[derive(Args)]
+struct SwitchArgs {
+    #[arg(add = ArgValueCandidates::new(change_id_candidates))]
+    id: String,
+}
+
+#[derive(Args)]
+struct RenameArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long)]
+    to: String,
+}
+
+#[derive(Subcommand)]
+enum StateCommands {
+    Fsck,
+    Repair(RepairArgs),
+}
+
+#[derive(Args)]
+struct CompletionsArgs {
+    #[arg(value_parser = ["bash", "zsh", "fish", "powershell", "elvish"])]
+    shell: String,
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    #[arg(long)]
+    show_origin: bool,
+}
+
+#[derive(Args)]
+struct InitArgs {
+    #[arg(long)]
+    write_gitignore: bool,
+    #[arg(long, value_parser = INIT_TEMPLATES)]
+    template: Option<String>,
+    #[arg(long)]
+    update_agents: bool,
+}
+
+#[derive(Args)]
+struct InstallArgs {
+    #[arg(long, conflicts_with_all = ["update", "force"])]
+    list: bool,
+    #[arg(long)]
+    update: bool,
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct GcArgs {
+    #[arg(long)]
+    dry_run: bool,
+    #[arg(long)]
+    keep_runs: Option<usize>,
+    #[arg(long)]
+    keep_archived: bool,
+    #[arg(long)]
+    max_size_mb: Option<u64>,
 }
 
 #[derive(Args)]
+struct RepairArgs {
+    #[arg(long)]
+    delete_branches: bool,
+}
+
+#[derive(Args, Clone)]
 struct PlansArgs {
+    #[arg(long, required_unless_present = "issue")]
+    name: Option<String>,
     #[arg(long)]
-    name: String,
+    issue: Option<String>,
     #[arg(long)]
+    goal: Option<String>,
+    #[arg(long = "scope")]
+    scope: Vec<String>,
+    #[arg(long, value_parser = COVERAGE_TOOLS)]
+    coverage: Option<String>,
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
-    #[arg(long, default_value_t = 4)]
-    agents: usize,
+    #[arg(long, default_value = "4")]
+    agents: AgentCount,
+    #[arg(long)]
+    include_untracked: bool,
+    #[arg(long = "after", add = ArgValueCandidates::new(change_id_candidates))]
+    after: Vec<String>,
+    #[arg(long)]
+    no_cache: bool,
+    #[arg(long)]
+    since: Option<String>,
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long, default_value_t = 2000)]
+    debounce_ms: u64,
+    #[arg(long)]
+    rerun: bool,
     #[arg(long)]
     include_untracked: bool,
 }
 
 #[derive(Args)]
 struct ChangeArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+}
+
+#[derive(Args)]
+struct ReviewArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
     #[arg(long)]
+    against_specs: bool,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    debaters: Option<u32>,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        num_args = 1..,
+        conflicts_with_all = ["against_specs", "debaters"]
+    )]
+    files: Vec<String>,
+}
+
+#[derive(Args)]
+struct TasksArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    split: Vec<String>,
 }
 
 #[derive(Args)]
 struct ApproveArgs {
-    #[arg(long)]
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
     #[arg(long)]
     by: Option<String>,
+    #[arg(long)]
+    revoke: bool,
+    #[arg(long, conflicts_with = "revoke")]
+    override_owners: bool,
+    #[arg(long)]
+    reason: Option<String>,
 }
 
 #[derive(Args)]
@@ -87,63 +423,173 @@ struct CheckArgs {
 
 #[derive(Args)]
 struct WorktreesArgs {
-    #[arg(long)]
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
     #[arg(long, default_value_t = 2)]
     agents: usize,
+    #[arg(long, value_parser = VARIATION_STRATEGIES)]
+    variation: Option<String>,
+    #[arg(long, conflicts_with = "ignore_deps")]
+    on_dependency: bool,
+    #[arg(long)]
+    ignore_deps: bool,
+    #[arg(long)]
+    force: bool,
 }
 
 #[derive(Args)]
 struct TestPlanArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
+    id: Option<String>,
+    #[arg(long, value_parser = COVERAGE_TOOLS)]
+    coverage: Option<String>,
+    #[arg(long)]
+    audit: Option<String>,
+    #[arg(long)]
+    bench: bool,
     #[arg(long)]
+    build_metrics: bool,
+    #[arg(long)]
+    mutants: bool,
+    #[arg(long, add = ArgValueCandidates::new(agent_candidates))]
+    agent: Vec<String>,
+    #[arg(long, add = ArgValueCandidates::new(agent_candidates))]
+    skip_agent: Vec<String>,
+    #[arg(long)]
+    max_parallel: Option<usize>,
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct SelectArgs {
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
-    #[arg(long, default_value = "llvm-cov")]
-    coverage: String,
+    #[arg(long)]
+    interactive: bool,
+    #[arg(long)]
+    html: bool,
+    #[arg(long, requires = "recompute", add = ArgValueCandidates::new(agent_candidates))]
+    agent: Option<String>,
+    #[arg(long, requires = "agent")]
+    recompute: bool,
+    #[arg(long)]
+    coverage: Option<String>,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    debaters: Option<u32>,
 }
 
 #[derive(Args)]
 struct FinalizeArgs {
-    #[arg(long)]
+    #[arg(long, add = ArgValueCandidates::new(change_id_candidates))]
     id: Option<String>,
-    #[arg(long)]
-    agent: String,
+    #[arg(long, add = ArgValueCandidates::new(agent_candidates))]
+    agent: Option<String>,
     #[arg(long, default_value = "merge")]
     strategy: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct VariantMetrics {
-    agent: String,
-    tests_passed: bool,
-    coverage_percent: Option<f64>,
-    coverage_tool: String,
-    test_output: String,
-    coverage_output: Option<String>,
+    #[arg(long)]
+    ignore_deps: bool,
+    #[arg(long)]
+    force: bool,
+    #[arg(long)]
+    preview: bool,
+    #[arg(long)]
+    pr: bool,
+    #[arg(long)]
+    allow_dirty: bool,
+    #[arg(long)]
+    allow_diverged: bool,
+    #[arg(long)]
+    allow_other_branch: bool,
+    #[arg(long)]
+    allow_risk: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct SelectionVariant {
     agent: String,
+    build_passed: bool,
     tests_passed: bool,
     coverage_percent: Option<f64>,
     lines_added: u64,
     lines_removed: u64,
+    new_advisories: Vec<String>,
+    bench_mean_delta_percent: Option<f64>,
+    bench_regressions: Vec<String>,
+    release: Option<ReleaseMetrics>,
+    mutation_score_percent: Option<f64>,
+    #[serde(default)]
+    stage_secs: BTreeMap<String, f64>,
+    #[serde(default)]
+    environment: Option<EnvFingerprint>,
+    #[serde(default)]
+    strategy: Option<String>,
+    #[serde(default)]
+    risk: RiskScore,
     notes: String,
 }
 
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("{err}");
-        std::process::exit(1);
+    CompleteEnv::with_factory(Cli::command).complete();
+    let cli = Cli::parse();
+    let json = cli.format == "json";
+    if let Err(err) = run(cli) {
+        let kind = error_kind(&err);
+        let code = kind.map_or(1, ErrorKind::exit_code);
+        if json {
+            let causes: Vec<String> = err.chain().skip(1).map(ToString::to_string).collect();
+            let payload = serde_json::json!({
+                "error": {
+                    "kind": kind.map_or("error", ErrorKind::name),
+                    "exit_code": code,
+                    "message": err.to_string(),
+                    "causes": causes,
+                }
+            });
+            eprintln!("{payload}");
+        } else {
+            eprintln!("{err}");
+        }
+        std::process::exit(code);
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::Install => cmd_install(),
-        Commands::Init => cmd_init(),
+fn run(cli: Cli) -> Result<()> {
+    init_language()?;
+    let log_dir = RepoPaths::load()
+        .ok()
+        .map(|paths| paths.logs_dir)
+        .filter(|dir| dir.parent().is_some_and(|p| p.exists()));
+    logging::init(
+        logging::level_from_flags(cli.log_level.as_deref(), cli.verbose, cli.quiet),
+        log_dir.as_deref(),
+    )?;
+    let _span = info_span!("command", command = command_name(&cli.command)).entered();
+    let json = cli.format == "json";
+    init_encryption();
+    let _locks = acquire_locks(&cli.command)?;
+    let notify_name = notify_name(&cli.command);
+    let notify_method = cli.notify.clone();
+    let hooks = match &cli.command {
+        Commands::Finalize(args) if args.preview => None,
+        command => HookRunner::load(command_name(command)),
+    };
+    let requested = requested_change(&cli.command);
+    if let Some(hooks) = &hooks {
+        let change_id = match &cli.command {
+            Commands::Plans(_) => None,
+            _ => hook_change_id(requested.clone()),
+        };
+        hooks.pre(change_id.as_deref())?;
+    }
+    let result = match cli.command {
+        Commands::Install(args) => cmd_install(args),
+        Commands::Completions(args) => cmd_completions(args),
+        Commands::Config(args) => cmd_config(args),
+        Commands::Init(args) => cmd_init(args),
+        Commands::New => cmd_new(),
         Commands::Plans(args) => cmd_plans(args),
+        Commands::Watch(args) => cmd_watch(args),
         Commands::Review(args) => cmd_review(args),
         Commands::Tasks(args) => cmd_tasks(args),
         Commands::Approve(args) => cmd_approve(args),
@@ -152,533 +598,4806 @@ fn run() -> Result<()> {
         Commands::TestPlan(args) => cmd_test_plan(args),
         Commands::Select(args) => cmd_select(args),
         Commands::Finalize(args) => cmd_finalize(args),
+        Commands::State(StateCommands::Fsck) => cmd_state_fsck(),
+        Commands::State(StateCommands::Repair(args)) => cmd_state_repair(args),
+        Commands::List => cmd_list(),
+        Commands::Stats(args) => cmd_stats(args),
+        Commands::Gc(args) => cmd_gc(args),
+        Commands::Switch(args) => cmd_switch(args),
+        Commands::Rename(args) => cmd_rename(args),
+        Commands::Audit(AuditCommands::Show(args)) => cmd_audit_show(args),
+        Commands::Audit(AuditCommands::Verify) => cmd_audit_verify(),
+        Commands::Findings(FindingsCommands::List(args)) => cmd_findings_list(args),
+        Commands::Findings(FindingsCommands::Ack(args)) => cmd_findings_mark(args, "acknowledged"),
+        Commands::Findings(FindingsCommands::Resolve(args)) => cmd_findings_mark(args, "resolved"),
+        Commands::Context(ContextCommands::Add(args)) => cmd_context_add(args),
+        Commands::Context(ContextCommands::List(args)) => cmd_context_list(args),
+        Commands::Facts(FactsCommands::Query(args)) => cmd_facts_query(args, json),
+        Commands::Encryption(EncryptionCommands::Seal) => cmd_encryption_seal(),
+        Commands::Encryption(EncryptionCommands::Cat(args)) => cmd_encryption_cat(args),
+        Commands::Inspect(args) => cmd_inspect(args, json),
+        Commands::Conflicts(args) => cmd_conflicts(args, json),
+        Commands::Spike(args) => cmd_spike(args, json),
+        Commands::Replay(args) => cmd_replay(args, json),
+    };
+    let result = match hooks.map(|hooks| hooks.post(hook_change_id(requested).as_deref(), &result))
+    {
+        Some(Err(err)) if result.is_ok() => Err(err),
+        Some(Err(err)) => {
+            warn!("{err:#}");
+            result
+        }
+        _ => result,
+    };
+    if let Some(name) = notify_name {
+        notify_finished(name, notify_method, &result);
     }
+    result
 }
 
-fn cmd_install() -> Result<()> {
-    log_event("info", "install prompt");
-    let global = GlobalPaths::load()?;
-    let prompt_path = write_prompt(&global.codex_home)?;
-    println!(
-        "prompts/plans.md を {} に作成しました。新しいCodexセッションを開いてください。",
-        prompt_path.display()
-    );
-    Ok(())
+fn requested_change(command: &Commands) -> Option<Option<String>> {
+    let requested = match command {
+        Commands::Plans(_) => None,
+        Commands::Watch(args) => args.id.clone(),
+        Commands::Review(args) => args.id.clone(),
+        Commands::Tasks(args) => args.id.clone(),
+        Commands::Approve(args) => args.id.clone(),
+        Commands::Worktrees(args) => args.id.clone(),
+        Commands::TestPlan(args) => args.id.clone(),
+        Commands::Select(args) => args.id.clone(),
+        Commands::Finalize(args) => args.id.clone(),
+        Commands::Stats(args) => args.id.clone(),
+        Commands::Switch(args) => Some(args.id.clone()),
+        Commands::Rename(args) => args.id.clone(),
+        Commands::Findings(FindingsCommands::List(args)) => args.id.clone(),
+        Commands::Findings(FindingsCommands::Ack(args) | FindingsCommands::Resolve(args)) => {
+            args.id.clone()
+        }
+        Commands::Context(ContextCommands::Add(args)) => args.id.clone(),
+        Commands::Context(ContextCommands::List(args)) => args.id.clone(),
+        Commands::Facts(FactsCommands::Query(args)) => args.id.clone(),
+        Commands::Inspect(args) => args.id.clone(),
+        Commands::Conflicts(args) => args.id.clone(),
+        Commands::Replay(args) => args.id.clone(),
+        _ => return None,
+    };
+    Some(requested)
 }
 
-fn cmd_init() -> Result<()> {
-    log_event("info", "init repo scaffold");
-    let paths = RepoPaths::load()?;
-    ensure_repo_scaffold(&paths.repo_root)?;
-    let created = ensure_agents_md(&paths.repo_root)?;
-    if created {
-        println!("AGENTS.md を作成しました。");
-    } else {
-        println!("AGENTS.md は既に存在します。");
-    }
-    println!(".codex/sdd/ を .gitignore に追加することを推奨します（.codex/skills は除外しないでください）。");
-    Ok(())
+fn hook_change_id(requested: Option<Option<String>>) -> Option<String> {
+    let requested = requested?;
+    let paths = RepoPaths::load().ok()?;
+    let state = State::load(&paths.state_path).ok()?;
+    resolve_change_id(&paths, &state, requested.as_deref()).ok()
 }
 
-fn cmd_plans(args: PlansArgs) -> Result<()> {
-    log_event("info", "plans start");
-    let paths = RepoPaths::load()?;
-    ensure_repo_scaffold(&paths.repo_root)?;
-
-    let mut state = State::load(&paths.state_path)?;
-    let name_slug = slugify(&args.name);
-    let base_id = args.id.unwrap_or_else(|| name_slug.clone());
-    let change_id = ensure_unique_change_id(&paths, &base_id, &name_slug)?;
-    let change_dir = paths.change_dir(&change_id, &name_slug);
-    ensure_change_scaffold(&change_dir)?;
-
-    let index_result = build_index(&paths.repo_root, args.include_untracked)?;
-    let context_dir = paths.change_context_dir(&change_dir);
-    let index_path = context_dir.join("file_index.json");
-    let tree_path = context_dir.join("repo_tree.txt");
-    crate::analysis::index::write_index(&index_path, &index_result.index)?;
-    crate::analysis::index::write_repo_tree(&tree_path, &index_result.repo_tree)?;
+fn command_name(command: &Commands) -> &'static str {
+    match command {
+        Commands::Install(_) => "install",
+        Commands::Completions(_) => "completions",
+        Commands::Config(_) => "config",
+        Commands::Init(_) => "init",
+        Commands::New => "new",
+        Commands::Plans(_) => "plans",
+        Commands::Watch(_) => "watch",
+        Commands::Review(_) => "review",
+        Commands::Tasks(_) => "tasks",
+        Commands::Approve(_) => "approve",
+        Commands::Check(_) => "check",
+        Commands::Worktrees(_) => "worktrees",
+        Commands::TestPlan(_) => "test-plan",
+        Commands::Select(_) => "select",
+        Commands::Finalize(_) => "finalize",
+        Commands::State(_) => "state",
+        Commands::List => "list",
+        Commands::Stats(_) => "stats",
+        Commands::Gc(_) => "gc",
+        Commands::Switch(_) => "switch",
+        Commands::Rename(_) => "rename",
+        Commands::Audit(_) => "audit",
+        Commands::Findings(_) => "findings",
+        Commands::Context(_) => "context",
+        Commands::Facts(_) => "facts",
+        Commands::Encryption(_) => "encryption",
+        Commands::Inspect(_) => "inspect",
+        Commands::Conflicts(_) => "conflicts",
+        Commands::Spike(_) => "spike",
+        Commands::Replay(_) => "replay",
+    }
+}
 
-    {
-        let change_state = state.change_state_mut(&change_id);
-        change_state
-            .file_hashes
-            .clone_from(&index_result.file_hashes);
-        change_state.file_index_hash = Some(index_result.index_hash.clone());
-        change_state.file_index_generated_at = Some(now_rfc3339());
+fn notify_name(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::New
+        | Commands::Plans(_)
+        | Commands::Review(_)
+        | Commands::Tasks(_)
+        | Commands::TestPlan(_)
+        | Commands::Spike(_) => Some(command_name(command)),
+        Commands::Select(args) if args.recompute => Some(command_name(command)),
+        _ => None,
     }
-    state.active_change_id = Some(change_id.clone());
-    let existing_shard_hashes = state
-        .change_state(&change_id)
-        .map(|c| c.reader_shard_hashes.clone())
-        .unwrap_or_default();
+}
 
-    ensure_schemas(&paths)?;
+fn init_language() -> Result<()> {
+    let value = match std::env::var(LANG_ENV) {
+        Ok(value) if !value.is_empty() => value,
+        _ => RepoPaths::load()
+            .and_then(|paths| Config::load(&paths.config_path))
+            .map(|config| config.language)
+            .unwrap_or_else(|_| "ja".to_string()),
+    };
+    i18n::set_lang(Lang::parse(&value)?);
+    Ok(())
+}
 
-    let shards = shard_files(&index_result.index, args.agents);
-    ensure_dir(&paths.runs_dir.join(&change_id))?;
+fn notify_finished(name: &str, method: Option<String>, result: &Result<()>) {
+    let method = method.or_else(|| {
+        RepoPaths::load()
+            .and_then(|paths| Config::load(&paths.config_path))
+            .ok()
+            .map(|config| config.notify)
+    });
+    let Some(method) = method else {
+        return;
+    };
+    match result {
+        Ok(()) => notify(
+            &method,
+            &tr!("codex-sdd {name} 完了", "codex-sdd {name} finished"),
+            &tr!("処理が完了しました", "The command has finished"),
+        ),
+        Err(err) => notify(
+            &method,
+            &tr!("codex-sdd {name} 失敗", "codex-sdd {name} failed"),
+            &err.to_string(),
+        ),
+    }
+}
 
-    let mut handles = Vec::new();
-    for (idx, shard) in shards.iter().enumerate() {
-        if shard.is_empty() {
-            continue;
+fn acquire_locks(command: &Commands) -> Result<Vec<RepoLock>> {
+    let (name, requested) = match command {
+        Commands::Install(_)
+        | Commands::Completions(_)
+        | Commands::Config(_)
+        | Commands::Init(_)
+        | Commands::New
+        | Commands::Watch(_)
+        | Commands::Check(_)
+        | Commands::List
+        | Commands::Stats(_)
+        | Commands::Gc(_)
+        | Commands::Audit(_)
+        | Commands::Findings(FindingsCommands::List(_))
+        | Commands::Context(ContextCommands::List(_))
+        | Commands::Facts(_)
+        | Commands::Inspect(_)
+        | Commands::Conflicts(_)
+        | Commands::Spike(_)
+        | Commands::Encryption(EncryptionCommands::Cat(_)) => return Ok(Vec::new()),
+        Commands::State(_) | Commands::Encryption(EncryptionCommands::Seal) => {
+            let paths = RepoPaths::load()?;
+            let lock = RepoLock::acquire(&paths.lock_path, command_name(command))?;
+            for entry in fs::read_dir(&paths.locks_dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "lock"))
+            {
+                if let Some(holder) = RepoLock::holder(&entry.path()) {
+                    return Err(ErrorKind::LockBusy.err(tr!(
+                        "change {} の処理中です ({holder})。終了後に再実行してください",
+                        "change {} is busy ({holder}); run again once it finishes",
+                        entry
+                            .path()
+                            .file_stem()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                    )));
+                }
+            }
+            return Ok(vec![lock]);
         }
-        let shard_name = format!("reader_{idx}");
-        let shard_hash_val = shard_hash(shard);
-        let existing_hash = existing_shard_hashes.get(&shard_name).cloned();
-        let (output_path, json_path) = output_paths(&paths.runs_dir, &change_id, &shard_name);
-
-        if existing_hash == Some(shard_hash_val.clone()) && output_path.exists() {
-            log_event("info", &format!("reuse shard {idx}"));
-            continue;
+        Commands::Plans(_) => ("plans", None),
+        Commands::Review(args) => ("review", args.id.clone()),
+        Commands::Tasks(args) => ("tasks", args.id.clone()),
+        Commands::Approve(args) => ("approve", args.id.clone()),
+        Commands::Worktrees(args) => ("worktrees", args.id.clone()),
+        Commands::TestPlan(args) => ("test-plan", args.id.clone()),
+        Commands::Select(args) => ("select", args.id.clone()),
+        Commands::Finalize(args) => ("finalize", args.id.clone()),
+        Commands::Switch(args) => ("switch", Some(args.id.clone())),
+        Commands::Rename(args) => ("rename", args.id.clone()),
+        Commands::Replay(args) => ("replay", args.id.clone()),
+        Commands::Findings(FindingsCommands::Ack(args) | FindingsCommands::Resolve(args)) => {
+            ("findings", args.id.clone())
         }
-
-        let prompt_path = context_dir.join(format!("reader_prompt_{idx}.md"));
-        let prompt = render_reader_prompt(&change_id, idx, shards.len(), shard);
-        write_string(&prompt_path, &prompt)?;
-
-        let schema_path = paths.schemas_dir.join("reader.json");
-        let exec_spec = ExecSpec {
-            cwd: paths.repo_root.clone(),
-            prompt_path,
-            output_path: output_path.clone(),
-            json_output_path: Some(json_path),
-            sandbox: "read-only".to_string(),
-            schema_path: Some(schema_path),
-        };
-
-        let shard_key = shard_name.clone();
-        handles.push(std::thread::spawn(
-            move || -> Result<(String, String, bool)> {
-                let result = crate::codex::exec::run(&exec_spec)?;
-                Ok((shard_key, shard_hash_val, result.status_ok))
-            },
-        ));
+        Commands::Context(ContextCommands::Add(args)) => ("context", args.id.clone()),
+    };
+    let paths = RepoPaths::load()?;
+    if let Some(holder) = RepoLock::holder(&paths.lock_path) {
+        return Err(ErrorKind::LockBusy.err(tr!(
+            "codex-sdd state が実行中です ({holder})。終了を待ってください",
+            "codex-sdd state is running ({holder}); wait for it to finish"
+        )));
     }
-
-    for handle in handles {
-        let (shard_key, shard_hash_val, ok) = handle
-            .join()
-            .map_err(|_| anyhow!("reader thread failed"))??;
-        if !ok {
-            return Err(anyhow!("reader agent failed"));
-        }
-        state.record_thread(&change_id, &shard_key, &shard_key);
-        let change_state = state.change_state_mut(&change_id);
-        change_state
-            .reader_shard_hashes
-            .insert(shard_key, shard_hash_val);
+    if matches!(command, Commands::Plans(_)) {
+        return Ok(Vec::new());
     }
-
-    let repo_digest = compose_repo_digest(&paths, &change_id, shards.len())?;
-    write_file(&change_dir.join("repo_digest.md"), &repo_digest)?;
-    write_file(&change_dir.join("10_repo_digest.md"), &repo_digest)?;
-
-    state.save(&paths.state_path)?;
-    println!("plans 完了: {}", change_dir.display());
-    Ok(())
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, requested.as_deref())?;
+    let mut locks = vec![RepoLock::acquire(
+        &paths.change_lock_path(&change_id),
+        name,
+    )?];
+    if let Commands::Rename(args) = command {
+        locks.push(RepoLock::acquire(
+            &paths.change_lock_path(&slugify(&args.to)),
+            name,
+        )?);
+    }
+    Ok(locks)
 }
 
-fn cmd_review(args: ChangeArgs) -> Result<()> {
-    log_event("info", "review start");
+fn cmd_install(args: InstallArgs) -> Result<()> {
+    info!("install prompts and skills");
+    let global = GlobalPaths::load()?;
+    let assets = install::inspect(&global.codex_home)?;
+    let agents = RepoPaths::load()
+        .ok()
+        .map(|paths| paths.repo_root.join("AGENTS.md"))
+        .filter(|path| path.is_file());
+    let agents = match agents {
+        Some(path) => {
+            let contents = read_to_string(&path)?;
+            if has_agents_block(&contents) {
+                Some((update_agents_md(&contents)?.changed(), path))
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+    if args.list {
+        for asset in &assets {
+            println!(
+                "{:<9} {}",
+                asset.status.label(),
+                global.codex_home.join(asset.rel_path).display()
+            );
+        }
+        if let Some((stale, path)) = &agents {
+            let label = if *stale { "outdated" } else { "current" };
+            println!("{label:<9} {}", path.display());
+        }
+        return Ok(());
+    }
+    let written = install::install(&global.codex_home, &assets, args.update, args.force)?;
+    for path in &written {
+        println!("{}", tr!("作成: {}", "wrote: {}", path.display()));
+    }
+    for asset in &assets {
+        if asset.status == AssetStatus::Current
+            || asset.status.should_write(args.update, args.force)
+        {
+            continue;
+        }
+        let path = global.codex_home.join(asset.rel_path);
+        if asset.status == AssetStatus::Outdated {
+            println!(
+                "{}",
+                tr!(
+                    "更新あり: {} (--update で更新できます)",
+                    "outdated: {} (run with --update)",
+                    path.display()
+                )
+            );
+        } else {
+            println!(
+                "{}",
+                tr!(
+                    "変更あり: {} (--force で上書きできます)",
+                    "modified: {} (run with --force to overwrite)",
+                    path.display()
+                )
+            );
+        }
+    }
+    match agents {
+        Some((true, path)) if args.update || args.force => update_repo_agents(&path)?,
+        Some((true, path)) => println!(
+            "{}",
+            tr!(
+                "更新あり: {} (--update で管理ブロックを更新できます)",
+                "outdated: {} (run with --update to refresh its managed block)",
+                path.display()
+            )
+        ),
+        _ => {}
+    }
+    if !written.is_empty() {
+        println!(
+            "{}",
+            tr!(
+                "新しいCodexセッションを開いてください。",
+                "Open a new Codex session."
+            )
+        );
+    }
+    Ok(())
+}
+
+fn cmd_completions(args: CompletionsArgs) -> Result<()> {
+    let shells = Shells::builtins();
+    let shell = shells
+        .completer(&args.shell)
+        .ok_or_else(|| anyhow!("unsupported shell: {}", args.shell))?;
+    let bin = env!("CARGO_PKG_NAME");
+    shell.write_registration("COMPLETE", bin, bin, bin, &mut std::io::stdout())?;
+    Ok(())
+}
+
+fn cmd_config(args: ConfigArgs) -> Result<()> {
+    let config_path = match RepoPaths::load() {
+        Ok(paths) => paths.config_path,
+        Err(_) => PathBuf::from(".codex/sdd/config.toml"),
+    };
+    if !args.show_origin {
+        let config = Config::load(&config_path)?;
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(());
+    }
+    for entry in Config::entries(&config_path)? {
+        let origin = entry
+            .origin
+            .map(|path| format!("file:{}", path.display()))
+            .unwrap_or_else(|| "default".to_string());
+        println!("{origin}\t{} = {}", entry.key, entry.value);
+    }
+    Ok(())
+}
+
+fn change_id_candidates() -> Vec<CompletionCandidate> {
+    let Ok(paths) = RepoPaths::load() else {
+        return Vec::new();
+    };
+    let Ok(state) = State::load(&paths.state_path) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<&String> = state
+        .changes
+        .keys()
+        .filter(|id| paths.find_change_dir(id).is_ok())
+        .collect();
+    ids.sort();
+    ids.into_iter().map(CompletionCandidate::new).collect()
+}
+
+fn agent_candidates() -> Vec<CompletionCandidate> {
+    let Ok(paths) = RepoPaths::load() else {
+        return Vec::new();
+    };
+    let Ok(state) = State::load(&paths.state_path) else {
+        return Vec::new();
+    };
+    let Ok(change_id) = resolve_change_id(&paths, &state, None) else {
+        return Vec::new();
+    };
+    let mut agents: Vec<String> = fs::read_dir(paths.worktrees_dir.join(change_id))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    agents.sort();
+    agents.into_iter().map(CompletionCandidate::new).collect()
+}
+
+fn cmd_init(args: InitArgs) -> Result<()> {
+    info!("init repo scaffold");
+    let paths = RepoPaths::load()?;
+    ensure_repo_scaffold(&paths.repo_root)?;
+    let config = Config::load(&paths.config_path)?.init;
+    let template = args.template.unwrap_or(config.template);
+    let created = apply_init_template(
+        &paths.repo_root,
+        &paths.templates_dir,
+        &template,
+        &config.packages,
+    )?;
+    for path in &created {
+        let rel = path.strip_prefix(&paths.repo_root).unwrap_or(path);
+        println!("{}", tr!("作成: {}", "created: {}", rel.display()));
+    }
+    if !created.iter().any(|path| path.ends_with("AGENTS.md")) {
+        if args.update_agents {
+            update_repo_agents(&paths.repo_root.join("AGENTS.md"))?;
+        } else {
+            println!(
+                "{}",
+                tr!(
+                    "AGENTS.md は既に存在します。--update-agents で管理ブロックを更新できます。",
+                    "AGENTS.md already exists; run with --update-agents to refresh its managed block."
+                )
+            );
+        }
+    }
+    if !args.write_gitignore {
+        println!("{}", tr!(".codex/sdd/ を .gitignore に追加することを推奨します（.codex/skills は除外しないでください）。--write-gitignore で自動追加できます。", "Consider adding .codex/sdd/ to .gitignore (keep .codex/skills tracked), or rerun with --write-gitignore."));
+        return Ok(());
+    }
+    let update = ensure_gitignore(&paths.repo_root)?;
+    if update.appended.is_empty() {
+        println!(
+            "{}",
+            tr!(
+                ".gitignore には既に .codex/sdd/ の設定があります。",
+                ".gitignore already ignores .codex/sdd/."
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            tr!(
+                ".gitignore に追加しました: {}",
+                "Added to .gitignore: {}",
+                update.appended.join(" ")
+            )
+        );
+    }
+    if update.hides_skills {
+        warn!(".gitignore ignores .codex/skills; skills will not be committed");
+        println!(
+            "{}",
+            tr!(
+                ".gitignore が .codex/skills も除外しています。該当ルールを見直してください。",
+                ".gitignore also ignores .codex/skills; review that rule."
+            )
+        );
+    }
+    Ok(())
+}
+
+fn update_repo_agents(path: &Path) -> Result<()> {
+    let update = update_agents_md(&read_to_string(path)?)?;
+    if !update.changed() {
+        println!(
+            "{}",
+            tr!(
+                "AGENTS.md の管理ブロックは最新です。",
+                "AGENTS.md managed block is up to date."
+            )
+        );
+        return Ok(());
+    }
+    write_string_atomic(path, &update.contents)?;
+    if update.adopted {
+        println!(
+            "{}",
+            tr!(
+                "AGENTS.md に管理ブロックを追加しました:",
+                "Added the managed block to AGENTS.md:"
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            tr!(
+                "AGENTS.md の管理ブロックを更新しました:",
+                "Updated the managed block in AGENTS.md:"
+            )
+        );
+    }
+    write_stdout(&line_diff(&update.old_block, &update.new_block))
+}
+
+fn cmd_new() -> Result<()> {
+    RepoPaths::load()?;
+    let name = prompt_line(&tr!("change 名 > ", "Change name > "))?;
+    if name.is_empty() {
+        return Err(anyhow!(tr!(
+            "change 名は必須です",
+            "a change name is required"
+        )));
+    }
+    let goal = prompt_line(&tr!("ゴール [{name}] > ", "Goal [{name}] > "))?;
+    let scope = prompt_line(&tr!(
+        "対象の glob (カンマ区切り, 空なら全体) > ",
+        "Scope globs (comma-separated, empty for all) > "
+    ))?;
+    let agents = loop {
+        let input = prompt_line(&tr!(
+            "agent 数 (数値|auto) [4] > ",
+            "Agent count (number|auto) [4] > "
+        ))?;
+        if input.is_empty() {
+            break AgentCount::Fixed(4);
+        }
+        match input.parse::<AgentCount>() {
+            Ok(count) => break count,
+            Err(_) => println!(
+                "{}",
+                tr!(
+                    "1 以上の数値か auto を入力してください",
+                    "Enter a number of 1 or more, or auto"
+                )
+            ),
+        }
+    };
+    let coverage = loop {
+        let input = prompt_line(&tr!(
+            "coverage ツール (llvm-cov|tarpaulin|none) [llvm-cov] > ",
+            "Coverage tool (llvm-cov|tarpaulin|none) [llvm-cov] > "
+        ))?;
+        if input.is_empty() {
+            break "llvm-cov".to_string();
+        }
+        if COVERAGE_TOOLS.contains(&input.as_str()) {
+            break input;
+        }
+        println!(
+            "{}",
+            tr!(
+                "llvm-cov / tarpaulin / none のいずれかを入力してください",
+                "Enter one of llvm-cov / tarpaulin / none"
+            )
+        );
+    };
+
+    let args = PlansArgs {
+        goal: (!goal.is_empty() && goal != name).then_some(goal),
+        name: Some(name),
+        issue: None,
+        id: None,
+        agents,
+        include_untracked: false,
+        after: Vec::new(),
+        scope: scope
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        coverage: Some(coverage),
+        no_cache: false,
+        since: None,
+    };
+    let mut command = format!("codex-sdd plans --name {:?}", plans_name(&args)?);
+    if let Some(goal) = &args.goal {
+        command.push_str(&format!(" --goal {goal:?}"));
+    }
+    for glob in &args.scope {
+        command.push_str(&format!(" --scope {glob:?}"));
+    }
+    command.push_str(&format!(" --agents {}", args.agents));
+    if let Some(coverage) = &args.coverage {
+        command.push_str(&format!(" --coverage {coverage}"));
+    }
+    println!("\n{command}");
+    let confirm = prompt_line(&tr!("実行しますか? [y/N] > ", "Run it? [y/N] > "))?;
+    if !confirm.eq_ignore_ascii_case("y") {
+        println!("{}", tr!("中止しました", "Aborted"));
+        return Ok(());
+    }
+    let _locks = acquire_locks(&Commands::Plans(args.clone()))?;
+    cmd_plans(args)
+}
+
+fn plans_name(args: &PlansArgs) -> Result<String> {
+    match (&args.name, &args.issue) {
+        (Some(name), _) => Ok(name.clone()),
+        (None, Some(issue)) => Ok(IssueRef::parse(issue)?.slug()),
+        (None, None) => Err(anyhow!(tr!(
+            "--name か --issue を指定してください",
+            "pass --name or --issue"
+        ))),
+    }
+}
+
+fn cmd_plans(args: PlansArgs) -> Result<()> {
+    info!("plans start");
+    let started = Instant::now();
+    let paths = RepoPaths::load()?;
+    ensure_repo_scaffold(&paths.repo_root)?;
+    let config = Config::load(&paths.config_path)?;
+    let issue = match &args.issue {
+        Some(issue) => {
+            let issue = fetch_issue(&paths.repo_root, &IssueRef::parse(issue)?, &config.issues)?;
+            info!("linked issue {} ({})", issue.link.key, issue.link.url);
+            Some(issue)
+        }
+        None => None,
+    };
+
+    let mut state = State::load(&paths.state_path)?;
+    let name = plans_name(&args)?;
+    let name_slug = slugify(&name);
+    let goal = args
+        .goal
+        .clone()
+        .or_else(|| issue.as_ref().map(|issue| issue.link.title.clone()))
+        .filter(|goal| !goal.trim().is_empty())
+        .unwrap_or_else(|| name.clone());
+    let base_id = args.id.unwrap_or_else(|| name_slug.clone());
+    let (change_id, _lock) = reserve_change_id(&paths, &base_id, &name_slug)?;
+    for dep in &args.after {
+        if state.change_state(dep).is_none() && paths.find_archived_change_dir(dep).is_none() {
+            return Err(ErrorKind::NotFound.err(tr!(
+                "依存先の change {dep} が見つかりません",
+                "dependency change {dep} not found"
+            )));
+        }
+    }
+    let focus = match &args.since {
+        Some(since) => {
+            resolve_commit(&paths.repo_root, since).map_err(|_| {
+                ErrorKind::NotFound.err(tr!(
+                    "ref が見つかりません: {since}",
+                    "ref not found: {since}"
+                ))
+            })?;
+            let touched = git_diff_names_since(&paths.repo_root, since)?;
+            if touched.is_empty() {
+                return Err(ErrorKind::NotFound.err(tr!(
+                    "{since} 以降に変更されたファイルがありません",
+                    "no files changed since {since}"
+                )));
+            }
+            let focus = with_dependents(&paths.repo_root, &touched, args.include_untracked)?;
+            info!(
+                "since {since}: {} touched files, {} with direct dependents",
+                touched.len(),
+                focus.len()
+            );
+            Some(focus)
+        }
+        None => None,
+    };
+    let change_dir = paths.change_dir(&change_id, &name_slug);
+    ensure_change_scaffold(&change_dir, &paths.templates_dir)?;
+    if let Some(issue) = &issue {
+        write_file(
+            &change_dir.join("00_issue.md"),
+            &format!(
+                "# {}: {}\n\n{}\n\n{}\n",
+                issue.link.key,
+                issue.link.title,
+                issue.link.url,
+                issue.body.trim()
+            ),
+        )?;
+    }
+
+    let index_result = build_index(
+        &paths.repo_root,
+        args.include_untracked,
+        &args.scope,
+        focus.as_ref(),
+    )?;
+    let context_dir = paths.change_context_dir(&change_dir);
+    let index_path = context_dir.join("file_index.json");
+    let tree_path = context_dir.join("repo_tree.txt");
+    write_index(&index_path, &index_result.index)?;
+    write_repo_tree(&tree_path, &index_result.repo_tree)?;
+
+    {
+        let change_state = state.change_state_mut(&change_id);
+        change_state.goal = Some(goal.clone());
+        change_state.issue = issue.as_ref().map(|issue| issue.link.clone());
+        change_state.scope.clone_from(&args.scope);
+        change_state.since.clone_from(&args.since);
+        change_state.coverage.clone_from(&args.coverage);
+        change_state
+            .file_hashes
+            .clone_from(&index_result.file_hashes);
+        change_state.file_index_hash = Some(index_result.index_hash.clone());
+        change_state.file_index_generated_at = Some(now_rfc3339());
+        for dep in &args.after {
+            if !change_state.depends_on.contains(dep) {
+                change_state.depends_on.push(dep.clone());
+            }
+        }
+    }
+    ensure_schemas(&paths)?;
+
+    let budget = Budget::from_config(&config, None)?;
+    let (agents, rationale) = match args.agents {
+        AgentCount::Fixed(count) => (count, None),
+        AgentCount::Auto => {
+            let auto = budget.auto_agents(&index_result.index, &config.plans);
+            println!(
+                "{}",
+                tr!(
+                    "agent 数を {} に自動設定しました ({})",
+                    "auto-selected {} agents ({})",
+                    auto.count,
+                    auto.rationale
+                )
+            );
+            (auto.count, Some(auto.rationale))
+        }
+    };
+    let shards = budget.fit_shards(&index_result.index, agents)?;
+    state.change_state_mut(&change_id).reader_shards = Some(shards.len());
+    let prompts = PromptRenderer::new(
+        &paths,
+        &change_id,
+        &change_dir,
+        state.change_state(&change_id),
+    )?;
+    let stale = stale_shards(&paths, &state, &change_id, &shards);
+    let readers_started = Instant::now();
+    let runs = run_readers(
+        &paths,
+        &mut state,
+        &change_id,
+        &prompts,
+        &shards,
+        &stale,
+        !args.no_cache,
+    )?;
+    let readers_secs = readers_started.elapsed().as_secs_f64();
+
+    let (header, sections) =
+        compose_repo_digest(&paths, &change_id, &shards, args.since.as_deref())?;
+    digest::write_digest(&change_dir, &header, &sections, &config.digest)?;
+    write_file_facts(&paths, &change_id, &change_dir, &shards)?;
+    let mut manifest = PlanManifest::build(
+        &paths.repo_root,
+        &paths.runs_dir,
+        &ManifestRun {
+            change_id: &change_id,
+            command: "plans",
+            shards: &shards,
+            runs: &runs,
+            readers_secs,
+            duration_secs: started.elapsed().as_secs_f64(),
+        },
+    );
+    manifest.agents = Some(args.agents.to_string());
+    manifest.agents_rationale = rationale.clone();
+    manifest.index_hash = Some(index_result.index_hash.clone());
+    let manifest_path = manifest.save(&paths.runs_dir)?;
+
+    state.save_change(&paths.state_path, &change_id)?;
+    State::activate(&paths.state_path, &change_id)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        "plans",
+        &[
+            ("readers", &shards.len().to_string()),
+            ("agents", &args.agents.to_string()),
+            ("agents_rationale", rationale.as_deref().unwrap_or("")),
+            ("after", &args.after.join(",")),
+            (
+                "issue",
+                issue.as_ref().map_or("", |issue| issue.link.url.as_str()),
+            ),
+        ],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "plan manifest: {}",
+            "plan manifest: {}",
+            manifest_path.display()
+        )
+    );
+    println!(
+        "{}",
+        tr!("plans 完了: {}", "plans done: {}", change_dir.display())
+    );
+    Ok(())
+}
+
+fn reader_shard_count(change: &ChangeState) -> usize {
+    change.reader_shards.unwrap_or_else(|| {
+        change
+            .reader_shard_hashes
+            .keys()
+            .filter_map(|key| key.strip_prefix("reader_")?.parse::<usize>().ok())
+            .max()
+            .map_or(4, |idx| idx + 1)
+    })
+}
+
+fn cmd_watch(args: WatchArgs) -> Result<()> {
+    info!("watch start");
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    if !paths
+        .change_context_dir(&change_dir)
+        .join("file_index.json")
+        .exists()
+    {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "file_index.json がありません。先に plans を実行してください",
+            "file_index.json not found; run plans first"
+        )));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).with_context(|| "start file watcher")?;
+    watcher
+        .watch(&paths.repo_root, RecursiveMode::Recursive)
+        .with_context(|| format!("watch {}", paths.repo_root.display()))?;
+    println!(
+        "{}",
+        tr!(
+            "{} を監視しています (Ctrl-C で終了)",
+            "Watching {} (Ctrl-C to stop)",
+            paths.repo_root.display()
+        )
+    );
+
+    let debounce = Duration::from_millis(args.debounce_ms);
+    let mut pending = BTreeSet::new();
+    loop {
+        let received = if pending.is_empty() {
+            Some(rx.recv().map_err(|_| anyhow!("file watcher stopped"))?)
+        } else {
+            match rx.recv_timeout(debounce) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return Err(anyhow!("file watcher stopped")),
+            }
+        };
+        let Some(event) = received else {
+            let changed: Vec<String> = std::mem::take(&mut pending).into_iter().collect();
+            if let Err(err) =
+                refresh_watched_change(&paths, &change_id, &change_dir, &changed, &args)
+            {
+                warn!("watch refresh failed: {err:#}");
+                if error_kind(&err) == Some(ErrorKind::LockBusy) {
+                    pending.extend(changed);
+                }
+            }
+            continue;
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                warn!("file watcher error: {err}");
+                continue;
+            }
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+        for path in &event.paths {
+            if path.starts_with(&change_dir) {
+                continue;
+            }
+            let Ok(rel) = path.strip_prefix(&paths.repo_root) else {
+                continue;
+            };
+            match normalize_path(rel) {
+                Ok(rel) if !should_exclude(&rel) => {
+                    pending.insert(rel);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn refresh_watched_change(
+    paths: &RepoPaths,
+    change_id: &str,
+    change_dir: &Path,
+    changed: &[String],
+    args: &WatchArgs,
+) -> Result<()> {
+    let _lock = RepoLock::acquire_waiting(
+        &paths.change_lock_path(change_id),
+        "watch",
+        WATCH_LOCK_TIMEOUT,
+    )?;
+    let mut state = State::load(&paths.state_path)?;
+    let change = state.change_state(change_id).cloned().unwrap_or_default();
+    let context_dir = paths.change_context_dir(change_dir);
+    let index_path = context_dir.join("file_index.json");
+    let mut index = read_index(&index_path)?;
+    let updated = update_index(
+        &paths.repo_root,
+        &mut index,
+        changed,
+        args.include_untracked,
+        &change.scope,
+    )?;
+    if updated.is_empty() {
+        return Ok(());
+    }
+    let index_result = index_result(index);
+    write_index(&index_path, &index_result.index)?;
+    write_repo_tree(&context_dir.join("repo_tree.txt"), &index_result.repo_tree)?;
+    {
+        let change_state = state.change_state_mut(change_id);
+        change_state.file_hashes = index_result.file_hashes;
+        change_state.file_index_hash = Some(index_result.index_hash);
+        change_state.file_index_generated_at = Some(now_rfc3339());
+    }
+    println!(
+        "{}",
+        tr!(
+            "{} ファイルを再ハッシュしました: {}",
+            "re-hashed {} files: {}",
+            updated.len(),
+            updated.join(", ")
+        )
+    );
+
+    let shards = shard_files(&index_result.index, reader_shard_count(&change));
+    let stale = stale_shards(paths, &state, change_id, &shards);
+    if stale.is_empty() {
+        println!(
+            "{}",
+            tr!("shard はすべて最新です", "all shards are up to date")
+        );
+    } else {
+        let names: Vec<String> = stale.iter().map(|idx| format!("reader_{idx}")).collect();
+        println!(
+            "{}",
+            tr!("古くなった shard: {}", "stale shards: {}", names.join(", "))
+        );
+        if args.rerun {
+            ensure_schemas(paths)?;
+            let prompts = PromptRenderer::new(paths, change_id, change_dir, Some(&change))?;
+            let started = Instant::now();
+            let runs = run_readers(
+                paths, &mut state, change_id, &prompts, &shards, &stale, true,
+            )?;
+            let readers_secs = started.elapsed().as_secs_f64();
+            let (header, sections) =
+                compose_repo_digest(paths, change_id, &shards, change.since.as_deref())?;
+            let config = Config::load(&paths.config_path)?;
+            let digest_path = digest::write_digest(change_dir, &header, &sections, &config.digest)?;
+            write_file_facts(paths, change_id, change_dir, &shards)?;
+            let mut manifest = PlanManifest::build(
+                &paths.repo_root,
+                &paths.runs_dir,
+                &ManifestRun {
+                    change_id,
+                    command: "watch",
+                    shards: &shards,
+                    runs: &runs,
+                    readers_secs,
+                    duration_secs: started.elapsed().as_secs_f64(),
+                },
+            );
+            manifest.index_hash = state
+                .change_state(change_id)
+                .and_then(|change| change.file_index_hash.clone());
+            manifest.save(&paths.runs_dir)?;
+            audit_log::append(
+                &paths.audit_log_path,
+                Some(change_id),
+                "watch",
+                &[("readers", &names.join(","))],
+            )?;
+            println!(
+                "{}",
+                tr!(
+                    "digest を更新しました: {}",
+                    "digest updated: {}",
+                    digest_path.display()
+                )
+            );
+        }
+    }
+    state.save_change(&paths.state_path, change_id)
+}
+
+fn stale_shards(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    shards: &[Vec<FileEntry>],
+) -> Vec<usize> {
+    let existing = state
+        .change_state(change_id)
+        .map(|c| c.reader_shard_hashes.clone())
+        .unwrap_or_default();
+    let mut stale = Vec::new();
+    for (idx, shard) in shards.iter().enumerate() {
+        if shard.is_empty() {
+            continue;
+        }
+        let shard_name = format!("reader_{idx}");
+        let (output_path, _) = output_paths(&paths.runs_dir, change_id, &shard_name);
+        if existing.get(&shard_name) == Some(&shard_hash(shard)) && output_path.exists() {
+            info!("reuse shard {idx}");
+            continue;
+        }
+        stale.push(idx);
+    }
+    stale
+}
+
+struct ReaderJob {
+    idx: usize,
+    shard_key: String,
+    shard_hash: String,
+    cache_key: String,
+    output_path: PathBuf,
+    spec: ExecSpec,
+    span: tracing::Span,
+    progress: Progress,
+}
+
+fn run_readers(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    prompts: &PromptRenderer,
+    shards: &[Vec<FileEntry>],
+    stale: &[usize],
+    use_cache: bool,
+) -> Result<BTreeMap<usize, Option<f64>>> {
+    ensure_dir(&paths.runs_dir.join(change_id))?;
+    let context_dir = paths.change_context_dir(&prompts.change_dir);
+    let config = Config::load(&paths.config_path)?;
+    let progress = ProgressGroup::new();
+    let mut jobs = Vec::new();
+    let mut runs = BTreeMap::new();
+    for &idx in stale {
+        let shard = &shards[idx];
+        let shard_name = format!("reader_{idx}");
+        let shard_hash_val = shard_hash(shard);
+        let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, &shard_name)?;
+        let files: Vec<&str> = shard.iter().map(|entry| entry.path.as_str()).collect();
+        let relevant_specs = prompts.relevant_specs(&files);
+        let schema_path = paths.schemas_dir.join("reader.json");
+        let cache_key = reader_cache_key(
+            &config,
+            prompts,
+            &shard_hash_val,
+            &relevant_specs,
+            &schema_path,
+        )?;
+        let cache_path = paths.reader_cache_path(&cache_key);
+        if use_cache && config.cache.readers && cache_path.exists() {
+            info!("reuse shard {idx} from reader cache");
+            fs::copy(&cache_path, &output_path).with_context(|| {
+                format!("copy {} to {}", cache_path.display(), output_path.display())
+            })?;
+            commit_latest(&output_path)?;
+            state
+                .change_state_mut(change_id)
+                .reader_shard_hashes
+                .insert(shard_name, shard_hash_val);
+            runs.insert(idx, None);
+            continue;
+        }
+
+        let prompt_path = context_dir.join(format!("reader_prompt_{idx}.md"));
+        let prompt = prompts.render(
+            "reader.md",
+            context! {
+                shard => format!("{}/{}", idx + 1, shards.len()),
+                shard_index => idx + 1,
+                shard_total => shards.len(),
+                relevant_specs => relevant_specs,
+                files => files,
+            },
+        )?;
+        write_string(&prompt_path, &prompt)?;
+
+        let inputs: Vec<PathBuf> = files
+            .iter()
+            .map(|file| paths.repo_root.join(file))
+            .collect();
+        let exec_spec = ExecSpec {
+            cwd: paths.repo_root.clone(),
+            prompt_path,
+            output_path: output_path.clone(),
+            json_output_path: Some(json_path),
+            sandbox: "read-only".to_string(),
+            schema_path: Some(schema_path),
+            model: config.model.clone(),
+            env: config.exec.clone(),
+            backend: stage_backend(&config, prompts, &inputs)?,
+        };
+
+        let span = info_span!("reader", shard = idx);
+        let reader_progress = progress.spinner(&shard_name);
+        reader_progress.set_message(&format!("{} files", shard.len()));
+        jobs.push(ReaderJob {
+            idx,
+            shard_key: shard_name,
+            shard_hash: shard_hash_val,
+            cache_key,
+            output_path,
+            spec: exec_spec,
+            span,
+            progress: reader_progress,
+        });
+    }
+
+    let workers = config.plans.max_parallel.max(1).min(jobs.len());
+    let queue = Mutex::new(jobs.into_iter());
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let (tx, queue) = (tx.clone(), &queue);
+            scope.spawn(move || loop {
+                let next = queue.lock().map(|mut q| q.next()).unwrap_or(None);
+                let Some(job) = next else {
+                    break;
+                };
+                let _span = job.span.entered();
+                let result = crate::codex::exec::run(&job.spec);
+                job.progress.finish(match &result {
+                    Ok(r) if r.status_ok => "done",
+                    _ => "failed",
+                });
+                let done = (
+                    job.idx,
+                    job.shard_key,
+                    job.shard_hash,
+                    job.cache_key,
+                    job.output_path,
+                    result,
+                );
+                if tx.send(done).is_err() {
+                    break;
+                }
+            });
+        }
+    });
+    drop(tx);
+    let mut finished: Vec<_> = rx.into_iter().collect();
+    finished.sort_by_key(|(idx, ..)| *idx);
+
+    for (idx, shard_key, shard_hash_val, cache_key, output_path, result) in finished {
+        let result = result?;
+        if !result.status_ok {
+            return Err(ErrorKind::AgentFailed.err("reader agent failed"));
+        }
+        state.record_thread(change_id, &shard_key, &shard_key, result.duration_secs);
+        runs.insert(idx, Some(result.duration_secs));
+        if config.cache.readers {
+            write_string_atomic(
+                &paths.reader_cache_path(&cache_key),
+                &read_to_string(&output_path)?,
+            )?;
+        }
+        let change_state = state.change_state_mut(change_id);
+        change_state
+            .reader_shard_hashes
+            .insert(shard_key, shard_hash_val);
+    }
+    Ok(runs)
+}
+
+fn reader_cache_key(
+    config: &Config,
+    prompts: &PromptRenderer,
+    shard_hash: &str,
+    relevant_specs: &[SpecExcerpt],
+    schema_path: &Path,
+) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(shard_hash.as_bytes());
+    hasher.update(config.backend.as_bytes());
+    let model = match config.backend.as_str() {
+        "openai" => config.openai.model.as_ref().or(config.model.as_ref()),
+        _ => config.model.as_ref(),
+    };
+    hasher.update(model.map_or("", String::as_str).as_bytes());
+    hasher.update(format!("{:?}", i18n::lang()).as_bytes());
+    for template in ["reader.md", "context.md"] {
+        hasher.update(prompts.template_source(template)?.as_bytes());
+    }
+    hasher.update(serde_json::to_string(relevant_specs)?.as_bytes());
+    if schema_path.exists() {
+        hasher.update(read_to_string(schema_path)?.as_bytes());
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn cmd_review(args: ReviewArgs) -> Result<()> {
+    info!("review start");
     let paths = RepoPaths::load()?;
     let mut state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
     let change_dir = paths.find_change_dir(&change_id)?;
     ensure_schemas(&paths)?;
     ensure_dir(&paths.runs_dir.join(&change_id))?;
+    if !args.files.is_empty() {
+        return review_files(&paths, &mut state, &change_id, &change_dir, &args.files);
+    }
+
+    review_stage(
+        &paths,
+        &mut state,
+        &change_id,
+        &change_dir,
+        args.against_specs,
+        args.debaters,
+    )?;
+    println!(
+        "{}",
+        tr!("review 完了: {}", "review done: {}", change_dir.display())
+    );
+    Ok(())
+}
+
+fn review_stage(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+    against_specs: bool,
+    debaters: Option<u32>,
+) -> Result<()> {
+    let prompts = PromptRenderer::new(paths, change_id, change_dir, state.change_state(change_id))?;
+    let requirements = if against_specs {
+        let mut specs = prompts.relevant_specs(&[]);
+        if specs.is_empty() {
+            specs = prompts.specs().to_vec();
+        }
+        let spec_paths: Vec<String> = specs.into_iter().map(|spec| spec.path).collect();
+        let requirements = load_requirements(&paths.repo_root, &spec_paths)?;
+        if requirements.is_empty() {
+            return Err(ErrorKind::NotFound.err(tr!(
+                "docs/sdd/specs に要件が見つかりません (`## Requirement: ...` または `## REQ-1 ...` の見出しが必要です)",
+                "no requirements found in docs/sdd/specs (expected `## Requirement: ...` or `## REQ-1 ...` headings)"
+            )));
+        }
+        requirements
+    } else {
+        Vec::new()
+    };
+    let vars = context! { against_specs, requirements => &requirements };
+    let schema_path = paths.schemas_dir.join(if against_specs {
+        "review_specs.json"
+    } else {
+        "review.json"
+    });
+    let mut contents = if let Some(debaters) = debaters {
+        let consensus = review_debate(
+            paths,
+            state,
+            change_id,
+            &prompts,
+            vars,
+            &schema_path,
+            debaters,
+        );
+        state.save_change(&paths.state_path, change_id)?;
+        consensus?
+    } else {
+        let prompt = prompts.render("review.md", vars)?;
+        let config = Config::load(&paths.config_path)?;
+        let digest_files = digest::digest_files(change_dir);
+        let files: Vec<&Path> = digest_files.iter().map(PathBuf::as_path).collect();
+        preflight_prompt(&config, &prompts, "review", &prompt, &files)?;
+        let prompt_path = paths
+            .change_context_dir(change_dir)
+            .join("review_prompt.md");
+        write_string(&prompt_path, &prompt)?;
+
+        let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, "review")?;
+        let exec_spec = ExecSpec {
+            cwd: paths.repo_root.clone(),
+            prompt_path,
+            output_path: output_path.clone(),
+            json_output_path: Some(json_path),
+            sandbox: "read-only".to_string(),
+            schema_path: Some(schema_path),
+            model: config.model.clone(),
+            env: config.exec.clone(),
+            backend: stage_backend(&config, &prompts, &digest_files)?,
+        };
+
+        let result = crate::codex::exec::run(&exec_spec)?;
+        if !result.status_ok {
+            return Err(ErrorKind::AgentFailed.err("review agent failed"));
+        }
+        state.record_thread(change_id, "review", "review", result.duration_secs);
+        state.save_change(&paths.state_path, change_id)?;
+        read_to_string(&output_path)?
+    };
+    if against_specs {
+        let compliance = build_compliance(&requirements, &contents)
+            .map_err(|err| ErrorKind::SchemaViolation.err(format!("{err:#}")))?;
+        write_string(
+            &paths.runs_dir.join(change_id).join("compliance.json"),
+            &serde_json::to_string_pretty(&compliance)?,
+        )?;
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push('\n');
+        contents.push_str(&render_compliance(&compliance));
+    }
+    write_file(&change_dir.join("20_review.md"), &contents)
+}
+
+fn review_files(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+    patterns: &[String],
+) -> Result<()> {
+    let index_path = paths.change_context_dir(change_dir).join("file_index.json");
+    if !index_path.exists() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "file_index.json がありません。先に plans を実行してください",
+            "file_index.json not found; run plans first"
+        )));
+    }
+    let index = read_index(&index_path)?;
+    let files = match_files(&index, patterns)?;
+    if files.is_empty() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{} に一致するファイルが file index にありません",
+            "no indexed files match {}",
+            patterns.join(", ")
+        )));
+    }
+
+    let change = state.change_state(change_id).cloned().unwrap_or_default();
+    let shards = shard_files(&index, reader_shard_count(&change));
+    let readers: Vec<PathBuf> = shards
+        .iter()
+        .enumerate()
+        .filter(|(_, shard)| {
+            shard
+                .iter()
+                .any(|entry| files.iter().any(|file| file.path == entry.path))
+        })
+        .map(|(idx, _)| output_paths(&paths.runs_dir, change_id, &format!("reader_{idx}")).0)
+        .filter(|path| path.exists())
+        .collect();
+    let readers = crypto::plain_copies(readers)?;
+    let file_paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+    let reader_paths: Vec<String> = readers
+        .paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+
+    let prompts = PromptRenderer::new(paths, change_id, change_dir, Some(&change))?;
+    let prompt = prompts.render(
+        "review_files.md",
+        context! { patterns => patterns, files => &file_paths, readers => &reader_paths },
+    )?;
+    let config = Config::load(&paths.config_path)?;
+    let mut inputs: Vec<PathBuf> = file_paths
+        .iter()
+        .map(|file| paths.repo_root.join(file))
+        .collect();
+    inputs.extend(readers.paths.iter().cloned());
+    preflight_prompt(
+        &config,
+        &prompts,
+        "review-files",
+        &prompt,
+        &inputs.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+    )?;
+    let prompt_path = paths
+        .change_context_dir(change_dir)
+        .join("review_files_prompt.md");
+    write_string(&prompt_path, &prompt)?;
+
+    let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, "review_files")?;
+    let exec_spec = ExecSpec {
+        cwd: paths.repo_root.clone(),
+        prompt_path,
+        output_path: output_path.clone(),
+        json_output_path: Some(json_path),
+        sandbox: "read-only".to_string(),
+        schema_path: Some(paths.schemas_dir.join("review.json")),
+        model: config.model.clone(),
+        env: config.exec.clone(),
+        backend: stage_backend(&config, &prompts, &inputs)?,
+    };
+    let result = crate::codex::exec::run(&exec_spec)?;
+    if !result.status_ok {
+        return Err(ErrorKind::AgentFailed.err("scoped review agent failed"));
+    }
+    state.record_thread(change_id, "review", "review_files", result.duration_secs);
+    state.save_change(&paths.state_path, change_id)?;
+
+    let review_path = change_dir.join("20_review.md");
+    let mut contents = if review_path.exists() {
+        read_to_string(&review_path)?
+    } else {
+        String::new()
+    };
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(&format!(
+        "\n{} {} ({})\n\n{}\n",
+        findings::SCOPED_REVIEW_HEADING,
+        patterns.join(", "),
+        now_rfc3339(),
+        read_to_string(&output_path)?.trim()
+    ));
+    write_file(&review_path, &contents)?;
+    println!(
+        "{}",
+        tr!(
+            "スコープ付き review 完了: {} ファイル ({})",
+            "scoped review done: {} files ({})",
+            files.len(),
+            review_path.display()
+        )
+    );
+    Ok(())
+}
+
+fn review_debate(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    prompts: &PromptRenderer,
+    vars: minijinja::Value,
+    schema_path: &Path,
+    debaters: u32,
+) -> Result<String> {
+    let config = Config::load(&paths.config_path)?;
+    let context_dir = paths.change_context_dir(&prompts.change_dir);
+    let debate_dir = prompts.change_dir.join("debate");
+    let digest_files = digest::digest_files(&prompts.change_dir);
+    let digest_files: Vec<&Path> = digest_files.iter().map(PathBuf::as_path).collect();
+    ensure_dir(&debate_dir)?;
+
+    let mut specs = Vec::new();
+    for debater in 1..=debaters {
+        let name = format!("review_{debater}");
+        let prompt_path = context_dir.join(format!("{name}_prompt.md"));
+        let prompt = prompts.render("review.md", context! { debater, ..vars.clone() })?;
+        preflight_prompt(&config, prompts, &name, &prompt, &digest_files)?;
+        write_string(&prompt_path, &prompt)?;
+        specs.push((
+            name.clone(),
+            debate_spec(
+                paths,
+                change_id,
+                &config,
+                &name,
+                prompt_path,
+                schema_path,
+                stage_backend(&config, prompts, &digest_files)?,
+            )?,
+        ));
+    }
+    info!("review debate: round 1 with {debaters} debaters");
+    let reviews = debate::run_round(specs)?;
+    for run in &reviews {
+        state.record_thread(change_id, "review", &run.name, run.duration_secs);
+        write_file(&debate_dir.join(format!("{}.md", run.name)), &run.output)?;
+    }
+
+    let candidates = debate::candidates(&reviews)
+        .map_err(|err| ErrorKind::SchemaViolation.err(format!("{err:#}")))?;
+    let ballots = if candidates.is_empty() {
+        Vec::new()
+    } else {
+        let mut specs = Vec::new();
+        for debater in 1..=debaters {
+            let name = format!("review_{debater}");
+            let prompt_path = context_dir.join(format!("{name}_votes_prompt.md"));
+            let prompt = prompts.render(
+                "review_debate.md",
+                context! { debater, findings => &candidates, ..vars.clone() },
+            )?;
+            preflight_prompt(
+                &config,
+                prompts,
+                &format!("{name}_votes"),
+                &prompt,
+                &digest_files,
+            )?;
+            write_string(&prompt_path, &prompt)?;
+            specs.push((
+                name.clone(),
+                debate_spec(
+                    paths,
+                    change_id,
+                    &config,
+                    &format!("{name}_votes"),
+                    prompt_path,
+                    &paths.schemas_dir.join("review_votes.json"),
+                    stage_backend(&config, prompts, &digest_files)?,
+                )?,
+            ));
+        }
+        info!("review debate: round 2 on {} findings", candidates.len());
+        debate::run_round(specs)?
+    };
+    for run in &ballots {
+        let name = format!("{}_votes", run.name);
+        state.record_thread(change_id, "review", &name, run.duration_secs);
+        write_file(&debate_dir.join(format!("{name}.md")), &run.output)?;
+    }
+
+    let tallies = debate::tally(&candidates, &ballots)
+        .map_err(|err| ErrorKind::SchemaViolation.err(format!("{err:#}")))?;
+    write_file(
+        &debate_dir.join("review_consensus.md"),
+        &debate::render_review_debate(&tallies, debaters as usize),
+    )?;
+    debate::review_consensus(&tallies, &reviews)
+        .map_err(|err| ErrorKind::SchemaViolation.err(format!("{err:#}")))
+}
+
+fn debate_spec(
+    paths: &RepoPaths,
+    change_id: &str,
+    config: &Config,
+    name: &str,
+    prompt_path: PathBuf,
+    schema_path: &Path,
+    backend: Backend,
+) -> Result<ExecSpec> {
+    let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, name)?;
+    Ok(ExecSpec {
+        cwd: paths.repo_root.clone(),
+        prompt_path,
+        output_path,
+        json_output_path: Some(json_path),
+        sandbox: "read-only".to_string(),
+        schema_path: Some(schema_path.to_path_buf()),
+        model: config.model.clone(),
+        env: config.exec.clone(),
+        backend,
+    })
+}
+
+fn stage_backend<P: AsRef<Path>>(
+    config: &Config,
+    prompts: &PromptRenderer,
+    files: &[P],
+) -> Result<Backend> {
+    let mut inputs: Vec<PathBuf> = files
+        .iter()
+        .map(|file| file.as_ref().to_path_buf())
+        .collect();
+    inputs.extend(prompts.refs().iter().cloned());
+    inputs.push(prompts.change_dir.join("00_issue.md"));
+    Backend::for_stage(config, inputs)
+}
+
+fn preflight_prompt(
+    config: &Config,
+    prompts: &PromptRenderer,
+    stage: &str,
+    prompt: &str,
+    files: &[&Path],
+) -> Result<()> {
+    let refs: Vec<&Path> = prompts.refs().iter().map(PathBuf::as_path).collect();
+    let estimate = preflight::estimate_text(prompt)
+        + preflight::estimate_files(files)
+        + preflight::estimate_files(&refs);
+    Budget::from_config(config, None)?.check(stage, estimate)
+}
+
+fn cmd_tasks(args: TasksArgs) -> Result<()> {
+    info!("tasks start");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    ensure_schemas(&paths)?;
+    ensure_dir(&paths.runs_dir.join(&change_id))?;
+    if !args.split.is_empty() {
+        let mut ids: Vec<String> = Vec::new();
+        for id in args.split {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        return tasks_split(&paths, &mut state, &change_id, &change_dir, &ids);
+    }
+
+    tasks_stage(&paths, &mut state, &change_id, &change_dir)?;
+    println!(
+        "{}",
+        tr!("tasks 完了: {}", "tasks done: {}", change_dir.display())
+    );
+    Ok(())
+}
+
+fn tasks_stage(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+) -> Result<()> {
+    let prompts = PromptRenderer::new(paths, change_id, change_dir, state.change_state(change_id))?;
+    let prompt = prompts.render("tasks.md", context! {})?;
+    let config = Config::load(&paths.config_path)?;
+    let mut files = digest::digest_files(change_dir);
+    files.push(change_dir.join("20_review.md"));
+    let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+    preflight_prompt(&config, &prompts, "tasks", &prompt, &files)?;
+    let prompt_path = paths.change_context_dir(change_dir).join("tasks_prompt.md");
+    write_string(&prompt_path, &prompt)?;
+
+    let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, "tasks")?;
+    let exec_spec = ExecSpec {
+        cwd: paths.repo_root.clone(),
+        prompt_path,
+        output_path: output_path.clone(),
+        json_output_path: Some(json_path),
+        sandbox: "read-only".to_string(),
+        schema_path: Some(paths.schemas_dir.join("tasks.json")),
+        model: config.model.clone(),
+        env: config.exec.clone(),
+        backend: stage_backend(&config, &prompts, &files)?,
+    };
+
+    let result = crate::codex::exec::run(&exec_spec)?;
+    if !result.status_ok {
+        return Err(ErrorKind::AgentFailed.err("tasks agent failed"));
+    }
+    state.record_thread(change_id, "tasks", "tasks", result.duration_secs);
+    state.save_change(&paths.state_path, change_id)?;
+
+    let contents = read_to_string(&output_path)?;
+    write_file(&change_dir.join("40_tasks.md"), &contents)
+}
+
+fn tasks_split(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+    ids: &[String],
+) -> Result<()> {
+    let tasks_path = change_dir.join("40_tasks.md");
+    if !tasks_path.exists() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "40_tasks.md がありません。先に codex-sdd tasks を実行してください",
+            "40_tasks.md is missing; run codex-sdd tasks first"
+        )));
+    }
+    let list = tasks::parse_tasks(&read_to_string(&tasks_path)?).map_err(|err| {
+        ErrorKind::SchemaViolation.err(tr!(
+            "40_tasks.md を JSON として読めません: {err:#}",
+            "cannot read 40_tasks.md as JSON: {err:#}"
+        ))
+    })?;
+    let selected = tasks::select(&list, ids)?;
+
+    let prompts = PromptRenderer::new(paths, change_id, change_dir, state.change_state(change_id))?;
+    let prompt = prompts.render("tasks_split.md", context! { tasks => selected })?;
+    let config = Config::load(&paths.config_path)?;
+    preflight_prompt(&config, &prompts, "tasks-split", &prompt, &[&tasks_path])?;
+    let prompt_path = paths
+        .change_context_dir(change_dir)
+        .join("tasks_split_prompt.md");
+    write_string(&prompt_path, &prompt)?;
+
+    let (output_path, json_path) = new_output_paths(&paths.runs_dir, change_id, "tasks_split")?;
+    let exec_spec = ExecSpec {
+        cwd: paths.repo_root.clone(),
+        prompt_path,
+        output_path: output_path.clone(),
+        json_output_path: Some(json_path),
+        sandbox: "read-only".to_string(),
+        schema_path: Some(paths.schemas_dir.join("tasks.json")),
+        model: config.model.clone(),
+        env: config.exec.clone(),
+        backend: stage_backend(&config, &prompts, &[&tasks_path])?,
+    };
+    let result = crate::codex::exec::run(&exec_spec)?;
+    if !result.status_ok {
+        return Err(ErrorKind::AgentFailed.err("tasks split agent failed"));
+    }
+    state.record_thread(
+        change_id,
+        "tasks-split",
+        "tasks-split",
+        result.duration_secs,
+    );
+    state.save_change(&paths.state_path, change_id)?;
+
+    let output = tasks::parse_tasks(&read_to_string(&output_path)?).map_err(|err| {
+        ErrorKind::SchemaViolation.err(tr!(
+            "分割結果が tasks スキーマに沿っていません: {err:#}",
+            "split output does not match the tasks schema: {err:#}"
+        ))
+    })?;
+    let merged = tasks::merge_split(&list, ids, &output)?;
+    let contents = serde_json::to_string_pretty(&merged)?;
+    write_file(&tasks_path, &contents)?;
+    let split_output = new_output_paths(&paths.runs_dir, change_id, "tasks")?.0;
+    write_string(&split_output, &contents)?;
+    commit_latest(&split_output)?;
+    let added = merged.tasks.len() + ids.len() - list.tasks.len();
+    println!(
+        "{}",
+        tr!(
+            "tasks 分割完了: {} -> {added} 件のサブタスク ({})",
+            "tasks split done: {} -> {added} subtasks ({})",
+            ids.join(", "),
+            tasks_path.display()
+        )
+    );
+    Ok(())
+}
+
+fn cmd_approve(args: ApproveArgs) -> Result<()> {
+    info!("approve change");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+
+    let config = Config::load(&paths.config_path)?;
+    let policy = config.approvals;
+    let approved_by = args.by.unwrap_or_else(current_user);
+    if !args.revoke && !policy.allowed.is_empty() && !policy.allowed.contains(&approved_by) {
+        return Err(anyhow!(tr!(
+            "{approved_by} は承認者として許可されていません (approvals.allowed: {})",
+            "{approved_by} is not an allowed approver (approvals.allowed: {})",
+            policy.allowed.join(", ")
+        )));
+    }
+    let owners = Owners::load(&paths.repo_root, &config.owners)?;
+    let change = state.change_state(&change_id).cloned().unwrap_or_default();
+    let owned = owners.route(&touched_paths(&paths, &change_id, &change_dir, &change)?);
+    if args.revoke {
+        let reason = args.reason.ok_or_else(|| {
+            anyhow!(tr!(
+                "--revoke には --reason が必要です",
+                "--revoke requires --reason"
+            ))
+        })?;
+        state.revoke_approval(&change_id, &approved_by, &reason);
+        state.save_change(&paths.state_path, &change_id)?;
+        audit_log::append(
+            &paths.audit_log_path,
+            Some(&change_id),
+            "revoke",
+            &[("revoked_by", &approved_by), ("reason", &reason)],
+        )?;
+    } else {
+        if args.reason.is_some() && !args.override_owners {
+            return Err(anyhow!(tr!(
+                "--reason は --revoke か --override-owners と一緒に指定してください",
+                "--reason requires --revoke or --override-owners"
+            )));
+        }
+        let enforce = config.owners.enforce && !owned.is_empty();
+        if enforce
+            && !args.override_owners
+            && !owned
+                .iter()
+                .any(|group| owners.is_owner(&approved_by, &group.owners))
+        {
+            let listed: BTreeSet<&String> = owned.iter().flat_map(|group| &group.owners).collect();
+            let listed = listed
+                .into_iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(ErrorKind::ApprovalRequired.err(tr!(
+                "{approved_by} は変更対象のパスの owner ではありません ({listed})。owner に承認を依頼するか、--override-owners --reason で理由を記録してください",
+                "{approved_by} does not own any touched path ({listed}); ask an owner to approve, or record an override with --override-owners --reason"
+            )));
+        }
+        let artifacts_hash = approved_artifacts_hash(&change_dir)?;
+        state.approve_change(&change_id, &approved_by, policy.required, &artifacts_hash);
+        let pending = if enforce && args.override_owners {
+            pending_owner_groups(&owners, &owned, &state, &change_id)
+        } else {
+            Vec::new()
+        };
+        if !pending.is_empty() {
+            let reason = args.reason.as_deref().ok_or_else(|| {
+                anyhow!(tr!(
+                    "--override-owners には --reason が必要です",
+                    "--override-owners requires --reason"
+                ))
+            })?;
+            let keys: Vec<String> = pending.iter().map(|group| group.key()).collect();
+            let listed = pending
+                .iter()
+                .map(|group| group.owners.join(", "))
+                .collect::<Vec<_>>()
+                .join("; ");
+            state.record_owner_override(&change_id, &keys, &approved_by, reason);
+            audit_log::append(
+                &paths.audit_log_path,
+                Some(&change_id),
+                "owner-override",
+                &[
+                    ("by", &approved_by),
+                    ("owners", &listed),
+                    ("reason", reason),
+                ],
+            )?;
+        }
+        state.save_change(&paths.state_path, &change_id)?;
+        audit_log::append(
+            &paths.audit_log_path,
+            Some(&change_id),
+            "approve",
+            &[("approved_by", &approved_by)],
+        )?;
+    }
+
+    let change_state = state
+        .change_state(&change_id)
+        .ok_or_else(|| anyhow!("change {change_id} not found"))?;
+    let mut decision = render_decision(change_state, policy.required);
+    let approvers: Vec<&str> = change_state
+        .approvals
+        .iter()
+        .map(|approval| approval.by.as_str())
+        .collect();
+    decision.push_str(&owners::render(
+        &owners,
+        &owned,
+        &approvers,
+        &change_state.owner_overrides,
+    ));
+    write_file(&change_dir.join("90_decision.md"), &decision)?;
+    let pending = if config.owners.enforce {
+        pending_owner_groups(&owners, &owned, &state, &change_id)
+    } else {
+        Vec::new()
+    };
+    let count = change_state.approval_count();
+    if args.revoke {
+        println!(
+            "{}",
+            tr!(
+                "approve 取り消し: {}",
+                "approval revoked: {}",
+                change_dir.display()
+            )
+        );
+    } else if !pending.is_empty() {
+        println!(
+            "{}",
+            tr!(
+                "approve 記録: owner の承認待ち: {}",
+                "approval recorded; owner approval pending: {}",
+                pending
+                    .iter()
+                    .map(|group| group.owners.join(", "))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )
+        );
+    } else if count < policy.required {
+        println!(
+            "{}",
+            tr!(
+                "approve 記録: {count}/{} (あと {} 名の承認が必要です)",
+                "approval recorded: {count}/{} ({} more needed)",
+                policy.required,
+                policy.required - count
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            tr!("approve 完了: {}", "approve done: {}", change_dir.display())
+        );
+    }
+    Ok(())
+}
+
+fn pending_owner_groups<'a>(
+    owners: &Owners,
+    owned: &'a [OwnedPaths],
+    state: &State,
+    change_id: &str,
+) -> Vec<&'a OwnedPaths> {
+    let Some(change) = state.change_state(change_id) else {
+        return owned.iter().collect();
+    };
+    let approvers: Vec<&str> = change
+        .approvals
+        .iter()
+        .map(|approval| approval.by.as_str())
+        .collect();
+    owners::pending(owners, owned, &approvers, &change.owner_overrides)
+}
+
+fn approved_artifacts_hash(change_dir: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    for name in APPROVED_ARTIFACTS {
+        hasher.update(name.as_bytes());
+        let files = if name == DIGEST_FILE {
+            digest::digest_files(change_dir)
+        } else {
+            vec![change_dir.join(name)]
+        };
+        for path in files {
+            if path.exists() {
+                hasher.update(read_to_string(&path)?.as_bytes());
+            }
+        }
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn require_approval(paths: &RepoPaths, state: &State, change_id: &str, force: bool) -> Result<()> {
+    let config = Config::load(&paths.config_path)?;
+    state.require_approved(change_id, config.approvals.required)?;
+    if config.owners.enforce {
+        let change_dir = paths.find_change_dir(change_id)?;
+        let change = state.change_state(change_id).cloned().unwrap_or_default();
+        let owners = Owners::load(&paths.repo_root, &config.owners)?;
+        let owned = owners.route(&touched_paths(paths, change_id, &change_dir, &change)?);
+        let pending = pending_owner_groups(&owners, &owned, state, change_id);
+        if !pending.is_empty() {
+            return Err(ErrorKind::ApprovalRequired.err(tr!(
+                "owner の承認がありません: {}。owner に approve を依頼するか、approve --override-owners --reason で理由を記録してください",
+                "owner approval missing for: {}; ask an owner to approve, or record an override with approve --override-owners --reason",
+                pending
+                    .iter()
+                    .map(|group| group.owners.join(", "))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )));
+        }
+    }
+    let Some(approved_hash) = state
+        .change_state(change_id)
+        .and_then(|c| c.approved_artifacts_hash.clone())
+    else {
+        return Ok(());
+    };
+    let current = approved_artifacts_hash(&paths.find_change_dir(change_id)?)?;
+    if current == approved_hash {
+        return Ok(());
+    }
+    if !force {
+        return Err(ErrorKind::ApprovalRequired.err(tr!(
+            "承認後に {} が変更されています。再度 approve するか --force を指定してください",
+            "{} changed after approval; approve again or pass --force",
+            APPROVED_ARTIFACTS.join(" / ")
+        )));
+    }
+    warn!("approved artifacts changed for {change_id}; continuing with --force");
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(change_id),
+        "approval-override",
+        &[("artifacts_hash", &current)],
+    )?;
+    Ok(())
+}
+
+fn touched_paths(
+    paths: &RepoPaths,
+    change_id: &str,
+    change_dir: &Path,
+    change: &ChangeState,
+) -> Result<BTreeSet<String>> {
+    let mut touched = BTreeSet::new();
+    let tasks_path = change_dir.join("40_tasks.md");
+    if tasks_path.exists() {
+        if let Ok(list) = tasks::parse_tasks(&read_to_string(&tasks_path)?) {
+            touched.extend(list.tasks.into_iter().flat_map(|task| task.files));
+        }
+    }
+    touched.extend(
+        git_diff_names(&paths.repo_root, "HEAD")?
+            .into_iter()
+            .filter(|p| p.starts_with("docs/sdd/specs/")),
+    );
+    if let Some(base_commit) = &change.base_commit {
+        for entry in fs::read_dir(paths.worktrees_dir.join(change_id))
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            if entry.path().is_dir() {
+                touched.extend(git_diff_names(&entry.path(), base_commit)?);
+            }
+        }
+    }
+    Ok(touched)
+}
+
+fn render_decision(change: &ChangeState, required: usize) -> String {
+    let mut out = format!(
+        "# Decision\n\n- approved: {}\n- approved_at: {}\n- approvals: {}/{required}\n- approvers:\n",
+        change.approved && change.approval_count() >= required,
+        change.approved_at.as_deref().unwrap_or("-"),
+        change.approval_count()
+    );
+    if change.approvals.is_empty() {
+        if let Some(by) = &change.approved_by {
+            out.push_str(&format!("  - {by}\n"));
+        }
+    }
+    for approval in &change.approvals {
+        out.push_str(&format!("  - {} ({})\n", approval.by, approval.at));
+    }
+    if !change.decision_history.is_empty() {
+        out.push_str("\n## History\n\n");
+        for event in &change.decision_history {
+            out.push_str(&format!("- {} {} by {}", event.at, event.action, event.by));
+            if let Some(reason) = &event.reason {
+                out.push_str(&format!(": {reason}"));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn cmd_check(args: CheckArgs) -> Result<()> {
+    info!("check start");
+    let paths = RepoPaths::load()?;
+    let base = resolve_base_ref(&paths.repo_root, args.base.as_deref())?;
+    let changed = git_diff_names(&paths.repo_root, &base)?;
+
+    if changed.is_empty() {
+        println!("{}", tr!("変更なし", "no changes"));
+        return Ok(());
+    }
+
+    let docs_only = changed.iter().all(|p| p.starts_with("docs/"));
+    if docs_only {
+        println!(
+            "{}",
+            tr!(
+                "docs-only 変更のため check は成功扱いです。",
+                "docs-only change; check passes."
+            )
+        );
+        return Ok(());
+    }
+
+    let config = Config::load(&paths.config_path)?;
+    let gates = SpecGate::from_config(&config.check)?;
+    let mut failed = Vec::new();
+    for gate in &gates {
+        let outcome = gate.evaluate(&changed);
+        let Some(name) = &gate.name else {
+            if let GateOutcome::Failed(message) = outcome {
+                return Err(ErrorKind::CheckViolation.err(message));
+            }
+            continue;
+        };
+        match outcome {
+            GateOutcome::NoCode => println!("{name}: {}", tr!("code変更なし", "no code changes")),
+            GateOutcome::Passed => println!("{name}: ok"),
+            GateOutcome::Failed(message) => {
+                println!("{name}: {message}");
+                failed.push(name.as_str());
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(ErrorKind::CheckViolation.err(tr!(
+            "サブプロジェクトの check に失敗しました: {}",
+            "check failed for subprojects: {}",
+            failed.join(", ")
+        )));
+    }
+
+    if config.risk.max_score.is_some() {
+        let state = State::load(&paths.state_path).ok();
+        let mut open = Vec::new();
+        for review in changed
+            .iter()
+            .filter(|p| p.starts_with("docs/sdd/") && p.ends_with("/20_review.md"))
+        {
+            let found = findings::load_findings(&paths.repo_root.join(review))?;
+            let statuses = review_change_id(review)
+                .and_then(|id| state.as_ref()?.change_state(id))
+                .map(|c| c.findings.clone())
+                .unwrap_or_default();
+            open.extend(
+                found
+                    .into_iter()
+                    .filter(|finding| findings::status(&statuses, finding) != "resolved"),
+            );
+        }
+        let open: Vec<&Finding> = open.iter().collect();
+        let risk = risk_score(
+            &paths.repo_root,
+            &config.risk,
+            &open,
+            &paths.repo_root,
+            &base,
+            &base,
+            None,
+        )?;
+        println!("risk: {}", risk.summary());
+        if let Some(max) = risk.exceeds(&config.risk) {
+            return Err(ErrorKind::CheckViolation.err(tr!(
+                "リスクスコアが risk.max_score ({max:.1}) を超えています: {}",
+                "the risk score exceeds risk.max_score ({max:.1}): {}",
+                risk.summary()
+            )));
+        }
+    }
+    if config.check.audit_gate {
+        if config.quality.audit == "none" {
+            return Err(anyhow!(tr!(
+                "check.audit_gate には quality.audit (cargo-audit|cargo-deny) の設定が必要です",
+                "check.audit_gate requires quality.audit (cargo-audit|cargo-deny)"
+            )));
+        }
+        let current = run_audit(&paths.repo_root, &config.quality.audit)?;
+        let base_path = paths
+            .worktrees_dir
+            .join(format!("check-{}.base", std::process::id()));
+        create_detached_worktree(
+            &paths.repo_root,
+            &base_path,
+            &resolve_commit(&paths.repo_root, &base)?,
+        )?;
+        let base_audit = run_audit(&base_path, &config.quality.audit);
+        if let Err(err) = remove_worktree(&paths.repo_root, &base_path) {
+            warn!("{err:#}");
+        }
+        let found: Vec<String> =
+            audit::new_advisories(&current.advisories, &base_audit?.advisories)
+                .into_iter()
+                .filter(|id| !config.quality.audit_ignore.contains(id))
+                .collect();
+        if !found.is_empty() {
+            return Err(ErrorKind::CheckViolation.err(tr!(
+                "{base} にない依存関係の脆弱性が検出されました: {}",
+                "vulnerable dependencies not in {base} found: {}",
+                found.join(", ")
+            )));
+        }
+    }
+
+    println!("{}", tr!("check 完了", "check done"));
+    Ok(())
+}
+
+fn review_change_id(review: &str) -> Option<&str> {
+    let dir = review.rsplit('/').nth(1)?;
+    let dir = match dir.get(..11) {
+        Some(date)
+            if date.ends_with('-')
+                && date[..10].chars().all(|c| c.is_ascii_digit() || c == '-') =>
+        {
+            &dir[11..]
+        }
+        _ => dir,
+    };
+    dir.split_once('_').map(|(id, _)| id)
+}
+
+fn cmd_worktrees(args: WorktreesArgs) -> Result<()> {
+    info!("worktrees start");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    require_approval(&paths, &state, &change_id, args.force)?;
+
+    let pending = state.pending_dependencies(&paths, &change_id);
+    let start_point = if args.on_dependency {
+        let [dep] = pending.as_slice() else {
+            return Err(ErrorKind::DependencyPending.err(tr!(
+                "--on-dependency には未 finalize の依存先がちょうど 1 つ必要です (現在: {})",
+                "--on-dependency needs exactly one unfinalized dependency (found: {})",
+                pending.len()
+            )));
+        };
+        let agent = state
+            .change_state(dep)
+            .and_then(|c| c.selected_agent.clone())
+            .ok_or_else(|| {
+                anyhow!(tr!(
+                    "依存先 {dep} はまだ select で選定されていません",
+                    "dependency {dep} has no selected agent yet"
+                ))
+            })?;
+        Some(format!("sdd/{dep}/{agent}"))
+    } else {
+        if !pending.is_empty() && !args.ignore_deps {
+            return Err(ErrorKind::DependencyPending.err(tr!(
+                "change {change_id} は {} の finalize 待ちです (--on-dependency で依存先の選定ブランチから開始するか --ignore-deps で無視できます)",
+                "change {change_id} is waiting on {} to finalize (start from its selected branch with --on-dependency, or pass --ignore-deps)",
+                pending.join(", ")
+            )));
+        }
+        None
+    };
+
+    let base_commit = match &start_point {
+        Some(branch) => resolve_commit(&paths.repo_root, branch)?,
+        None => current_commit(&paths.repo_root)?,
+    };
+    let config = Config::load(&paths.config_path)?;
+    let strategy = args
+        .variation
+        .clone()
+        .unwrap_or_else(|| config.variation.strategy.clone());
+    if !VARIATION_STRATEGIES.contains(&strategy.as_str()) {
+        return Err(anyhow!(tr!(
+            "未対応の variation.strategy です: {strategy}",
+            "unsupported variation.strategy: {strategy}"
+        )));
+    }
+    let agent_names: Vec<String> = (1..=args.agents).map(|idx| format!("agent{idx}")).collect();
+    let variations = variation::resolve(
+        &config.variation,
+        &strategy,
+        &agent_names,
+        &paths.find_change_dir(&change_id)?,
+    )?;
+    for (agent, v) in &variations {
+        info!("variation for {agent}: {}", variation::describe(v));
+    }
+    let change_state = state.change_state_mut(&change_id);
+    change_state.base_commit = Some(base_commit.clone());
+    change_state.variations = variations;
+    state.save_change(&paths.state_path, &change_id)?;
+
+    let worktree_root = paths.worktrees_dir.join(&change_id);
+    ensure_dir(&worktree_root)?;
+    exclude_path(&paths.repo_root, IMPLEMENT_PROMPT_EXCLUDE)?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let prompts = PromptRenderer::new(
+        &paths,
+        &change_id,
+        &change_dir,
+        state.change_state(&change_id),
+    )?;
+
+    for agent_name in &agent_names {
+        let branch = format!("sdd/{change_id}/{agent_name}");
+        let path = worktree_root.join(agent_name);
+        if path.exists() {
+            continue;
+        }
+        create_worktree(&paths.repo_root, &branch, &path, Some(&base_commit))?;
+        bootstrap_worktree(
+            &paths,
+            &config.worktrees,
+            &config.exec,
+            &change_id,
+            agent_name,
+            &path,
+        )?;
+        let prompt = prompts.render(
+            "implement.md",
+            context! { agent => agent_name, variation => prompts.variation(agent_name) },
+        )?;
+        write_string(&path.join(IMPLEMENT_PROMPT), &prompt)?;
+    }
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        "worktrees",
+        &[
+            ("base_commit", &base_commit),
+            ("agents", &args.agents.to_string()),
+            ("variation", &strategy),
+        ],
+    )?;
+
+    println!(
+        "{}",
+        tr!(
+            "worktrees 完了: {}",
+            "worktrees done: {}",
+            worktree_root.display()
+        )
+    );
+    println!(
+        "{}",
+        tr!(
+            "各 worktree の実装プロンプト: <worktree>/{IMPLEMENT_PROMPT}",
+            "implementation prompt in each worktree: <worktree>/{IMPLEMENT_PROMPT}"
+        )
+    );
+    Ok(())
+}
+
+fn cmd_test_plan(args: TestPlanArgs) -> Result<()> {
+    info!("test-plan start");
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let config = Config::load(&paths.config_path)?;
+    require_approval(&paths, &state, &change_id, args.force)?;
+    state.save_change(&paths.state_path, &change_id)?;
+
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let worktree_root = paths.worktrees_dir.join(&change_id);
+    if !worktree_root.exists() {
+        return Err(ErrorKind::NotFound.err(tr!("worktrees が存在しません", "no worktrees found")));
+    }
+    ensure_schemas(&paths)?;
+    ensure_dir(&paths.runs_dir.join(&change_id))?;
+
+    let options = MeasureOptions {
+        coverage: args
+            .coverage
+            .or_else(|| {
+                state
+                    .change_state(&change_id)
+                    .and_then(|c| c.coverage.clone())
+            })
+            .unwrap_or_else(|| "llvm-cov".to_string()),
+        audit: args.audit.unwrap_or_else(|| config.quality.audit.clone()),
+        bench: args.bench || config.quality.bench,
+        build_metrics: args.build_metrics || config.quality.build_metrics,
+        mutants: args.mutants || config.quality.mutants,
+    };
+    let measure = MeasureContext::prepare(&paths, &state, &change_id, &config, options)?;
+
+    let mut metrics = Vec::new();
+    let mut plan_sections = BTreeMap::new();
+
+    let mut agents = Vec::new();
+    for entry in fs::read_dir(&worktree_root)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            agents.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    agents.sort();
+    for requested in args.agent.iter().chain(args.skip_agent.iter()) {
+        if !agents.contains(requested) {
+            return Err(ErrorKind::NotFound.err(tr!(
+                "worktree が存在しません: {requested}",
+                "worktree not found: {requested}"
+            )));
+        }
+    }
+
+    let all_agents = agents.clone();
+    let mut pending = Vec::new();
+    for agent in agents {
+        let selected = args.agent.is_empty() || args.agent.contains(&agent);
+        if !selected || args.skip_agent.contains(&agent) {
+            let (output_path, _) =
+                output_paths(&paths.runs_dir, &change_id, &format!("test_plan_{agent}"));
+            if output_path.exists() {
+                let contents = read_to_string(&output_path)?;
+                plan_sections.insert(agent.clone(), format!("## {agent}\n\n{contents}\n"));
+            }
+            info!("skip agent {agent}");
+            continue;
+        }
+        pending.push(agent);
+    }
+
+    let max_parallel = args.max_parallel.unwrap_or(config.max_parallel).max(1);
+    let workers = max_parallel.min(pending.len());
+    let progress_group = ProgressGroup::new();
+    let agent_progress: HashMap<String, Progress> = pending
+        .iter()
+        .map(|agent| {
+            let progress = progress_group.spinner(agent);
+            progress.set_message("queued");
+            (agent.clone(), progress)
+        })
+        .collect();
+    let prompts = PromptRenderer::new(
+        &paths,
+        &change_id,
+        &change_dir,
+        state.change_state(&change_id),
+    )?;
+    let queue = Mutex::new(pending.into_iter());
+    let (tx, rx) = mpsc::channel();
+    let mut failures = Vec::new();
+    let parent = tracing::Span::current();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (parent, agent_progress, queue, paths, change_id, prompts, worktree_root, measure) = (
+                &parent,
+                &agent_progress,
+                &queue,
+                &paths,
+                &change_id,
+                &prompts,
+                &worktree_root,
+                &measure,
+            );
+            scope.spawn(move || loop {
+                let next = queue.lock().map(|mut q| q.next()).unwrap_or(None);
+                let Some(agent) = next else {
+                    break;
+                };
+                let worktree_path = worktree_root.join(&agent);
+                let _span = info_span!(parent: parent, "agent", agent = %agent).entered();
+                let progress = &agent_progress[&agent];
+                let result = run_test_plan_agent(
+                    paths,
+                    change_id,
+                    prompts,
+                    &agent,
+                    &worktree_path,
+                    measure,
+                    progress,
+                );
+                progress.finish(if result.is_ok() { "done" } else { "failed" });
+                if tx.send((agent, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        for (agent, result) in rx {
+            match result {
+                Ok((metric, contents)) => {
+                    info!("agent {agent} finished");
+                    plan_sections.insert(agent.clone(), format!("## {agent}\n\n{contents}\n"));
+                    metrics.push(metric);
+                }
+                Err(err) => {
+                    error!("agent {agent} failed: {err}");
+                    failures.push(agent);
+                }
+            }
+        }
+    });
+    let trend_path = paths.runs_dir.join(&change_id).join("trend.json");
+    let mut trend = TrendFile::load(&trend_path)?;
+    for metric in &metrics {
+        trend.record(
+            metric,
+            current_commit(&worktree_root.join(&metric.agent)).ok(),
+        );
+    }
+    trend.save(&trend_path)?;
+    let metrics_path = paths.runs_dir.join(&change_id).join("metrics.json");
+    let mut metrics_file = MetricsFile::load(&metrics_path)?;
+    if args.agent.is_empty() && args.skip_agent.is_empty() {
+        metrics_file.retain_agents(&all_agents);
+    }
+    for metric in metrics {
+        metrics_file.upsert(metric);
+    }
+    metrics_file.save(&metrics_path)?;
+    record_metrics(&paths.state_path, &change_id, &metrics_file.variants)?;
+    if !failures.is_empty() {
+        return Err(
+            ErrorKind::AgentFailed.err(format!("test plan agent failed: {}", failures.join(", ")))
+        );
+    }
+    let sections: Vec<String> = plan_sections.into_values().collect();
+    let summary = format!(
+        "# Test Plan\n\n{}\n{}",
+        sections.join("\n"),
+        trend.render(&all_agents)
+    );
+    write_file(&change_dir.join("50_test_plan.md"), &summary)?;
+    let measured: Vec<&str> = metrics_file
+        .variants
+        .iter()
+        .map(|m| m.agent.as_str())
+        .collect();
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        "test-plan",
+        &[("agents", &measured.join(","))],
+    )?;
+
+    println!(
+        "{}",
+        tr!(
+            "test-plan 完了: {}",
+            "test-plan done: {}",
+            change_dir.display()
+        )
+    );
+    Ok(())
+}
+
+fn run_test_plan_agent(
+    paths: &RepoPaths,
+    change_id: &str,
+    prompts: &PromptRenderer,
+    agent: &str,
+    worktree_path: &Path,
+    measure: &MeasureContext,
+    progress: &Progress,
+) -> Result<(VariantMetrics, String)> {
+    progress.set_message("codex exec");
+    let variation = prompts.variation(agent);
+    let prompt = prompts.render(
+        "test_plan.md",
+        context! { agent => agent, variation => variation },
+    )?;
+    let prompt_path = paths
+        .change_context_dir(&prompts.change_dir)
+        .join(format!("test_plan_prompt_{agent}.md"));
+    write_string(&prompt_path, &prompt)?;
+
+    let (output_path, json_path) =
+        new_output_paths(&paths.runs_dir, change_id, &format!("test_plan_{agent}"))?;
+    let config = Config::load(&paths.config_path)?;
+    let exec_spec = ExecSpec {
+        cwd: worktree_path.to_path_buf(),
+        prompt_path,
+        output_path: output_path.clone(),
+        json_output_path: Some(json_path),
+        sandbox: "workspace-write".to_string(),
+        schema_path: Some(paths.schemas_dir.join("tasks.json")),
+        model: variation
+            .and_then(|v| v.model.clone())
+            .or_else(|| config.model.clone()),
+        env: config.exec.clone(),
+        backend: Backend::Codex,
+    };
+    let result = crate::codex::exec::run(&exec_spec)?;
+    if !result.status_ok {
+        return Err(ErrorKind::AgentFailed.err("test plan agent failed"));
+    }
+
+    progress.set_message("build / tests / coverage");
+    let mut metric = measure.measure(agent, worktree_path)?;
+    metric
+        .stage_secs
+        .insert("codex".to_string(), result.duration_secs);
+    let contents = read_to_string(&output_path)?;
+    Ok((metric, contents))
+}
+
+fn cmd_select(args: SelectArgs) -> Result<()> {
+    info!("select start");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+
+    select_stage(&paths, &mut state, &change_id, &change_dir, &args)?;
+    println!(
+        "{}",
+        tr!("select 完了: {}", "select done: {}", change_dir.display())
+    );
+    Ok(())
+}
+
+fn select_stage(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+    args: &SelectArgs,
+) -> Result<()> {
+    let metrics_path = paths.runs_dir.join(change_id).join("metrics.json");
+    if !metrics_path.exists() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "metrics が見つかりません。先に test-plan を実行してください",
+            "metrics not found; run test-plan first"
+        )));
+    }
+    let mut metrics_file = MetricsFile::load(&metrics_path)?;
+
+    if let (true, Some(agent)) = (args.recompute, args.agent.as_deref()) {
+        let worktree_path = paths.worktrees_dir.join(change_id).join(agent);
+        if !worktree_path.exists() {
+            return Err(ErrorKind::NotFound.err(tr!(
+                "worktree が存在しません: {agent}",
+                "worktree not found: {agent}"
+            )));
+        }
+        let config = Config::load(&paths.config_path)?;
+        let previous = metrics_file.get(agent);
+        let options = MeasureOptions {
+            coverage: args
+                .coverage
+                .clone()
+                .or_else(|| {
+                    previous
+                        .map(|m| m.coverage_tool.clone())
+                        .filter(|tool| tool != "none")
+                })
+                .or_else(|| {
+                    state
+                        .change_state(change_id)
+                        .and_then(|c| c.coverage.clone())
+                })
+                .unwrap_or_else(|| "llvm-cov".to_string()),
+            audit: previous
+                .and_then(|m| m.audit_tool.clone())
+                .unwrap_or_else(|| config.quality.audit.clone()),
+            bench: previous.is_some_and(|m| m.bench.is_some()) || config.quality.bench,
+            build_metrics: previous.is_some_and(|m| m.release.is_some())
+                || config.quality.build_metrics,
+            mutants: previous.is_some_and(|m| m.mutants.is_some()) || config.quality.mutants,
+        };
+        info!("recompute metrics for {agent}");
+        let measured = MeasureContext::prepare(paths, state, change_id, &config, options)?
+            .measure(agent, &worktree_path)?;
+        let trend_path = paths.runs_dir.join(change_id).join("trend.json");
+        let mut trend = TrendFile::load(&trend_path)?;
+        trend.record(&measured, current_commit(&worktree_path).ok());
+        trend.save(&trend_path)?;
+        metrics_file.upsert(measured.clone());
+        metrics_file.save(&metrics_path)?;
+        record_metrics(&paths.state_path, change_id, &[measured])?;
+    }
+
+    let base_commit = state
+        .change_state(change_id)
+        .and_then(|c| c.base_commit.clone())
+        .unwrap_or_else(|| "HEAD~1".to_string());
+
+    let risk_config = Config::load(&paths.config_path)?.risk;
+    let open = open_findings(paths, state, change_id)?;
+    let open: Vec<&Finding> = open.iter().collect();
+    let mut variants = Vec::new();
+    let worktree_root = paths.worktrees_dir.join(change_id);
+    for metric in metrics_file.variants {
+        let worktree_path = worktree_root.join(&metric.agent);
+        let (added, removed) = git_diff_numstat(&worktree_path, &base_commit)?;
+        let notes = format!("coverage: {:?}", metric.coverage_percent);
+        let strategy = state
+            .change_state(change_id)
+            .and_then(|c| c.variations.get(&metric.agent))
+            .map(variation::describe);
+        let risk = risk_score(
+            &paths.repo_root,
+            &risk_config,
+            &open,
+            &worktree_path,
+            &base_commit,
+            &base_commit,
+            metric.changed_line_coverage_percent,
+        )?;
+        variants.push(SelectionVariant {
+            agent: metric.agent,
+            build_passed: metric.build_passed,
+            tests_passed: metric.tests_passed,
+            coverage_percent: metric.coverage_percent,
+            lines_added: added,
+            lines_removed: removed,
+            new_advisories: metric.new_advisories,
+            bench_mean_delta_percent: metric.bench.as_ref().and_then(|b| b.mean_delta_percent),
+            bench_regressions: metric.bench.map(|b| b.regressions).unwrap_or_default(),
+            release: metric.release,
+            mutation_score_percent: metric.mutants.and_then(|m| m.score_percent),
+            stage_secs: metric.stage_secs,
+            environment: metric.environment,
+            strategy,
+            risk,
+            notes,
+        });
+    }
+
+    let tasks_completion = task_completion_ratio(&change_dir.join("40_tasks.md"));
+
+    let mut summary = String::from("# Selection Summary\n\n");
+    summary.push_str(&format!(
+        "- tasks_completion: {:.1}%\n",
+        tasks_completion * 100.0
+    ));
+    summary.push_str(&format!("- open_findings: {}\n", open.len()));
+    if let Some(max) = risk_config.max_score {
+        summary.push_str(&format!("- risk_max_score: {max:.1}\n"));
+    }
+    summary.push('\n');
+    summary.push_str("## Variants\n");
+    for v in &variants {
+        summary.push_str(&format!(
+            "- {}: verdict={}, tests_passed={}, coverage={:?}, diff=+{} -{}, new_advisories={}\n",
+            v.agent,
+            if v.build_passed { "ok" } else { "build failed" },
+            v.tests_passed,
+            v.coverage_percent,
+            v.lines_added,
+            v.lines_removed,
+            v.new_advisories.len()
+        ));
+        summary.push_str(&format!("  - risk: {}\n", v.risk.summary()));
+        if let Some(max) = v.risk.exceeds(&risk_config) {
+            summary.push_str(&tr!(
+                "  - risk_gate: max_score {max:.1} を超えています\n",
+                "  - risk_gate: exceeds max_score {max:.1}\n"
+            ));
+        }
+        for id in &v.new_advisories {
+            summary.push_str(&format!("  - advisory: {id}\n"));
+        }
+        if let Some(delta) = v.bench_mean_delta_percent {
+            summary.push_str(&format!("  - bench: mean {delta:+.1}% vs base\n"));
+        }
+        for regression in &v.bench_regressions {
+            summary.push_str(&format!("  - bench regression: {regression}\n"));
+        }
+        if let Some(score) = v.mutation_score_percent {
+            summary.push_str(&format!("  - mutation_score: {score:.1}%\n"));
+        }
+        if let Some(release) = &v.release {
+            summary.push_str(&format!(
+                "  - release: build={:.1}s ({}), binary_size={} ({})\n",
+                release.build_secs,
+                release
+                    .build_secs_delta
+                    .map(|d| format!("{d:+.1}s vs base"))
+                    .unwrap_or_else(|| "base n/a".to_string()),
+                release
+                    .binary_size_bytes
+                    .map(|b| format!("{b} bytes"))
+                    .unwrap_or_else(|| "n/a".to_string()),
+                release
+                    .binary_size_delta_bytes
+                    .map(|d| format!("{d:+} bytes vs base"))
+                    .unwrap_or_else(|| "base n/a".to_string())
+            ));
+        }
+        if let Some(strategy) = &v.strategy {
+            summary.push_str(&format!("  - strategy: {strategy}\n"));
+        }
+        if !v.stage_secs.is_empty() {
+            summary.push_str(&format!(
+                "  - durations: {}\n",
+                format_stage_secs(&v.stage_secs)
+            ));
+        }
+    }
+
+    let fastest = variants
+        .iter()
+        .filter(|v| v.build_passed && v.tests_passed)
+        .filter_map(|v| v.bench_mean_delta_percent.map(|d| (v, d)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((v, delta)) = fastest {
+        summary.push_str(&format!(
+            "\n## Bench\n\n- fastest_passing: {} (mean {delta:+.1}% vs base)\n",
+            v.agent
+        ));
+    }
+
+    let environments: Vec<(&str, &EnvFingerprint)> = variants
+        .iter()
+        .filter_map(|v| Some((v.agent.as_str(), v.environment.as_ref()?)))
+        .collect();
+    if let Some((_, first)) = environments.first() {
+        summary.push_str("\n## Environment\n\n");
+        let skew: BTreeSet<&str> = environments
+            .iter()
+            .flat_map(|(_, env)| first.differences(env))
+            .collect();
+        if skew.is_empty() {
+            summary.push_str(&format!("- {}\n", first.summary()));
+        } else {
+            warn!("environment differs between agents: {skew:?}");
+            for (agent, env) in &environments {
+                summary.push_str(&format!("- {agent}: {}\n", env.summary()));
+            }
+            summary.push_str(&tr!(
+                "- 環境差異: {}\n",
+                "- environment skew: {}\n",
+                skew.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+
+    if let Some(debaters) = args.debaters {
+        let agents: Vec<String> = variants.iter().map(|v| v.agent.clone()).collect();
+        let consensus = select_debate(
+            paths, state, change_id, change_dir, &summary, &agents, debaters,
+        );
+        state.save_change(&paths.state_path, change_id)?;
+        summary.push_str(&consensus?);
+    }
+
+    if args.interactive {
+        let findings = key_findings(&change_dir.join("20_review.md"));
+        if let Some((agent, rationale)) =
+            interactive_select(paths, change_id, &base_commit, &variants, &findings)?
+        {
+            summary.push_str(&format!(
+                "\n## Decision\n\n- selected: {agent}\n- rationale: {rationale}\n- selected_at: {}\n",
+                now_rfc3339()
+            ));
+            state.record_selection(change_id, &agent, &rationale);
+            state.save_change(&paths.state_path, change_id)?;
+            audit_log::append(
+                &paths.audit_log_path,
+                Some(change_id),
+                "select",
+                &[("agent", &agent), ("rationale", &rationale)],
+            )?;
+        }
+    }
+
+    write_file(&change_dir.join("80_selection.md"), &summary)?;
+    let json_path = paths.runs_dir.join(change_id).join("selection.json");
+    write_string(&json_path, &serde_json::to_string_pretty(&variants)?)?;
+
+    if args.html {
+        let mut html_variants = Vec::new();
+        for v in &variants {
+            let branch = format!("sdd/{change_id}/{}", v.agent);
+            html_variants.push(HtmlVariant {
+                agent: v.agent.clone(),
+                build_passed: v.build_passed,
+                tests_passed: v.tests_passed,
+                coverage_percent: v.coverage_percent,
+                lines_added: v.lines_added,
+                lines_removed: v.lines_removed,
+                new_advisories: v.new_advisories.len(),
+                diff: git_diff_text(&paths.repo_root, &base_commit, &branch)?,
+            });
+        }
+        let html = render_selection_html(change_id, &now_rfc3339(), &html_variants);
+        let html_path = paths.runs_dir.join(change_id).join("selection.html");
+        write_string(&html_path, &html)?;
+        println!(
+            "{}",
+            tr!("HTML レポート: {}", "HTML report: {}", html_path.display())
+        );
+    }
+
+    Ok(())
+}
+
+fn select_debate(
+    paths: &RepoPaths,
+    state: &mut State,
+    change_id: &str,
+    change_dir: &Path,
+    selection: &str,
+    agents: &[String],
+    debaters: u32,
+) -> Result<String> {
+    if agents.is_empty() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "判定対象の variant がありません",
+            "no variants to judge"
+        )));
+    }
+    ensure_schemas(paths)?;
+    let config = Config::load(&paths.config_path)?;
+    let prompts = PromptRenderer::new(paths, change_id, change_dir, state.change_state(change_id))?;
+    let context_dir = paths.change_context_dir(change_dir);
+    let debate_dir = change_dir.join("debate");
+    ensure_dir(&debate_dir)?;
+    let schema_path = paths.schemas_dir.join("select_judgment.json");
+    let worktree_root = paths.worktrees_dir.join(change_id).display().to_string();
+
+    let mut rounds: Vec<Vec<(String, debate::Judgment)>> = Vec::new();
+    for round in 1..=2 {
+        let mut specs = Vec::new();
+        for debater in 1..=debaters {
+            let name = format!("select_{debater}");
+            let judgments: Vec<minijinja::Value> = rounds
+                .last()
+                .into_iter()
+                .flatten()
+                .filter(|(other, _)| *other != name)
+                .map(|(other, judgment)| {
+                    context! {
+                        debater => other,
+                        choice => &judgment.choice,
+                        rationale => &judgment.rationale,
+                    }
+                })
+                .collect();
+            let prompt_path = context_dir.join(format!("{name}_round{round}_prompt.md"));
+            let prompt = prompts.render(
+                "select.md",
+                context! {
+                    debater,
+                    agents,
+                    selection,
+                    worktree_root => &worktree_root,
+                    judgments => &judgments,
+                },
+            )?;
+            write_string(&prompt_path, &prompt)?;
+            specs.push((
+                name.clone(),
+                debate_spec(
+                    paths,
+                    change_id,
+                    &config,
+                    &format!("{name}_round{round}"),
+                    prompt_path,
+                    &schema_path,
+                    Backend::Codex,
+                )?,
+            ));
+        }
+        info!("select debate: round {round} with {debaters} debaters");
+        let runs = debate::run_round(specs)?;
+        for run in &runs {
+            let name = format!("{}_round{round}", run.name);
+            state.record_thread(change_id, "select", &name, run.duration_secs);
+            write_file(&debate_dir.join(format!("{name}.md")), &run.output)?;
+        }
+        rounds.push(
+            debate::judgments(&runs, agents)
+                .map_err(|err| ErrorKind::SchemaViolation.err(format!("{err:#}")))?,
+        );
+    }
+
+    let consensus = debate::select_consensus(&rounds[1], agents);
+    match &consensus {
+        Some(choice) => info!("select debate converged on {choice}"),
+        None => warn!("select debate did not converge"),
+    }
+    let section = debate::render_select_debate(&rounds[0], &rounds[1], consensus.as_deref());
+    write_file(
+        &debate_dir.join("select_consensus.md"),
+        section.trim_start(),
+    )?;
+    Ok(section)
+}
+
+fn cmd_finalize(args: FinalizeArgs) -> Result<()> {
+    info!("finalize start");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    if args.preview {
+        return finalize_preview(&paths, &state, &change_id, &args);
+    }
+    let opened = state
+        .change_state(&change_id)
+        .and_then(|c| c.pull_request.clone());
+    if let (true, Some(url)) = (args.pr, opened) {
+        return finalize_merged_pull_request(&paths, &state, &change_id, &url, &args);
+    }
+    require_approval(&paths, &state, &change_id, args.force)?;
+    let pending = state.pending_dependencies(&paths, &change_id);
+    if !pending.is_empty() {
+        if !args.ignore_deps {
+            return Err(ErrorKind::DependencyPending.err(tr!(
+                "依存先 {} が finalize されていません (--ignore-deps で無視できます)",
+                "dependencies {} are not finalized (pass --ignore-deps to ignore)",
+                pending.join(", ")
+            )));
+        }
+        warn!(
+            "finalize {change_id} before dependencies: {}",
+            pending.join(", "),
+        );
+    }
+    let unresolved = match unresolved_findings(&paths, &state, &change_id) {
+        Err(err) if args.force && error_kind(&err) == Some(ErrorKind::SchemaViolation) => {
+            warn!("finalize {change_id} without the findings gate: {err:#}");
+            Vec::new()
+        }
+        result => result?,
+    };
+    if !unresolved.is_empty() {
+        return Err(ErrorKind::CheckViolation.err(tr!(
+            "未解決の findings があります: {} (codex-sdd findings resolve で解決してください)",
+            "unresolved findings remain: {} (resolve them with codex-sdd findings resolve)",
+            unresolved.join(", ")
+        )));
+    }
+
+    let agent = finalize_agent(&state, &change_id, args.agent.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    if finalize_spec_files(&paths, &state, &change_id, &agent)?.is_some_and(|s| s.is_empty()) {
+        return Err(ErrorKind::CheckViolation.err(tr!(
+            "finalize には docs/sdd/specs/<spec>.md の更新が必要です",
+            "finalize requires an update to docs/sdd/specs/<spec>.md"
+        )));
+    }
+    let branch = format!("sdd/{change_id}/{agent}");
+    let mut overrides = Vec::new();
+    for (flag, message) in finalize_safety(&paths, &state, &change_id, &agent, &args)? {
+        if !finalize_override(&args, flag) {
+            return Err(ErrorKind::CheckViolation.err(tr!(
+                "{message} ({flag} で無視できます)",
+                "{message} (pass {flag} to override)"
+            )));
+        }
+        warn!("finalize {change_id} with {flag}: {message}");
+        overrides.push(flag);
+    }
+
+    if args.pr {
+        let url = open_pull_request(&paths, &state, &change_id, &agent, &branch)?;
+        state.change_state_mut(&change_id).pull_request = Some(url.clone());
+        state.save_change(&paths.state_path, &change_id)?;
+        audit_log::append(
+            &paths.audit_log_path,
+            Some(&change_id),
+            "pull-request",
+            &[
+                ("agent", &agent),
+                ("pr", &url),
+                ("overrides", &overrides.join(",")),
+            ],
+        )?;
+        println!(
+            "{}",
+            tr!(
+                "PR を作成しました: {url}\nマージ後に codex-sdd finalize --pr --id {change_id} を実行すると archive されます",
+                "opened pull request: {url}\nonce it is merged, run codex-sdd finalize --pr --id {change_id} to archive the change"
+            )
+        );
+        return Ok(());
+    }
+    match args.strategy.as_str() {
+        "cherry-pick" => cherry_pick(&paths.repo_root, &branch)?,
+        _ => merge_branch(&paths.repo_root, &branch, true)?,
+    }
+
+    let archive_dir = finalize_archive_dir(&paths, &change_dir);
+    move_dir(&change_dir, &archive_dir)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        "finalize",
+        &[
+            ("agent", &agent),
+            ("strategy", &args.strategy),
+            ("commit", &current_commit(&paths.repo_root)?),
+            ("overrides", &overrides.join(",")),
+        ],
+    )?;
+
+    println!(
+        "{}",
+        tr!(
+            "finalize 完了: {}",
+            "finalize done: {}",
+            archive_dir.display()
+        )
+    );
+    Ok(())
+}
+
+fn finalize_merged_pull_request(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    url: &str,
+    args: &FinalizeArgs,
+) -> Result<()> {
+    let pr_state = tracker::pull_request_state(&paths.repo_root, url)?;
+    if pr_state != "MERGED" {
+        return Err(ErrorKind::DependencyPending.err(tr!(
+            "PR はまだマージされていません ({pr_state}): {url}",
+            "the pull request is not merged yet ({pr_state}): {url}"
+        )));
+    }
+    let agent = finalize_agent(state, change_id, args.agent.as_deref())?;
+    let change_dir = paths.find_change_dir(change_id)?;
+    let archive_dir = finalize_archive_dir(paths, &change_dir);
+    move_dir(&change_dir, &archive_dir)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(change_id),
+        "finalize",
+        &[("agent", &agent), ("strategy", "pr"), ("pr", url)],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "finalize 完了: {}",
+            "finalize done: {}",
+            archive_dir.display()
+        )
+    );
+    Ok(())
+}
+
+fn finalize_safety(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    agent: &str,
+    args: &FinalizeArgs,
+) -> Result<Vec<(&'static str, String)>> {
+    let mut issues = Vec::new();
+    if !args.pr {
+        let dirty: Vec<String> = git_dirty_paths(&paths.repo_root)?
+            .into_iter()
+            .filter(|path| !path.starts_with(".codex/") && !path.starts_with("docs/sdd/"))
+            .collect();
+        if !dirty.is_empty() {
+            issues.push((
+                "--allow-dirty",
+                tr!(
+                    "作業ツリーに未コミットの変更があります: {}",
+                    "the working tree has uncommitted changes: {}",
+                    dirty.join(", ")
+                ),
+            ));
+        }
+        let base_commit = state
+            .change_state(change_id)
+            .and_then(|c| c.base_commit.clone());
+        if let Some(base_commit) = base_commit {
+            let head = current_commit(&paths.repo_root)?;
+            let branch = format!("sdd/{change_id}/{agent}");
+            if head != base_commit && !is_ancestor(&paths.repo_root, &head, &branch)? {
+                let worktree = paths.worktrees_dir.join(change_id).join(agent);
+                let short = |commit: &str| commit[..commit.len().min(12)].to_string();
+                issues.push((
+                    "--allow-diverged",
+                    tr!(
+                        "HEAD ({}) が base_commit ({}) から進んでいます。git -C {} rebase {} で {branch} を追従させてください",
+                        "HEAD ({}) has moved past base_commit ({}); bring {branch} up to date with git -C {} rebase {}",
+                        short(&head),
+                        short(&base_commit),
+                        worktree.display(),
+                        short(&head)
+                    ),
+                ));
+            }
+        }
+    }
+    let config = Config::load(&paths.config_path)?;
+    if config.risk.max_score.is_some() {
+        let base = state
+            .change_state(change_id)
+            .and_then(|c| c.base_commit.clone())
+            .unwrap_or_else(|| "HEAD".to_string());
+        let coverage = MetricsFile::load(&paths.runs_dir.join(change_id).join("metrics.json"))?
+            .get(agent)
+            .and_then(|m| m.changed_line_coverage_percent);
+        let open = open_findings(paths, state, change_id)?;
+        let open: Vec<&Finding> = open.iter().collect();
+        let risk = risk_score(
+            &paths.repo_root,
+            &config.risk,
+            &open,
+            &paths.repo_root,
+            &format!("{base}...sdd/{change_id}/{agent}"),
+            &base,
+            coverage,
+        )?;
+        if let Some(max) = risk.exceeds(&config.risk) {
+            issues.push((
+                "--allow-risk",
+                tr!(
+                    "{agent} のリスクスコアが risk.max_score ({max:.1}) を超えています: {}",
+                    "the risk score of {agent} exceeds risk.max_score ({max:.1}): {}",
+                    risk.summary()
+                ),
+            ));
+        }
+    }
+    if let Some(base_branch) = config.finalize.base_branch {
+        let current = current_branch(&paths.repo_root).unwrap_or_else(|_| "HEAD".to_string());
+        if current != base_branch {
+            issues.push((
+                "--allow-other-branch",
+                tr!(
+                    "現在のブランチ {current} が finalize.base_branch ({base_branch}) と異なります",
+                    "the current branch {current} is not finalize.base_branch ({base_branch})"
+                ),
+            ));
+        }
+    }
+    Ok(issues)
+}
+
+fn finalize_override(args: &FinalizeArgs, flag: &str) -> bool {
+    match flag {
+        "--allow-dirty" => args.allow_dirty,
+        "--allow-diverged" => args.allow_diverged,
+        "--allow-other-branch" => args.allow_other_branch,
+        "--allow-risk" => args.allow_risk,
+        _ => false,
+    }
+}
+
+fn open_pull_request(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    agent: &str,
+    branch: &str,
+) -> Result<String> {
+    let change = state.change_state(change_id);
+    let issue = change.and_then(|c| c.issue.as_ref());
+    let goal = change
+        .and_then(|c| c.goal.clone())
+        .unwrap_or_else(|| change_id.to_string());
+    let title = match issue {
+        Some(issue) if issue.tracker == "jira" => format!("[{}] {goal}", issue.key),
+        _ => goal.clone(),
+    };
+    let mut body = format!("{goal}\n\ncodex-sdd change `{change_id}` (agent: {agent})\n");
+    if let Some(issue) = issue {
+        body.push_str(&format!("\n{}\n", tracker::closing_reference(issue)));
+    }
+    let base = current_branch(&paths.repo_root)?;
+    push_branch(&paths.repo_root, "origin", branch)?;
+    tracker::create_pull_request(&paths.repo_root, &base, branch, &title, &body)
+}
+
+fn finalize_preview(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    args: &FinalizeArgs,
+) -> Result<()> {
+    let agent = finalize_agent(state, change_id, args.agent.as_deref())?;
+    let change_dir = paths.find_change_dir(change_id)?;
+    let branch = format!("sdd/{change_id}/{agent}");
+    resolve_commit(&paths.repo_root, &branch).map_err(|_| {
+        ErrorKind::NotFound.err(tr!(
+            "ブランチが見つかりません: {branch}",
+            "branch not found: {branch}"
+        ))
+    })?;
+
+    let mut blockers = Vec::new();
+    if let Err(err) = require_approval(paths, state, change_id, false) {
+        blockers.push(format!("{err:#}"));
+    }
+    let pending = state.pending_dependencies(paths, change_id);
+    if !pending.is_empty() && !args.ignore_deps {
+        blockers.push(tr!(
+            "依存先 {} が finalize されていません",
+            "dependencies {} are not finalized",
+            pending.join(", ")
+        ));
+    }
+    let unresolved = match unresolved_findings(paths, state, change_id) {
+        Err(err) if error_kind(&err) == Some(ErrorKind::SchemaViolation) => {
+            if !args.force {
+                blockers.push(format!("{err:#}"));
+            }
+            Vec::new()
+        }
+        result => result?,
+    };
+    if !unresolved.is_empty() {
+        blockers.push(tr!(
+            "未解決の findings があります: {}",
+            "unresolved findings remain: {}",
+            unresolved.join(", ")
+        ));
+    }
+    for (flag, message) in finalize_safety(paths, state, change_id, &agent, args)? {
+        if !finalize_override(args, flag) {
+            blockers.push(format!("{message} ({flag})"));
+        }
+    }
+    let spec_files = finalize_spec_files(paths, state, change_id, &agent)?;
+    if spec_files.as_ref().is_some_and(|s| s.is_empty()) {
+        blockers.push(tr!(
+            "docs/sdd/specs/<spec>.md が更新されていません",
+            "docs/sdd/specs/<spec>.md is not updated"
+        ));
+    }
+
+    let range = format!("HEAD...{branch}");
+    println!("# Finalize preview: {change_id}\n");
+    println!("- branch: {branch}");
+    let issue = state.change_state(change_id).and_then(|c| c.issue.as_ref());
+    if let Some(issue) = issue {
+        println!("- issue: {} {} ({})", issue.key, issue.title, issue.url);
+    }
+    if args.pr {
+        let closing = issue.map(tracker::closing_reference);
+        println!(
+            "- strategy: pull request{}",
+            closing.map_or(String::new(), |c| format!(" ({c})"))
+        );
+    } else {
+        println!("- strategy: {}", args.strategy);
+    }
+    if blockers.is_empty() {
+        println!("- gate: ok");
+    } else {
+        for blocker in &blockers {
+            println!("- gate: {blocker}");
+        }
+    }
+
+    let commits = git_log_oneline(&paths.repo_root, &format!("HEAD..{branch}"))?;
+    println!("\n## Commits ({})\n", commits.len());
+    if commits.is_empty() {
+        println!("{}", tr!("(なし)", "(none)"));
+    }
+    for commit in &commits {
+        println!("{commit}");
+    }
+
+    println!("\n## Diff stat ({range})\n");
+    let stat = git_diff_stat(&paths.repo_root, &range)?;
+    if stat.trim().is_empty() {
+        println!("{}", tr!("(なし)", "(none)"));
+    }
+    print!("{stat}");
+
+    println!("\n## Specs\n");
+    match &spec_files {
+        Some(files) if files.is_empty() => println!("{}", tr!("(なし)", "(none)")),
+        Some(files) => {
+            for file in files {
+                println!("{file}");
+            }
+        }
+        None => println!(
+            "{}",
+            tr!(
+                "(worktree または base commit がないため確認しません)",
+                "(not checked: no worktree or base commit)"
+            )
+        ),
+    }
+
+    let archive_dir = finalize_archive_dir(paths, &change_dir);
+    println!(
+        "\n## Archive\n\n{} -> {}\n",
+        change_dir.display(),
+        archive_dir.display()
+    );
+    let mut artifacts = Vec::new();
+    collect_relative_files(&change_dir, &change_dir, &mut artifacts);
+    artifacts.sort();
+    for artifact in artifacts {
+        println!("{artifact}");
+    }
+    Ok(())
+}
+
+fn finalize_agent(state: &State, change_id: &str, agent: Option<&str>) -> Result<String> {
+    match agent {
+        Some(agent) => Ok(agent.to_string()),
+        None => state
+            .change_state(change_id)
+            .and_then(|c| c.selected_agent.clone())
+            .ok_or_else(|| {
+                anyhow!(tr!(
+                    "--agent を指定するか select --interactive で選定してください",
+                    "pass --agent or pick one with select --interactive"
+                ))
+            }),
+    }
+}
+
+fn finalize_spec_files(
+    paths: &RepoPaths,
+    state: &State,
+    change_id: &str,
+    agent: &str,
+) -> Result<Option<Vec<String>>> {
+    let worktree_path = paths.worktrees_dir.join(change_id).join(agent);
+    let base_commit = state
+        .change_state(change_id)
+        .and_then(|c| c.base_commit.clone());
+    let (true, Some(base_commit)) = (worktree_path.exists(), base_commit) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        git_diff_names(&worktree_path, &base_commit)?
+            .into_iter()
+            .filter(|p| p.starts_with("docs/sdd/specs/") && p.ends_with(".md"))
+            .collect(),
+    ))
+}
+
+fn finalize_archive_dir(paths: &RepoPaths, change_dir: &Path) -> PathBuf {
+    let archive_name = format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y-%m-%d"),
+        change_dir.file_name().unwrap().to_string_lossy()
+    );
+    paths.docs_sdd.join("archive").join(archive_name)
+}
+
+fn collect_relative_files(root: &Path, dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_relative_files(root, &path, out);
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            out.push(rel.display().to_string());
+        }
+    }
+}
 
-    let prompt = render_review_prompt(&change_dir, &change_id);
-    let prompt_path = paths
-        .change_context_dir(&change_dir)
-        .join("review_prompt.md");
-    write_string(&prompt_path, &prompt)?;
+fn interactive_select(
+    paths: &RepoPaths,
+    change_id: &str,
+    base_commit: &str,
+    variants: &[SelectionVariant],
+    findings: &[String],
+) -> Result<Option<(String, String)>> {
+    println!(
+        "\n{:<10} {:<8} {:<6} {:>9} {:>14} {:>10}",
+        "agent", "build", "tests", "coverage", "diff", "advisories"
+    );
+    for v in variants {
+        println!(
+            "{:<10} {:<8} {:<6} {:>9} {:>14} {:>10}",
+            v.agent,
+            if v.build_passed { "ok" } else { "failed" },
+            if v.tests_passed { "pass" } else { "fail" },
+            v.coverage_percent
+                .map(|c| format!("{c:.1}%"))
+                .unwrap_or_else(|| "-".to_string()),
+            format!("+{} -{}", v.lines_added, v.lines_removed),
+            v.new_advisories.len()
+        );
+    }
+    if !findings.is_empty() {
+        println!("\n{}", tr!("主要な指摘:", "Key findings:"));
+        for finding in findings {
+            println!("  {finding}");
+        }
+    }
 
-    let (output_path, json_path) = output_paths(&paths.runs_dir, &change_id, "review");
-    let exec_spec = ExecSpec {
-        cwd: paths.repo_root.clone(),
-        prompt_path,
-        output_path: output_path.clone(),
-        json_output_path: Some(json_path),
-        sandbox: "read-only".to_string(),
-        schema_path: Some(paths.schemas_dir.join("review.json")),
+    loop {
+        let input = prompt_line("\n[diff <agent> | pick <agent> | quit] > ")?;
+        let mut parts = input.split_whitespace();
+        let command = parts.next().unwrap_or("");
+        let agent = parts.next().unwrap_or("");
+        match command {
+            "diff" | "pick" if !variants.iter().any(|v| v.agent == agent) => {
+                println!(
+                    "{}",
+                    tr!(
+                        "agent が見つかりません: {agent}",
+                        "agent not found: {agent}"
+                    )
+                );
+            }
+            "diff" => {
+                let branch = format!("sdd/{change_id}/{agent}");
+                show_diff_paged(&paths.repo_root, base_commit, &branch)?;
+            }
+            "pick" => {
+                let rationale = prompt_line(&tr!("選定理由 > ", "Rationale > "))?;
+                return Ok(Some((agent.to_string(), rationale)));
+            }
+            "quit" | "q" => return Ok(None),
+            _ => println!(
+                "{}",
+                tr!(
+                    "diff <agent> / pick <agent> / quit を入力してください",
+                    "enter diff <agent> / pick <agent> / quit"
+                )
+            ),
+        }
+    }
+}
+
+fn key_findings(path: &Path) -> Vec<String> {
+    let Ok(contents) = read_to_string(path) else {
+        return Vec::new();
     };
+    contents
+        .lines()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            lower.contains("high") || lower.contains("重大") || lower.contains("critical")
+        })
+        .take(5)
+        .map(|line| line.trim().to_string())
+        .collect()
+}
 
-    let result = crate::codex::exec::run(&exec_spec)?;
-    if !result.status_ok {
-        return Err(anyhow!("review failed"));
+fn cmd_list() -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let mut change_ids: Vec<&String> = state.changes.keys().collect();
+    change_ids.sort();
+    if change_ids.is_empty() {
+        println!("{}", tr!("change はまだありません", "no changes yet"));
+        return Ok(());
     }
-    state.record_thread(&change_id, "review", "review");
-    state.save(&paths.state_path)?;
+    let context_id = paths.cwd_change_id.clone().or_else(env_change_id);
+    for change_id in change_ids {
+        let change = &state.changes[change_id];
+        let marker = if context_id.as_deref() == Some(change_id.as_str()) {
+            ">"
+        } else if state.active_change_id.as_deref() == Some(change_id.as_str()) {
+            "*"
+        } else {
+            " "
+        };
+        let status = if paths.find_archived_change_dir(change_id).is_some() {
+            "archived"
+        } else if change.approved
+            && change.approved_artifacts_hash.as_ref().is_some_and(|hash| {
+                paths
+                    .find_change_dir(change_id)
+                    .and_then(|dir| approved_artifacts_hash(&dir))
+                    .is_ok_and(|current| current != *hash)
+            })
+        {
+            "approved (stale)"
+        } else if change.pull_request.is_some() {
+            "pr open"
+        } else if change.approved {
+            "approved"
+        } else {
+            "draft"
+        };
+        let after = if change.depends_on.is_empty() {
+            String::new()
+        } else {
+            format!("  after: {}", change.depends_on.join(", "))
+        };
+        println!(
+            "{marker} {change_id}  {status}  selected: {}  threads: {}{after}",
+            change.selected_agent.as_deref().unwrap_or("-"),
+            change.codex_threads.len()
+        );
+    }
+    Ok(())
+}
 
-    let contents = read_to_string(&output_path)?;
-    write_file(&change_dir.join("20_review.md"), &contents)?;
-    println!("review 完了: {}", change_dir.display());
+fn cmd_switch(args: SwitchArgs) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    if state.change_state(&args.id).is_none() {
+        return Err(ErrorKind::NotFound.err(format!("change {} not found", args.id)));
+    }
+    paths.find_change_dir(&args.id)?;
+    State::activate(&paths.state_path, &args.id)?;
+    audit_log::append(&paths.audit_log_path, Some(&args.id), "switch", &[])?;
+    println!(
+        "{}",
+        tr!(
+            "active change を {} に切り替えました",
+            "switched active change to {}",
+            args.id
+        )
+    );
+    println!(
+        "{}",
+        tr!(
+            "このターミナルだけで切り替える場合: export {CHANGE_ENV}={}",
+            "to switch only this terminal: export {CHANGE_ENV}={}",
+            args.id
+        )
+    );
     Ok(())
 }
 
-fn cmd_tasks(args: ChangeArgs) -> Result<()> {
-    log_event("info", "tasks start");
+fn cmd_rename(args: RenameArgs) -> Result<()> {
     let paths = RepoPaths::load()?;
-    let mut state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
-    let change_dir = paths.find_change_dir(&change_id)?;
-    ensure_schemas(&paths)?;
-    ensure_dir(&paths.runs_dir.join(&change_id))?;
+    let state = State::load(&paths.state_path)?;
+    let from = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let to = args.to.clone();
+    if slugify(&to) != to {
+        return Err(anyhow!(tr!(
+            "新しい change id は英小文字・数字・ハイフンで指定してください (例: {})",
+            "the new change id must use lowercase letters, digits, and hyphens (for example {})",
+            slugify(&to)
+        )));
+    }
+    if to == from {
+        return Err(anyhow!(tr!(
+            "change id が変わっていません: {to}",
+            "the change id is unchanged: {to}"
+        )));
+    }
+    if state.change_state(&to).is_some()
+        || paths.find_change_dir(&to).is_ok()
+        || paths.find_archived_change_dir(&to).is_some()
+    {
+        return Err(anyhow!(tr!(
+            "change {to} は既に存在します",
+            "change {to} already exists"
+        )));
+    }
+    let change_dir = paths.find_change_dir(&from)?;
 
-    let prompt = render_tasks_prompt(&change_dir, &change_id);
-    let prompt_path = paths
-        .change_context_dir(&change_dir)
-        .join("tasks_prompt.md");
-    write_string(&prompt_path, &prompt)?;
+    let prefix = format!("sdd/{from}/");
+    let branches = list_branches(&paths.repo_root, &format!("{prefix}*"))?;
+    let mut steps: Vec<RenameStep> = branches
+        .iter()
+        .map(|branch| {
+            RenameStep::Branch(
+                branch.clone(),
+                format!("sdd/{to}/{}", &branch[prefix.len()..]),
+            )
+        })
+        .collect();
+    let old_root = paths.worktrees_dir.join(&from);
+    let new_root = paths.worktrees_dir.join(&to);
+    let mut worktrees: Vec<(PathBuf, PathBuf)> = fs::read_dir(&old_root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| (entry.path(), new_root.join(entry.file_name())))
+        .collect();
+    let old_base = paths.worktrees_dir.join(format!("{from}.base"));
+    if old_base.exists() {
+        worktrees.push((old_base, paths.worktrees_dir.join(format!("{to}.base"))));
+    }
+    steps.extend(
+        worktrees
+            .iter()
+            .map(|(old, new)| RenameStep::Worktree(old.clone(), new.clone())),
+    );
+    let old_runs = paths.runs_dir.join(&from);
+    if old_runs.exists() {
+        steps.push(RenameStep::Dir(old_runs, paths.runs_dir.join(&to)));
+    }
+    let new_dir = paths.change_dir(&to, &to);
+    steps.push(RenameStep::Dir(change_dir, new_dir.clone()));
 
-    let (output_path, json_path) = output_paths(&paths.runs_dir, &change_id, "tasks");
-    let exec_spec = ExecSpec {
-        cwd: paths.repo_root.clone(),
-        prompt_path,
-        output_path: output_path.clone(),
-        json_output_path: Some(json_path),
-        sandbox: "read-only".to_string(),
-        schema_path: Some(paths.schemas_dir.join("tasks.json")),
+    let taken = list_branches(&paths.repo_root, &format!("sdd/{to}/*"))?;
+    for step in &steps {
+        let exists = match step {
+            RenameStep::Branch(_, new) => taken.contains(new),
+            RenameStep::Worktree(_, new) | RenameStep::Dir(_, new) => new.exists(),
+        };
+        if exists {
+            return Err(anyhow!(tr!(
+                "改名先が既に存在します: {}",
+                "the rename target already exists: {}",
+                step.target()
+            )));
+        }
+    }
+
+    let mut done = Vec::new();
+    let result = steps
+        .iter()
+        .try_for_each(|step| {
+            step.apply(&paths.repo_root)?;
+            done.push(step);
+            Ok(())
+        })
+        .and_then(|()| State::rename_change(&paths.state_path, &from, &to));
+    if let Err(err) = result {
+        for step in done.iter().rev() {
+            if let Err(undo) = step.undo(&paths.repo_root) {
+                warn!("undo rename {}: {undo:#}", step.target());
+            }
+        }
+        if new_root.exists() {
+            let _ = fs::remove_dir(&new_root);
+        }
+        return Err(err);
+    }
+    if old_root.exists() {
+        fs::remove_dir(&old_root).with_context(|| format!("remove {}", old_root.display()))?;
+    }
+
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&to),
+        "rename",
+        &[
+            ("from", &from),
+            ("branches", &branches.len().to_string()),
+            ("worktrees", &worktrees.len().to_string()),
+        ],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "{from} を {to} に改名しました: {} (ブランチ {} 件, worktree {} 件)",
+            "renamed {from} to {to}: {} ({} branches, {} worktrees)",
+            new_dir.display(),
+            branches.len(),
+            worktrees.len()
+        )
+    );
+    if std::env::var(CHANGE_ENV).is_ok_and(|value| value == from) {
+        println!(
+            "{}",
+            tr!(
+                "{CHANGE_ENV} を更新してください: export {CHANGE_ENV}={to}",
+                "update {CHANGE_ENV}: export {CHANGE_ENV}={to}"
+            )
+        );
+    }
+    Ok(())
+}
+
+enum RenameStep {
+    Branch(String, String),
+    Worktree(PathBuf, PathBuf),
+    Dir(PathBuf, PathBuf),
+}
+
+impl RenameStep {
+    fn target(&self) -> String {
+        match self {
+            RenameStep::Branch(_, new) => new.clone(),
+            RenameStep::Worktree(_, new) | RenameStep::Dir(_, new) => new.display().to_string(),
+        }
+    }
+
+    fn apply(&self, repo_root: &Path) -> Result<()> {
+        match self {
+            RenameStep::Branch(old, new) => {
+                info!("rename branch {old} -> {new}");
+                rename_branch(repo_root, old, new)
+            }
+            RenameStep::Worktree(old, new) => {
+                if let Some(parent) = new.parent() {
+                    ensure_dir(parent)?;
+                }
+                info!("move worktree {} -> {}", old.display(), new.display());
+                move_worktree(repo_root, old, new)
+            }
+            RenameStep::Dir(old, new) => move_dir(old, new),
+        }
+    }
+
+    fn undo(&self, repo_root: &Path) -> Result<()> {
+        match self {
+            RenameStep::Branch(old, new) => rename_branch(repo_root, new, old),
+            RenameStep::Worktree(old, new) => move_worktree(repo_root, new, old),
+            RenameStep::Dir(old, new) => move_dir(new, old),
+        }
+    }
+}
+
+fn cmd_stats(args: ChangeArgs) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change = state
+        .change_state(&change_id)
+        .ok_or_else(|| anyhow!("change {change_id} not found"))?;
+
+    println!("# Stats {change_id}");
+    let mut purposes: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+    for thread in &change.codex_threads {
+        let entry = purposes.entry(thread.purpose.as_str()).or_default();
+        entry.0 += 1;
+        entry.1 += thread.duration_secs.unwrap_or(0.0);
+    }
+    println!("threads: {}", change.codex_threads.len());
+    for (purpose, (count, secs)) in purposes {
+        if secs > 0.0 {
+            println!("  {purpose}: {count} ({secs:.1}s)");
+        } else {
+            println!("  {purpose}: {count}");
+        }
+    }
+
+    let history = match metrics_history(&paths.state_path, &change_id)? {
+        Some(history) => history,
+        None => json_metrics_history(&paths.runs_dir.join(&change_id))?,
     };
+    println!("measurements: {}", history.len());
+    if let Some(last) = history.iter().map(|r| r.measured_at.as_str()).max() {
+        println!("last measured: {last}");
+    }
+    let mut per_agent: BTreeMap<&str, (usize, usize, Option<f64>)> = BTreeMap::new();
+    for record in &history {
+        let entry = per_agent.entry(record.agent.as_str()).or_default();
+        entry.0 += 1;
+        if record.tests_passed {
+            entry.1 += 1;
+        }
+        if let Some(coverage) = record.coverage_percent {
+            entry.2 = Some(entry.2.map_or(coverage, |best: f64| best.max(coverage)));
+        }
+    }
+    for (agent, (runs, passed, best_coverage)) in per_agent {
+        let best_coverage = best_coverage
+            .map(|c| format!("{c:.2}%"))
+            .unwrap_or_else(|| "n/a".to_string());
+        println!(
+            "  {agent}: pass {passed}/{runs} ({:.0}%), best coverage {best_coverage}",
+            passed as f64 / runs as f64 * 100.0
+        );
+    }
 
-    let result = crate::codex::exec::run(&exec_spec)?;
-    if !result.status_ok {
-        return Err(anyhow!("tasks failed"));
+    let metrics = MetricsFile::load(&paths.runs_dir.join(&change_id).join("metrics.json"))?;
+    let timed: Vec<&VariantMetrics> = metrics
+        .variants
+        .iter()
+        .filter(|v| !v.stage_secs.is_empty())
+        .collect();
+    if !timed.is_empty() {
+        println!("durations (latest):");
+        for variant in timed {
+            println!(
+                "  {}: {}",
+                variant.agent,
+                format_stage_secs(&variant.stage_secs)
+            );
+        }
     }
-    state.record_thread(&change_id, "tasks", "tasks");
-    state.save(&paths.state_path)?;
+    Ok(())
+}
 
-    let contents = read_to_string(&output_path)?;
-    write_file(&change_dir.join("40_tasks.md"), &contents)?;
-    println!("tasks 完了: {}", change_dir.display());
+fn format_stage_secs(stage_secs: &BTreeMap<String, f64>) -> String {
+    let total: f64 = stage_secs.values().sum();
+    let stages: Vec<String> = stage_secs
+        .iter()
+        .map(|(stage, secs)| format!("{stage} {secs:.1}s"))
+        .collect();
+    format!("{} (total {total:.1}s)", stages.join(", "))
+}
+
+fn cmd_gc(args: GcArgs) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let config = Config::load(&paths.config_path)?;
+    let policy = GcPolicy {
+        keep_runs: args.keep_runs.unwrap_or(config.gc.keep_runs),
+        delete_archived: config.gc.delete_archived && !args.keep_archived,
+        max_bytes: args
+            .max_size_mb
+            .or(config.gc.max_size_mb)
+            .map(|mb| mb * 1024 * 1024),
+        keep_spikes: config.spike.keep,
+    };
+    let removals = gc::plan(&paths, &state, &policy)?;
+    if removals.is_empty() {
+        println!("{}", tr!("削除対象はありません", "nothing to remove"));
+        return Ok(());
+    }
+    for removal in &removals {
+        println!(
+            "{}\t{}\t{}",
+            removal.reason.label(),
+            format_bytes(removal.bytes),
+            removal
+                .path
+                .strip_prefix(&paths.repo_root)
+                .unwrap_or(&removal.path)
+                .display()
+        );
+    }
+    let total: u64 = removals.iter().map(|removal| removal.bytes).sum();
+    if args.dry_run {
+        println!(
+            "{}",
+            tr!(
+                "dry-run: {} 件 ({}) を削除予定",
+                "dry-run: would remove {} entries ({})",
+                removals.len(),
+                format_bytes(total)
+            )
+        );
+        return Ok(());
+    }
+    let freed = gc::apply(&removals)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        None,
+        "gc",
+        &[
+            ("removed", &removals.len().to_string()),
+            ("bytes", &freed.to_string()),
+        ],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "{} 件 ({}) を削除しました",
+            "removed {} entries ({})",
+            removals.len(),
+            format_bytes(freed)
+        )
+    );
     Ok(())
 }
 
-fn cmd_approve(args: ApproveArgs) -> Result<()> {
-    log_event("info", "approve change");
+fn json_metrics_history(runs_dir: &Path) -> Result<Vec<MetricsRecord>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(runs_dir.join("metrics"))
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    files.push(runs_dir.join("metrics.json"));
+
+    let mut seen = BTreeSet::new();
+    let mut history = Vec::new();
+    for file in files.iter().filter(|p| p.exists()) {
+        for variant in MetricsFile::load(file)?.variants {
+            if !seen.insert((variant.agent.clone(), variant.measured_at.clone())) {
+                continue;
+            }
+            history.push(MetricsRecord {
+                agent: variant.agent,
+                measured_at: variant.measured_at,
+                tests_passed: variant.tests_passed,
+                coverage_percent: variant.coverage_percent,
+            });
+        }
+    }
+    Ok(history)
+}
+
+fn cmd_audit_show(args: ChangeArgs) -> Result<()> {
     let paths = RepoPaths::load()?;
-    let mut state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
+    let entries = audit_log::read_entries(&paths.audit_log_path)?;
+    let chain = audit_log::verify(&entries);
+    for entry in entries
+        .iter()
+        .filter(|e| args.id.is_none() || e.change_id == args.id)
+    {
+        let details: Vec<String> = entry
+            .details
+            .iter()
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        let line = format!(
+            "#{} {} {} {} by {} {}",
+            entry.seq,
+            entry.ts,
+            entry.change_id.as_deref().unwrap_or("-"),
+            entry.event,
+            entry.actor,
+            details.join(" ")
+        );
+        println!("{}", line.trim_end());
+    }
+    chain
+}
+
+fn cmd_audit_verify() -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let entries = audit_log::read_entries(&paths.audit_log_path)?;
+    audit_log::verify(&entries)?;
+    println!(
+        "{}",
+        tr!(
+            "audit log: {} 件、hash chain は正常です",
+            "audit log: {} entries, hash chain intact",
+            entries.len()
+        )
+    );
+    Ok(())
+}
+
+fn cmd_findings_list(args: FindingsListArgs) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
     let change_dir = paths.find_change_dir(&change_id)?;
+    let found = findings::load_findings(&change_dir.join("20_review.md"))?;
+    let statuses = state
+        .change_state(&change_id)
+        .map(|c| c.findings.clone())
+        .unwrap_or_default();
+    let mut shown = 0;
+    for finding in &found {
+        let status = findings::status(&statuses, finding);
+        if args.status.as_deref().is_some_and(|s| s != status) {
+            continue;
+        }
+        shown += 1;
+        println!(
+            "{} [{}] {} {}",
+            finding.id, status, finding.severity, finding.file
+        );
+        let rationale = finding.rationale.trim();
+        if !rationale.is_empty() {
+            println!("    {}", rationale.replace('\n', "\n    "));
+        }
+        if let Some(entry) = statuses.get(&finding.id) {
+            let note = entry
+                .note
+                .as_deref()
+                .map(|note| format!(": {note}"))
+                .unwrap_or_default();
+            println!("    {} by {} at {}{note}", entry.status, entry.by, entry.at);
+        }
+    }
+    if shown == 0 {
+        println!("{}", tr!("findings はありません", "no findings"));
+    }
+    Ok(())
+}
 
-    let approved_by = args
-        .by
-        .or_else(|| std::env::var("USER").ok())
-        .unwrap_or_else(|| "unknown".to_string());
-    state.approve_change(&change_id, &approved_by);
-    state.save(&paths.state_path)?;
+fn cmd_findings_mark(args: FindingArgs, status: &str) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let found = findings::load_findings(&change_dir.join("20_review.md"))?;
+    let finding = findings::find(&found, &args.finding)?;
+    let by = args.by.unwrap_or_else(current_user);
+    state.change_state_mut(&change_id).findings.insert(
+        finding.id.clone(),
+        FindingStatus {
+            status: status.to_string(),
+            by: by.clone(),
+            at: now_rfc3339(),
+            note: args.note.clone(),
+        },
+    );
+    state.save_change(&paths.state_path, &change_id)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        &format!("findings-{status}"),
+        &[
+            ("finding", &finding.id),
+            ("by", &by),
+            ("note", args.note.as_deref().unwrap_or_default()),
+        ],
+    )?;
+    println!(
+        "{} [{status}] {} {}",
+        finding.id, finding.severity, finding.file
+    );
+    Ok(())
+}
 
-    let decision = format!(
-        "# Decision\n\n- approved: true\n- approved_at: {}\n- approved_by: {}\n",
-        now_rfc3339(),
-        approved_by
+fn cmd_context_add(args: ContextAddArgs) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let path = refs::add_ref(
+        &paths.change_context_dir(&change_dir),
+        &args.source,
+        args.name.as_deref(),
+    )?;
+    let file = path
+        .strip_prefix(&change_dir)
+        .unwrap_or(&path)
+        .display()
+        .to_string();
+    audit_log::append(
+        &paths.audit_log_path,
+        Some(&change_id),
+        "context-add",
+        &[("source", &args.source), ("file", &file)],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "参考資料を追加しました: {}",
+            "added reference: {}",
+            path.display()
+        )
     );
-    write_file(&change_dir.join("90_decision.md"), &decision)?;
-    println!("approve 完了: {}", change_dir.display());
     Ok(())
 }
 
-fn cmd_check(args: CheckArgs) -> Result<()> {
-    log_event("info", "check start");
+fn cmd_context_list(args: ContextListArgs) -> Result<()> {
     let paths = RepoPaths::load()?;
-    let base = resolve_base_ref(&paths.repo_root, args.base.as_deref())?;
-    let changed = git_diff_names(&paths.repo_root, &base)?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let found = refs::list_refs(&paths.change_context_dir(&change_dir));
+    if found.is_empty() {
+        println!("{}", tr!("参考資料はありません", "no reference documents"));
+    }
+    for path in found {
+        let size = fs::metadata(&path).map_or(0, |meta| meta.len());
+        println!("{:>10}  {}", format_bytes(size), path.display());
+    }
+    Ok(())
+}
 
-    if changed.is_empty() {
-        println!("変更なし");
+fn cmd_facts_query(args: FactsQueryArgs, json: bool) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let known = facts::load_facts(&paths.change_context_dir(&change_dir))?;
+    let found = facts::query(&known, &args.path)?;
+    if found.is_empty() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{} についての reader の記録はありません ({change_id})",
+            "no reader facts for {} in {change_id}",
+            args.path
+        )));
+    }
+    if json {
+        let found: BTreeMap<&String, &facts::FileFact> = found.into_iter().collect();
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+    for (path, fact) in found {
+        println!("{path} (reader_{})", fact.shard);
+        for (name, value) in fact.fields() {
+            if !value.is_empty() {
+                println!("  {name}: {}", value.replace('\n', "\n    "));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_inspect(args: InspectArgs, json: bool) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let (change_dir, archived) = match paths.find_change_dir(&change_id) {
+        Ok(dir) => (dir, false),
+        Err(err) => match paths.find_archived_change_dir(&change_id) {
+            Some(dir) => (dir, true),
+            None => return Err(err),
+        },
+    };
+    let change = state.change_state(&change_id);
+    let selected = change.and_then(|c| c.selected_agent.as_deref());
+    let painter = Painter::detect();
+    let (path, data, text) = match args.artifact.as_str() {
+        "digest" => {
+            let path = change_dir.join(digest::digest_entry(&change_dir));
+            let contents = read_artifact(&path)?;
+            let text = inspect::render_markdown(&painter, &contents);
+            (path, serde_json::json!({ "contents": contents }), text)
+        }
+        "review" => {
+            let path = change_dir.join("20_review.md");
+            let contents = read_artifact(&path)?;
+            let found = findings::load_findings(&path)?;
+            let statuses = change.map(|c| c.findings.clone()).unwrap_or_default();
+            let mut text = inspect::render_findings(&painter, &found, &statuses);
+            let appendix = inspect::review_appendix(&contents);
+            if !appendix.is_empty() {
+                text.push('\n');
+                text.push_str(&inspect::render_markdown(&painter, appendix));
+            }
+            let found: Vec<serde_json::Value> = found
+                .iter()
+                .map(|finding| {
+                    let mut value = serde_json::to_value(finding)?;
+                    value["status"] = findings::status(&statuses, finding).into();
+                    Ok(value)
+                })
+                .collect::<Result<_>>()?;
+            (path, serde_json::json!({ "findings": found }), text)
+        }
+        "tasks" => {
+            let path = change_dir.join("40_tasks.md");
+            let contents = read_artifact(&path)?;
+            let data = match tasks::parse_tasks(&contents) {
+                Ok(list) => serde_json::to_value(list)?,
+                Err(_) => serde_json::json!({ "contents": contents }),
+            };
+            (path, data, inspect::render_tasks(&painter, &contents))
+        }
+        "selection" => {
+            let path = change_dir.join("80_selection.md");
+            let contents = read_artifact(&path)?;
+            let rationale = change.and_then(|c| c.selection_rationale.as_deref());
+            let variants_path = paths.runs_dir.join(&change_id).join("selection.json");
+            let variants: serde_json::Value = if variants_path.exists() {
+                serde_json::from_str(&read_to_string(&variants_path)?)
+                    .with_context(|| format!("parse {}", variants_path.display()))?
+            } else {
+                serde_json::Value::Null
+            };
+            let text = inspect::render_selection(&painter, selected, rationale, &contents);
+            let data = serde_json::json!({
+                "selected_agent": selected,
+                "rationale": rationale,
+                "variants": variants,
+            });
+            (path, data, text)
+        }
+        "metrics" => {
+            let path = paths.runs_dir.join(&change_id).join("metrics.json");
+            if !path.exists() {
+                return Err(ErrorKind::NotFound.err(tr!(
+                    "{} がありません。test-plan を実行してください (archive 済みの change の runs は gc で削除されます)",
+                    "{} not found; run test-plan first (gc removes the runs of archived changes)",
+                    path.display()
+                )));
+            }
+            let metrics = MetricsFile::load(&path)?;
+            let text = inspect::render_metrics(&painter, &metrics, selected);
+            (path, serde_json::to_value(&metrics)?, text)
+        }
+        other => return Err(anyhow!("unknown artifact {other}")),
+    };
+    if json {
+        let payload = serde_json::json!({
+            "change_id": change_id,
+            "artifact": args.artifact,
+            "path": path,
+            "archived": archived,
+            "data": data,
+        });
+        return write_stdout(&format!("{}\n", serde_json::to_string_pretty(&payload)?));
+    }
+    let archived = if archived { " (archived)" } else { "" };
+    write_stdout(&format!(
+        "{change_id}{archived}: {}\n\n{text}",
+        path.display()
+    ))
+}
+
+fn cmd_spike(args: SpikeArgs, json: bool) -> Result<()> {
+    info!("spike start");
+    let paths = RepoPaths::load()?;
+    let config = Config::load(&paths.config_path)?;
+    let keywords = spike::keywords(&args.question);
+    if keywords.is_empty() {
+        return Err(anyhow!(tr!(
+            "質問からキーワードを取り出せません。ファイル名や識別子を含めてください",
+            "no keywords in the question; mention file names or identifiers"
+        )));
+    }
+    let index = build_index(&paths.repo_root, args.include_untracked, &args.scope, None)?;
+    let ranked = spike::rank(
+        &paths.repo_root,
+        &index.index.files,
+        &keywords,
+        args.max_files.unwrap_or(config.spike.max_files),
+    );
+    if ranked.is_empty() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "質問に一致するファイルがありません ({})。--scope や質問の言い回しを見直してください",
+            "no files match the question ({}); adjust --scope or the wording",
+            keywords.join(", ")
+        )));
+    }
+    let slug: String = slugify(&args.question).chars().take(40).collect();
+    let spike_id = format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S"),
+        slug.trim_end_matches('-')
+    );
+    let spike_dir = paths.spikes_dir.join(&spike_id);
+    ensure_dir(&spike_dir)?;
+    write_string(
+        &spike_dir.join("file_index.json"),
+        &serde_json::to_string_pretty(&ranked)?,
+    )?;
+
+    let files: Vec<&str> = ranked.iter().map(|file| file.path.as_str()).collect();
+    let prompts = PromptRenderer::new(&paths, &spike_id, &spike_dir, None)?;
+    let mut haystack = files.clone();
+    haystack.push(&args.question);
+    let prompt = prompts.render(
+        "spike.md",
+        context! {
+            question => &args.question,
+            files => &files,
+            relevant_specs => prompts.relevant_specs(&haystack),
+        },
+    )?;
+    let prompt_path = spike_dir.join("prompt.md");
+    write_string(&prompt_path, &prompt)?;
+    let output_path = spike_dir.join("answer.md");
+    let mut env = config.exec.clone();
+    env.archive_prompts = false;
+    env.timeout_secs = Some(args.timeout_secs.unwrap_or(config.spike.timeout_secs));
+    let inputs = files
+        .iter()
+        .map(|file| paths.repo_root.join(file))
+        .collect();
+    let spec = ExecSpec {
+        cwd: paths.repo_root.clone(),
+        prompt_path,
+        output_path: output_path.clone(),
+        json_output_path: Some(spike_dir.join("events.jsonl")),
+        sandbox: "read-only".to_string(),
+        schema_path: None,
+        model: config.model.clone(),
+        env,
+        backend: Backend::for_stage(&config, inputs)?,
+    };
+    let started_at = now_rfc3339();
+    let result = crate::codex::exec::run(&spec)?;
+    let meta = serde_json::json!({
+        "question": &args.question,
+        "keywords": &keywords,
+        "files": &ranked,
+        "started_at": started_at,
+        "duration_secs": result.duration_secs,
+        "status_ok": result.status_ok,
+        "timed_out": result.timed_out,
+    });
+    write_string(
+        &spike_dir.join("meta.json"),
+        &serde_json::to_string_pretty(&meta)?,
+    )?;
+    let rel_dir = spike_dir
+        .strip_prefix(&paths.repo_root)
+        .unwrap_or(&spike_dir)
+        .display()
+        .to_string();
+    if !result.status_ok {
+        return Err(ErrorKind::AgentFailed.err(tr!(
+            "spike の agent が失敗しました ({rel_dir})",
+            "spike agent failed ({rel_dir})"
+        )));
+    }
+    let answer = read_to_string(&output_path)?;
+    if json {
+        let payload = serde_json::json!({
+            "spike_id": spike_id,
+            "dir": rel_dir,
+            "files": &ranked,
+            "duration_secs": result.duration_secs,
+            "answer": answer,
+        });
+        return write_stdout(&format!("{}\n", serde_json::to_string_pretty(&payload)?));
+    }
+    write_stdout(&format!("{}\n\nspike: {rel_dir}\n", answer.trim_end()))
+}
+
+fn cmd_conflicts(args: ConflictsArgs, json: bool) -> Result<()> {
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let focus = match args.id.as_deref() {
+        Some(id) => Some(resolve_change_id(&paths, &state, Some(id))?),
+        None => None,
+    };
+    let in_flight = state.in_flight_changes(&paths);
+    if let Some(focus) = focus.as_ref().filter(|id| !in_flight.contains(id)) {
+        return Err(ErrorKind::NotFound.err(format!("change {focus} not found")));
+    }
+    let mut changes = Vec::new();
+    for change_id in &in_flight {
+        let Some(base_commit) = state
+            .change_state(change_id)
+            .and_then(|change| change.base_commit.as_deref())
+        else {
+            continue;
+        };
+        changes.push(conflicts::ChangeFiles::collect(
+            &paths.repo_root,
+            &paths.worktrees_dir,
+            change_id,
+            base_commit,
+        )?);
+    }
+    let mut overlaps = conflicts::overlaps(&changes);
+    if let Some(focus) = &focus {
+        overlaps.retain(|overlap| overlap.a == *focus || overlap.b == *focus);
+    }
+    if json {
+        let payload = serde_json::json!({
+            "changes": changes,
+            "overlaps": overlaps,
+        });
+        return write_stdout(&format!("{}\n", serde_json::to_string_pretty(&payload)?));
+    }
+    if changes.is_empty() {
+        println!(
+            "{}",
+            tr!(
+                "worktree のある進行中の change はありません",
+                "no in-flight changes with worktrees"
+            )
+        );
         return Ok(());
     }
+    write_stdout(&conflicts::render(&changes, &overlaps))
+}
+
+fn cmd_replay(args: ReplayArgs, json: bool) -> Result<()> {
+    info!("replay start");
+    let paths = RepoPaths::load()?;
+    let state = State::load(&paths.state_path)?;
+    let change_id = resolve_change_id(&paths, &state, args.id.as_deref())?;
+    let change_dir = paths.find_change_dir(&change_id)?;
+    let recordings = replay::recordings(&paths.runs_dir.join(&change_id))?;
+    if recordings.is_empty() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{change_id} の transcript がありません。[exec] archive_prompts = true で pipeline を実行してください",
+            "no transcripts recorded for {change_id}; run the pipeline with [exec] archive_prompts = true"
+        )));
+    }
 
-    let docs_only = changed.iter().all(|p| p.starts_with("docs/"));
-    if docs_only {
-        println!("docs-only 変更のため check は成功扱いです。");
-        return Ok(());
+    let sandbox_root = paths.replay_dir.join(&change_id);
+    let sandbox = paths.sandboxed(&sandbox_root);
+    replay::prepare_sandbox(&paths, &sandbox, &change_id, &change_dir)?;
+    let sandbox_change_dir = sandbox.find_change_dir(&change_id)?;
+    paths::redirect_to_sandbox(sandbox_root.clone())?;
+    replay::start(&sandbox_root, &paths.repo_root, &recordings)?;
+    let selected = change_dir.join("80_selection.md").is_file()
+        && paths
+            .runs_dir
+            .join(&change_id)
+            .join("metrics.json")
+            .is_file();
+    let steps = replay::plan(&recordings, selected);
+    let since = SystemTime::now();
+    let mut errors = Vec::new();
+    for step in &steps {
+        info!("replay {}", step.label());
+        if let Err(err) = replay_step(&sandbox, &change_id, &sandbox_change_dir, step) {
+            warn!("replay {} failed: {err:#}", step.label());
+            errors.push((step.label(), err));
+        }
     }
+    let (runs, unused) = replay::finish()?;
+    let mut artifacts = replay::compare_artifacts(
+        &change_dir,
+        &sandbox_change_dir,
+        true,
+        since,
+        &paths.repo_root,
+        &sandbox_root,
+    )?;
+    artifacts.extend(replay::compare_artifacts(
+        &paths.runs_dir.join(&change_id),
+        &sandbox.runs_dir.join(&change_id),
+        false,
+        since,
+        &paths.repo_root,
+        &sandbox_root,
+    )?);
 
-    let code_changed = changed.iter().any(|p| {
-        p.starts_with("src/") || p.starts_with("tests/") || p == "Cargo.toml" || p == "Cargo.lock"
-    });
-
-    if code_changed {
-        let required_specs = changed
-            .iter()
-            .any(|p| p.starts_with("docs/sdd/specs/") && p.ends_with(".md"));
-        if !required_specs {
-            return Err(anyhow!(
-                "code変更には docs/sdd/specs/<spec>.md の更新が必要です"
+    let drifted = runs.iter().filter(|run| !run.prompt_matched).count();
+    let differs = artifacts
+        .iter()
+        .filter(|artifact| artifact.status != "identical")
+        .count();
+    if json {
+        let payload = serde_json::json!({
+            "change_id": change_id,
+            "sandbox": sandbox_root,
+            "steps": steps.iter().map(Step::label).collect::<Vec<_>>(),
+            "runs": runs,
+            "not_replayed": unused
+                .iter()
+                .map(|recording| format!("{:03}_{}", recording.number, recording.name))
+                .collect::<Vec<_>>(),
+            "artifacts": artifacts,
+            "errors": errors
+                .iter()
+                .map(|(step, err)| format!("{step}: {err:#}"))
+                .collect::<Vec<_>>(),
+        });
+        write_stdout(&format!("{}\n", serde_json::to_string_pretty(&payload)?))?;
+    } else {
+        let mut out = format!(
+            "{}\n{}\n",
+            tr!(
+                "replay: {change_id} (記録された run {} 件)",
+                "replay: {change_id} ({} recorded runs)",
+                recordings.len()
+            ),
+            tr!("sandbox: {}", "sandbox: {}", sandbox_root.display())
+        );
+        out.push_str(&format!("\n{}\n", tr!("steps:", "steps:")));
+        for step in &steps {
+            out.push_str(&format!("  {}\n", step.label()));
+        }
+        for (step, err) in &errors {
+            out.push_str(&format!("  {}: {step}: {err:#}\n", tr!("失敗", "failed")));
+        }
+        out.push_str(&format!("\n{}\n", tr!("runs:", "runs:")));
+        for run in &runs {
+            let status = if run.prompt_matched {
+                tr!("prompt 一致", "prompt identical")
+            } else {
+                tr!("prompt 差分あり", "prompt drifted")
+            };
+            out.push_str(&format!("  {:03} {}: {status}", run.number, run.name));
+            if run.redactions > 0 {
+                out.push_str(&tr!(
+                    " (redaction {} 件)",
+                    " ({} redactions)",
+                    run.redactions
+                ));
+            }
+            out.push('\n');
+            if let Some(diff) = &run.prompt_diff {
+                out.push_str(&indent_diff(diff));
+            }
+        }
+        for recording in &unused {
+            out.push_str(&format!(
+                "  {:03} {}: {}\n",
+                recording.number,
+                recording.name,
+                tr!("再生対象外", "not replayed")
             ));
         }
-
-        let (decision_ok, tasks_ok, test_plan_ok) = required_artifacts(&changed);
-        if !(decision_ok && tasks_ok && test_plan_ok) {
-            return Err(anyhow!("code変更には docs/sdd/changes/<id>_<name>/90_decision.md, 40_tasks.md, 50_test_plan.md が必要です"));
+        out.push_str(&format!("\n{}\n", tr!("artifacts:", "artifacts:")));
+        if artifacts.is_empty() {
+            out.push_str(&tr!("  (なし)\n", "  (none)\n"));
         }
+        for artifact in &artifacts {
+            out.push_str(&format!("  {}: {}\n", artifact.path, artifact.status));
+            if let Some(diff) = &artifact.diff {
+                out.push_str(&indent_diff(diff));
+            }
+        }
+        write_stdout(&out)?;
+    }
+    if let Some((_, err)) = errors.into_iter().next() {
+        return Err(err);
+    }
+    if drifted + differs > 0 {
+        return Err(ErrorKind::CheckViolation.err(tr!(
+            "replay が記録と一致しません: prompt 差分 {drifted} 件、artifact 差分 {differs} 件",
+            "replay differs from the recording: {drifted} prompts drifted, {differs} artifacts differ"
+        )));
+    }
+    if !json {
+        println!(
+            "{}",
+            tr!(
+                "replay は記録と一致しました",
+                "replay matches the recording"
+            )
+        );
     }
-
-    println!("check 完了");
     Ok(())
 }
 
-fn cmd_worktrees(args: WorktreesArgs) -> Result<()> {
-    log_event("info", "worktrees start");
-    let paths = RepoPaths::load()?;
+fn replay_step(paths: &RepoPaths, change_id: &str, change_dir: &Path, step: &Step) -> Result<()> {
     let mut state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
-    state.require_approved(&change_id)?;
-
-    let base_commit = current_commit(&paths.repo_root)?;
-    let change_state = state.change_state_mut(&change_id);
-    change_state.base_commit = Some(base_commit);
-    state.save(&paths.state_path)?;
-
-    let worktree_root = paths.worktrees_dir.join(&change_id);
-    ensure_dir(&worktree_root)?;
-
-    for idx in 1..=args.agents {
-        let agent_name = format!("agent{idx}");
-        let branch = format!("sdd/{change_id}/{agent_name}");
-        let path = worktree_root.join(&agent_name);
-        create_worktree(&paths.repo_root, &branch, &path)?;
+    match step {
+        Step::Readers(stale) => {
+            let change = state.change_state(change_id).cloned().unwrap_or_default();
+            let index = read_index(&paths.change_context_dir(change_dir).join("file_index.json"))?;
+            let shards = shard_files(&index, reader_shard_count(&change));
+            let stale: Vec<usize> = stale
+                .iter()
+                .copied()
+                .filter(|idx| *idx < shards.len())
+                .collect();
+            let prompts =
+                PromptRenderer::new(paths, change_id, change_dir, state.change_state(change_id))?;
+            run_readers(
+                paths, &mut state, change_id, &prompts, &shards, &stale, false,
+            )?;
+            let (header, sections) =
+                compose_repo_digest(paths, change_id, &shards, change.since.as_deref())?;
+            let config = Config::load(&paths.config_path)?;
+            digest::write_digest(change_dir, &header, &sections, &config.digest)?;
+            write_file_facts(paths, change_id, change_dir, &shards)?;
+            state.save_change(&paths.state_path, change_id)
+        }
+        Step::Review {
+            against_specs,
+            debaters,
+        } => {
+            ensure_schemas(paths)?;
+            review_stage(
+                paths,
+                &mut state,
+                change_id,
+                change_dir,
+                *against_specs,
+                *debaters,
+            )
+        }
+        Step::Tasks => {
+            ensure_schemas(paths)?;
+            tasks_stage(paths, &mut state, change_id, change_dir)
+        }
+        Step::SelectDebate { .. } | Step::Select => {
+            let debaters = match step {
+                Step::SelectDebate { debaters } => Some(*debaters),
+                _ => None,
+            };
+            let args = SelectArgs {
+                id: Some(change_id.to_string()),
+                interactive: false,
+                html: false,
+                agent: None,
+                recompute: false,
+                coverage: None,
+                debaters,
+            };
+            select_stage(paths, &mut state, change_id, change_dir, &args)
+        }
     }
-
-    println!("worktrees 完了: {}", worktree_root.display());
-    Ok(())
 }
 
-fn cmd_test_plan(args: TestPlanArgs) -> Result<()> {
-    log_event("info", "test-plan start");
-    let paths = RepoPaths::load()?;
-    let state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
-    state.require_approved(&change_id)?;
-    state.save(&paths.state_path)?;
+fn indent_diff(diff: &str) -> String {
+    diff.lines().map(|line| format!("    {line}\n")).collect()
+}
 
-    let change_dir = paths.find_change_dir(&change_id)?;
-    let worktree_root = paths.worktrees_dir.join(&change_id);
-    if !worktree_root.exists() {
-        return Err(anyhow!("worktrees が存在しません"));
+fn write_stdout(text: &str) -> Result<()> {
+    match std::io::stdout().lock().write_all(text.as_bytes()) {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.with_context(|| "write stdout"),
     }
-    ensure_schemas(&paths)?;
-    ensure_dir(&paths.runs_dir.join(&change_id))?;
-
-    let mut metrics = Vec::new();
-    let mut plan_sections = Vec::new();
+}
 
-    for entry in fs::read_dir(&worktree_root)? {
-        let entry = entry?;
-        if !entry.file_type()?.is_dir() {
-            continue;
-        }
-        let agent = entry.file_name().to_string_lossy().to_string();
-        let worktree_path = entry.path();
+fn read_artifact(path: &Path) -> Result<String> {
+    if !path.exists() {
+        return Err(ErrorKind::NotFound.err(tr!(
+            "{} がありません",
+            "{} not found",
+            path.display()
+        )));
+    }
+    read_to_string(path)
+}
 
-        let prompt = render_test_plan_prompt(&change_id, &agent);
-        let prompt_path = paths
-            .change_context_dir(&change_dir)
-            .join(format!("test_plan_prompt_{agent}.md"));
-        write_string(&prompt_path, &prompt)?;
+fn unresolved_findings(paths: &RepoPaths, state: &State, change_id: &str) -> Result<Vec<String>> {
+    let policy = Config::load(&paths.config_path)?.findings;
+    if !policy.finalize_gate {
+        return Ok(Vec::new());
+    }
+    let review_path = paths.find_change_dir(change_id)?.join("20_review.md");
+    let found = findings::load_findings(&review_path).map_err(|err| {
+        ErrorKind::SchemaViolation.err(tr!(
+            "findings gate が {} を読めません: {err:#} (--force で無視できます)",
+            "the findings gate cannot read {}: {err:#} (pass --force to ignore)",
+            review_path.display()
+        ))
+    })?;
+    let statuses = state
+        .change_state(change_id)
+        .map(|c| c.findings.clone())
+        .unwrap_or_default();
+    Ok(
+        findings::blocking(&found, &statuses, &policy.blocking_severities)
+            .into_iter()
+            .map(|finding| format!("{} ({})", finding.id, finding.severity))
+            .collect(),
+    )
+}
 
-        let (output_path, json_path) =
-            output_paths(&paths.runs_dir, &change_id, &format!("test_plan_{agent}"));
-        let exec_spec = ExecSpec {
-            cwd: worktree_path.clone(),
-            prompt_path: prompt_path.clone(),
-            output_path: output_path.clone(),
-            json_output_path: Some(json_path),
-            sandbox: "workspace-write".to_string(),
-            schema_path: Some(paths.schemas_dir.join("tasks.json")),
-        };
-        let result = crate::codex::exec::run(&exec_spec)?;
-        if !result.status_ok {
-            return Err(anyhow!("test plan agent failed"));
-        }
+fn init_encryption() {
+    let Ok(paths) = RepoPaths::load() else {
+        return;
+    };
+    let Ok(config) = Config::load(&paths.config_path) else {
+        return;
+    };
+    if config.encryption.enabled && store::is_sqlite(&paths.state_path) {
+        warn!("state.db is not encrypted; use state_backend = \"json\" to encrypt state");
+    }
+    let backups = paths.state_path.with_file_name("backups");
+    crypto::init(
+        &config.encryption,
+        vec![
+            paths.runs_dir,
+            paths.cache_dir,
+            paths.state_path,
+            backups,
+            paths.replay_dir,
+            paths.spikes_dir,
+        ],
+    );
+}
 
-        let test_result = run_tests(&worktree_path)?;
-        let test_output_path = paths
-            .runs_dir
-            .join(&change_id)
-            .join(format!("test_results_{agent}.txt"));
-        write_string(&test_output_path, &test_result.stdout)?;
-
-        let (coverage_percent, coverage_output_path, coverage_tool) = match args.coverage.as_str() {
-            "none" => (None, None, "none".to_string()),
-            "tarpaulin" => {
-                let cov = run_tarpaulin(&worktree_path)?;
-                let out_path = paths
-                    .runs_dir
-                    .join(&change_id)
-                    .join(format!("coverage_{agent}.txt"));
-                write_string(&out_path, &cov.stdout)?;
-                (
-                    cov.percent,
-                    Some(out_path.to_string_lossy().to_string()),
-                    "tarpaulin".to_string(),
-                )
-            }
-            _ => {
-                let cov = run_llvm_cov(&worktree_path)?;
-                let out_path = paths
-                    .runs_dir
-                    .join(&change_id)
-                    .join(format!("coverage_{agent}.txt"));
-                write_string(&out_path, &cov.stdout)?;
-                (
-                    cov.percent,
-                    Some(out_path.to_string_lossy().to_string()),
-                    "llvm-cov".to_string(),
-                )
+fn cmd_encryption_seal() -> Result<()> {
+    info!("encryption seal start");
+    let paths = RepoPaths::load()?;
+    if !crypto::enabled() {
+        return Err(anyhow!(tr!(
+            "encryption が無効です。config.toml の [encryption] enabled = true を設定してください",
+            "encryption is disabled; set [encryption] enabled = true in config.toml"
+        )));
+    }
+    let backups = paths.state_path.with_file_name("backups");
+    let mut sealed = 0;
+    for root in [
+        &paths.runs_dir,
+        &paths.cache_dir,
+        &backups,
+        &paths.replay_dir,
+        &paths.spikes_dir,
+    ] {
+        for entry in WalkDir::new(root).into_iter().flatten() {
+            if entry.file_type().is_file() && crypto::seal_file(entry.path())? {
+                sealed += 1;
             }
-        };
-
-        let contents = read_to_string(&output_path)?;
-        plan_sections.push(format!("## {agent}\n\n{contents}\n"));
-
-        metrics.push(VariantMetrics {
-            agent,
-            tests_passed: test_result.success,
-            coverage_percent,
-            coverage_tool,
-            test_output: test_output_path.to_string_lossy().to_string(),
-            coverage_output: coverage_output_path,
-        });
+        }
     }
+    if crypto::seal_file(&paths.state_path)? {
+        sealed += 1;
+    }
+    audit_log::append(
+        &paths.audit_log_path,
+        None,
+        "encryption-seal",
+        &[("sealed", &sealed.to_string())],
+    )?;
+    println!(
+        "{}",
+        tr!(
+            "{sealed} 個のファイルを暗号化しました",
+            "sealed {sealed} files"
+        )
+    );
+    Ok(())
+}
 
-    let summary = format!("# Test Plan\n\n{}", plan_sections.join("\n"));
-    write_file(&change_dir.join("50_test_plan.md"), &summary)?;
-    let metrics_path = paths.runs_dir.join(&change_id).join("metrics.json");
-    write_string(&metrics_path, &serde_json::to_string_pretty(&metrics)?)?;
-
-    println!("test-plan 完了: {}", change_dir.display());
+fn cmd_encryption_cat(args: EncryptionCatArgs) -> Result<()> {
+    print!("{}", read_to_string(&args.path)?);
     Ok(())
 }
 
-fn cmd_select(args: ChangeArgs) -> Result<()> {
-    log_event("info", "select start");
+fn cmd_state_fsck() -> Result<()> {
+    info!("state fsck start");
     let paths = RepoPaths::load()?;
     let state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
-    let change_dir = paths.find_change_dir(&change_id)?;
-
-    let metrics_path = paths.runs_dir.join(&change_id).join("metrics.json");
-    if !metrics_path.exists() {
-        return Err(anyhow!(
-            "metrics が見つかりません。先に test-plan を実行してください"
-        ));
+    let issues = fsck(&paths, &state)?;
+    if issues.is_empty() {
+        println!(
+            "{}",
+            tr!("state fsck: 問題はありません", "state fsck: no issues")
+        );
+        return Ok(());
     }
-    let data = read_to_string(&metrics_path)?;
-    let metrics: Vec<VariantMetrics> = serde_json::from_str(&data)?;
-
-    let base_commit = state
-        .change_state(&change_id)
-        .and_then(|c| c.base_commit.clone())
-        .unwrap_or_else(|| "HEAD~1".to_string());
-
-    let mut variants = Vec::new();
-    let worktree_root = paths.worktrees_dir.join(&change_id);
-    for metric in metrics {
-        let worktree_path = worktree_root.join(&metric.agent);
-        let (added, removed) = git_diff_numstat(&worktree_path, &base_commit)?;
-        let notes = format!("coverage: {:?}", metric.coverage_percent);
-        variants.push(SelectionVariant {
-            agent: metric.agent,
-            tests_passed: metric.tests_passed,
-            coverage_percent: metric.coverage_percent,
-            lines_added: added,
-            lines_removed: removed,
-            notes,
-        });
+    for issue in &issues {
+        println!("- {issue}");
     }
+    Err(anyhow!(tr!(
+        "state fsck: {} 件の不整合があります。codex-sdd state repair で修復できます",
+        "state fsck: {} issues found; run codex-sdd state repair to fix them",
+        issues.len()
+    )))
+}
 
-    let tasks_completion = task_completion_ratio(&change_dir.join("40_tasks.md"));
-    let risk_flag = detect_risk(&change_dir.join("20_review.md"));
-
-    let mut summary = String::from("# Selection Summary\n\n");
-    summary.push_str(&format!(
-        "- tasks_completion: {:.1}%\n",
-        tasks_completion * 100.0
-    ));
-    summary.push_str(&format!(
-        "- risk_flag: {}\n\n",
-        if risk_flag { "あり" } else { "なし" }
-    ));
-    summary.push_str("## Variants\n");
-    for v in &variants {
-        summary.push_str(&format!(
-            "- {}: tests_passed={}, coverage={:?}, diff=+{} -{}\n",
-            v.agent, v.tests_passed, v.coverage_percent, v.lines_added, v.lines_removed
-        ));
+fn cmd_state_repair(args: RepairArgs) -> Result<()> {
+    info!("state repair start");
+    let paths = RepoPaths::load()?;
+    let mut state = State::load(&paths.state_path)?;
+    let issues = fsck(&paths, &state)?;
+    let actions = repair(&paths, &mut state, &issues, args.delete_branches)?;
+    state.save(&paths.state_path)?;
+    audit_log::append(
+        &paths.audit_log_path,
+        None,
+        "state-repair",
+        &[("actions", &actions.join("; "))],
+    )?;
+    for action in &actions {
+        println!("- {action}");
     }
-
-    write_file(&change_dir.join("80_selection.md"), &summary)?;
-    let json_path = paths.runs_dir.join(&change_id).join("selection.json");
-    write_string(&json_path, &serde_json::to_string_pretty(&variants)?)?;
-
-    println!("select 完了: {}", change_dir.display());
+    let remaining = fsck(&paths, &state)?;
+    for issue in &remaining {
+        println!("{}", tr!("- 未解決: {issue}", "- unresolved: {issue}"));
+    }
+    println!("{}", tr!("state repair 完了", "state repair done"));
     Ok(())
 }
 
-fn cmd_finalize(args: FinalizeArgs) -> Result<()> {
-    log_event("info", "finalize start");
-    let paths = RepoPaths::load()?;
-    let state = State::load(&paths.state_path)?;
-    let change_id = resolve_change_id(&state, args.id.as_deref())?;
-    state.require_approved(&change_id)?;
-
-    let change_dir = paths.find_change_dir(&change_id)?;
-    let worktree_path = paths.worktrees_dir.join(&change_id).join(&args.agent);
-    if worktree_path.exists() {
-        if let Some(base_commit) = state
-            .change_state(&change_id)
-            .and_then(|c| c.base_commit.clone())
-        {
-            let changed = git_diff_names(&worktree_path, &base_commit)?;
-            let spec_updated = changed
-                .iter()
-                .any(|p| p.starts_with("docs/sdd/specs/") && p.ends_with(".md"));
-            if !spec_updated {
-                return Err(anyhow!(
-                    "finalize には docs/sdd/specs/<spec>.md の更新が必要です"
-                ));
+fn resolve_change_id(paths: &RepoPaths, state: &State, requested: Option<&str>) -> Result<String> {
+    let context_id = paths.cwd_change_id.clone().or_else(env_change_id);
+    let change_id = match requested.map(str::to_string).or(context_id) {
+        Some(id) => id,
+        None => {
+            let id = state.active_change_id.clone().ok_or_else(|| {
+                anyhow!(tr!("change id を指定してください", "specify a change id"))
+            })?;
+            let in_flight = state.in_flight_changes(paths);
+            if in_flight.len() > 1 {
+                warn!("{} changes in flight ({}); using active change {id}. Pass --id or set {CHANGE_ENV} to pin one",
+                        in_flight.len(),
+                        in_flight.join(", "),
+                );
             }
+            id
+        }
+    };
+    if let Some(cwd_id) = &paths.cwd_change_id {
+        if *cwd_id != change_id {
+            return Err(anyhow!(tr!(
+                "現在のディレクトリは change {cwd_id} の worktree です。{change_id} を操作するにはリポジトリのルートで実行してください",
+                "the current directory is a worktree of change {cwd_id}; run from the repository root to operate on {change_id}"
+            )));
         }
     }
-    let branch = format!("sdd/{change_id}/{}", args.agent);
-
-    match args.strategy.as_str() {
-        "cherry-pick" => cherry_pick(&paths.repo_root, &branch)?,
-        _ => merge_branch(&paths.repo_root, &branch, true)?,
-    }
-
-    let archive_name = format!(
-        "{}-{}",
-        chrono::Utc::now().format("%Y-%m-%d"),
-        change_dir.file_name().unwrap().to_string_lossy()
-    );
-    let archive_dir = paths.docs_sdd.join("archive").join(archive_name);
-    move_dir(&change_dir, &archive_dir)?;
-
-    println!("finalize 完了: {}", archive_dir.display());
-    Ok(())
+    Ok(change_id)
 }
 
-fn resolve_change_id(state: &State, requested: Option<&str>) -> Result<String> {
-    if let Some(id) = requested {
-        return Ok(id.to_string());
-    }
-    state
-        .active_change_id
-        .clone()
-        .ok_or_else(|| anyhow!("change id を指定してください"))
+fn env_change_id() -> Option<String> {
+    std::env::var(CHANGE_ENV).ok().filter(|id| !id.is_empty())
 }
 
-fn ensure_unique_change_id(paths: &RepoPaths, base_id: &str, name_slug: &str) -> Result<String> {
+fn reserve_change_id(
+    paths: &RepoPaths,
+    base_id: &str,
+    name_slug: &str,
+) -> Result<(String, RepoLock)> {
     let mut candidate = base_id.to_string();
     let mut counter = 2;
     loop {
-        let dir = paths.change_dir(&candidate, name_slug);
-        if !dir.exists() {
-            return Ok(candidate);
+        if !paths.change_dir(&candidate, name_slug).exists() {
+            match RepoLock::acquire(&paths.change_lock_path(&candidate), "plans") {
+                Ok(lock) if !paths.change_dir(&candidate, name_slug).exists() => {
+                    return Ok((candidate, lock));
+                }
+                Ok(_) => {}
+                Err(err) if error_kind(&err) != Some(ErrorKind::LockBusy) => return Err(err),
+                Err(_) => info!("change id {candidate} is reserved by another plans run"),
+            }
         }
         candidate = format!("{}-{}", base_id, counter);
         counter += 1;
@@ -727,6 +5446,67 @@ fn ensure_schemas(paths: &RepoPaths) -> Result<()> {
     }
   },
   "required": ["findings"]
+}"#;
+    let review_specs_schema = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "type": "object",
+  "properties": {
+    "findings": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "severity": {"type": "string"},
+          "file": {"type": "string"},
+          "requirement": {"type": "string"},
+          "rationale": {"type": "string"},
+          "suggestion": {"type": "string"}
+        },
+        "required": ["severity", "file", "requirement"]
+      }
+    },
+    "compliance": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "requirement": {"type": "string"},
+          "status": {"type": "string", "enum": ["met", "partial", "missing", "unknown"]},
+          "notes": {"type": "string"}
+        },
+        "required": ["requirement", "status"]
+      }
+    }
+  },
+  "required": ["findings", "compliance"]
+}"#;
+    let review_votes_schema = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "type": "object",
+  "properties": {
+    "votes": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "properties": {
+          "finding": {"type": "string"},
+          "agree": {"type": "boolean"},
+          "reason": {"type": "string"}
+        },
+        "required": ["finding", "agree"]
+      }
+    }
+  },
+  "required": ["votes"]
+}"#;
+    let select_judgment_schema = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "type": "object",
+  "properties": {
+    "choice": {"type": "string"},
+    "rationale": {"type": "string"}
+  },
+  "required": ["choice", "rationale"]
 }"#;
     let tasks_schema = r#"{
   "$schema": "http://json-schema.org/draft-07/schema#",
@@ -774,8 +5554,20 @@ fn ensure_schemas(paths: &RepoPaths) -> Result<()> {
 
     write_schema_file(&paths.schemas_dir.join("reader.json"), reader_schema)?;
     write_schema_file(&paths.schemas_dir.join("review.json"), review_schema)?;
+    write_schema_file(
+        &paths.schemas_dir.join("review_specs.json"),
+        review_specs_schema,
+    )?;
     write_schema_file(&paths.schemas_dir.join("tasks.json"), tasks_schema)?;
     write_schema_file(&paths.schemas_dir.join("select.json"), select_schema)?;
+    write_schema_file(
+        &paths.schemas_dir.join("review_votes.json"),
+        review_votes_schema,
+    )?;
+    write_schema_file(
+        &paths.schemas_dir.join("select_judgment.json"),
+        select_judgment_schema,
+    )?;
     Ok(())
 }
 
@@ -786,88 +5578,52 @@ fn write_schema_file(path: &Path, contents: &str) -> Result<()> {
     write_string(path, contents)
 }
 
-fn compose_repo_digest(paths: &RepoPaths, change_id: &str, shards: usize) -> Result<String> {
-    let mut out = String::from("# Repo Digest\n\n");
-    for idx in 0..shards {
-        let name = format!("reader_{idx}");
-        let (output_path, _) = output_paths(&paths.runs_dir, change_id, &name);
-        if output_path.exists() {
-            let contents = read_to_string(&output_path)?;
-            out.push_str(&format!("## Shard {idx}\n\n{contents}\n"));
+fn write_file_facts(
+    paths: &RepoPaths,
+    change_id: &str,
+    change_dir: &Path,
+    shards: &[Vec<FileEntry>],
+) -> Result<()> {
+    let mut collected = BTreeMap::new();
+    for (idx, shard) in shards.iter().enumerate() {
+        let (output_path, _) = output_paths(&paths.runs_dir, change_id, &format!("reader_{idx}"));
+        if !output_path.exists() {
+            continue;
+        }
+        let files: BTreeSet<&str> = shard.iter().map(|entry| entry.path.as_str()).collect();
+        match facts::parse_reader_output(&read_to_string(&output_path)?, idx, &files) {
+            Some(found) => collected.extend(found),
+            None => warn!("skip file facts from reader_{idx}: output is not reader JSON"),
         }
     }
-    Ok(out)
-}
-
-fn render_reader_prompt(change_id: &str, idx: usize, total: usize, shard: &[FileEntry]) -> String {
-    let mut out = String::new();
-    out.push_str("# Reader\n\n");
-    out.push_str(&format!("change_id: {change_id}\n"));
-    out.push_str(&format!("shard: {}/{}\n\n", idx + 1, total));
-    out.push_str("対象ファイル:\n");
-    for entry in shard {
-        out.push_str(&format!("- {}\n", entry.path));
-    }
-    out.push_str(
-        "\n以下を日本語で簡潔にまとめてください:\n- 役割\n- 公開API\n- リスク\n- テスト観点\n",
-    );
-    out
-}
-
-fn render_review_prompt(change_dir: &Path, change_id: &str) -> String {
-    format!(
-        "# Review\n\nchange_id: {change_id}\n\n次のドキュメントを読み、レビュー観点を整理してください:\n- {}/10_repo_digest.md\n\n出力は JSON スキーマに沿って作成してください。\n",
-        change_dir.display()
-    )
+    facts::write_facts(&paths.change_context_dir(change_dir), &collected)
 }
 
-fn render_tasks_prompt(change_dir: &Path, change_id: &str) -> String {
-    format!(
-        "# Tasks\n\nchange_id: {change_id}\n\n次のドキュメントを読み、実装タスクを整理してください:\n- {}/10_repo_digest.md\n- {}/20_review.md\n\n出力は JSON スキーマに沿って作成してください。\n",
-        change_dir.display(),
-        change_dir.display()
-    )
-}
-
-fn render_test_plan_prompt(change_id: &str, agent: &str) -> String {
-    format!(
-        "# Test Plan\n\nchange_id: {change_id}\nagent: {agent}\n\n対象ブランチのテスト計画を日本語で整理してください。\n"
-    )
-}
-
-fn required_artifacts(changed: &[String]) -> (bool, bool, bool) {
-    let mut by_change: HashMap<String, (bool, bool, bool)> = HashMap::new();
-    for path in changed {
-        if let Some(rest) = path.strip_prefix("docs/sdd/changes/") {
-            let mut parts = rest.split('/');
-            if let Some(change_dir) = parts.next() {
-                let entry = by_change
-                    .entry(change_dir.to_string())
-                    .or_insert((false, false, false));
-                if path.ends_with("/90_decision.md") {
-                    entry.0 = true;
-                }
-                if path.ends_with("/40_tasks.md") {
-                    entry.1 = true;
-                }
-                if path.ends_with("/50_test_plan.md") {
-                    entry.2 = true;
-                }
-            }
-        }
+fn compose_repo_digest(
+    paths: &RepoPaths,
+    change_id: &str,
+    shards: &[Vec<FileEntry>],
+    since: Option<&str>,
+) -> Result<(String, Vec<DigestSection>)> {
+    let mut header = String::from("# Repo Digest\n\n");
+    if let Some(since) = since {
+        header.push_str(&format!(
+            "- since: `{since}` (files changed in `{since}...HEAD` and their direct dependents)\n\n"
+        ));
     }
-    let mut decision = false;
-    let mut tasks = false;
-    let mut test_plan = false;
-    for (_, (d, t, tp)) in by_change {
-        if d && t && tp {
-            decision = true;
-            tasks = true;
-            test_plan = true;
-            break;
+    let mut sections = Vec::new();
+    for (idx, shard) in shards.iter().enumerate() {
+        let name = format!("reader_{idx}");
+        let (output_path, _) = output_paths(&paths.runs_dir, change_id, &name);
+        if output_path.exists() {
+            sections.push(DigestSection {
+                title: format!("Shard {idx}"),
+                body: read_to_string(&output_path)?,
+                files: shard.iter().map(|entry| entry.path.clone()).collect(),
+            });
         }
     }
-    (decision, tasks, test_plan)
+    Ok((header, sections))
 }
 
 fn resolve_base_ref(repo_root: &Path, requested: Option<&str>) -> Result<String> {
@@ -893,10 +5649,40 @@ fn task_completion_ratio(path: &Path) -> f64 {
     0.0
 }
 
-fn detect_risk(path: &Path) -> bool {
-    if let Ok(contents) = read_to_string(path) {
-        let lower = contents.to_lowercase();
-        return lower.contains("high") || lower.contains("重大") || lower.contains("critical");
-    }
-    false
+fn open_findings(paths: &RepoPaths, state: &State, change_id: &str) -> Result<Vec<Finding>> {
+    let Ok(change_dir) = paths.find_change_dir(change_id) else {
+        return Ok(Vec::new());
+    };
+    let found = findings::load_findings(&change_dir.join("20_review.md"))?;
+    let statuses = state
+        .change_state(change_id)
+        .map(|c| c.findings.clone())
+        .unwrap_or_default();
+    Ok(found
+        .into_iter()
+        .filter(|finding| findings::status(&statuses, finding) != "resolved")
+        .collect())
+}
+
+fn risk_score(
+    repo_root: &Path,
+    config: &RiskConfig,
+    open: &[&Finding],
+    diff_root: &Path,
+    diff_ref: &str,
+    churn_rev: &str,
+    changed_line_coverage_percent: Option<f64>,
+) -> Result<RiskScore> {
+    let (added, removed) = git_diff_numstat(diff_root, diff_ref)?;
+    let files: BTreeSet<String> = git_diff_names(diff_root, diff_ref)?.into_iter().collect();
+    let churn_commits = git_file_churn(repo_root, churn_rev, config.churn_window, &files)?;
+    Ok(risk::score(
+        config,
+        &RiskInputs {
+            findings: open,
+            lines_changed: added + removed,
+            churn_commits,
+            changed_line_coverage_percent,
+        },
+    ))
 }
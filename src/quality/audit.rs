@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+
+use crate::util::run_cmd_allow_fail;
+
+#[derive(Debug, Clone)]
+pub struct AuditResult {
+    pub stdout: String,
+    pub advisories: Vec<String>,
+}
+
+pub fn run_audit(repo_root: &Path, tool: &str) -> Result<AuditResult> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(repo_root);
+    match tool {
+        "cargo-audit" => {
+            cmd.args(["audit", "--json"]);
+        }
+        "cargo-deny" => {
+            cmd.args(["deny", "check", "advisories"]);
+        }
+        other => return Err(anyhow!("unknown audit tool: {other}")),
+    }
+    let output = run_cmd_allow_fail(cmd)?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    stdout.push_str(&String::from_utf8_lossy(&output.stderr));
+    let advisories = parse_advisories(&stdout);
+    let has_report = match tool {
+        "cargo-audit" => serde_json::from_slice::<serde_json::Value>(&output.stdout).is_ok(),
+        _ => !advisories.is_empty(),
+    };
+    if !output.status.success() && !has_report {
+        let tail: Vec<&str> = stdout.lines().rev().take(20).collect();
+        let tail: Vec<&str> = tail.into_iter().rev().collect();
+        return Err(anyhow!(
+            "{tool} failed ({}) without an advisory report:\n{}",
+            output.status,
+            tail.join("\n")
+        ));
+    }
+    Ok(AuditResult { stdout, advisories })
+}
+
+pub fn new_advisories(variant: &[String], base: &[String]) -> Vec<String> {
+    let base: BTreeSet<&String> = base.iter().collect();
+    variant
+        .iter()
+        .filter(|id| !base.contains(id))
+        .cloned()
+        .collect()
+}
+
+fn parse_advisories(output: &str) -> Vec<String> {
+    let mut ids = BTreeSet::new();
+    for token in output.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-')) {
+        if is_advisory_id(token) {
+            ids.insert(token.to_string());
+        }
+    }
+    ids.into_iter().collect()
+}
+
+fn is_advisory_id(token: &str) -> bool {
+    let Some(rest) = token.strip_prefix("RUSTSEC-") else {
+        return false;
+    };
+    let mut parts = rest.split('-');
+    let year = parts.next().unwrap_or("");
+    let num = parts.next().unwrap_or("");
+    parts.next().is_none()
+        && year.len() == 4
+        && num.len() == 4
+        && year.chars().all(|c| c.is_ascii_digit())
+        && num.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::config::ExecConfig;
+use crate::util::run_cmd_allow_fail;
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub stdout: String,
+    pub timings: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchDelta {
+    pub name: String,
+    pub base_ns: f64,
+    pub variant_ns: f64,
+    pub delta_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BenchComparison {
+    pub threshold_percent: f64,
+    pub mean_delta_percent: Option<f64>,
+    pub deltas: Vec<BenchDelta>,
+    pub regressions: Vec<String>,
+}
+
+pub fn run_bench(repo_root: &Path, command: &str, env: &ExecConfig) -> Result<BenchResult> {
+    let mut parts = command.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("bench command is empty"))?;
+    let mut cmd = Command::new(program);
+    cmd.current_dir(repo_root).args(parts);
+    env.apply(&mut cmd);
+    let output = run_cmd_allow_fail(cmd)?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    stdout.push_str(&String::from_utf8_lossy(&output.stderr));
+    let timings = parse_timings(&stdout);
+    Ok(BenchResult { stdout, timings })
+}
+
+pub fn compare_bench(
+    base: &BTreeMap<String, f64>,
+    variant: &BTreeMap<String, f64>,
+    threshold_percent: f64,
+) -> BenchComparison {
+    let mut deltas = Vec::new();
+    let mut regressions = Vec::new();
+    for (name, variant_ns) in variant {
+        let Some(base_ns) = base.get(name) else {
+            continue;
+        };
+        if *base_ns <= 0.0 {
+            continue;
+        }
+        let delta_percent = (variant_ns - base_ns) / base_ns * 100.0;
+        if delta_percent > threshold_percent {
+            regressions.push(format!("{name}: +{delta_percent:.1}%"));
+        }
+        deltas.push(BenchDelta {
+            name: name.clone(),
+            base_ns: *base_ns,
+            variant_ns: *variant_ns,
+            delta_percent,
+        });
+    }
+    let mean_delta_percent = if deltas.is_empty() {
+        None
+    } else {
+        Some(deltas.iter().map(|d| d.delta_percent).sum::<f64>() / deltas.len() as f64)
+    };
+    BenchComparison {
+        threshold_percent,
+        mean_delta_percent,
+        deltas,
+        regressions,
+    }
+}
+
+fn parse_timings(output: &str) -> BTreeMap<String, f64> {
+    let mut timings = BTreeMap::new();
+    let mut last_name: Option<String> = None;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("test ") {
+            if let Some((name, bench)) = rest.split_once(" ... bench:") {
+                let value = bench
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .replace(',', "");
+                if let Ok(ns) = value.parse::<f64>() {
+                    timings.insert(name.trim().to_string(), ns);
+                }
+            }
+            continue;
+        }
+        if let Some(idx) = trimmed.find("time:") {
+            let name = trimmed[..idx].trim();
+            let name = if name.is_empty() {
+                last_name.clone()
+            } else {
+                Some(name.to_string())
+            };
+            if let (Some(name), Some(ns)) = (name, parse_criterion_mid(&trimmed[idx + 5..])) {
+                timings.insert(name, ns);
+            }
+            continue;
+        }
+        if !trimmed.is_empty() && !trimmed.contains(':') && !trimmed.starts_with('[') {
+            last_name = Some(trimmed.to_string());
+        }
+    }
+    timings
+}
+
+fn parse_criterion_mid(rest: &str) -> Option<f64> {
+    let inner = rest.trim().trim_start_matches('[').trim_end_matches(']');
+    let tokens: Vec<&str> = inner.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return None;
+    }
+    let value = tokens[2].parse::<f64>().ok()?;
+    let scale = match tokens[3] {
+        "ps" => 0.001,
+        "ns" => 1.0,
+        "µs" | "us" => 1_000.0,
+        "ms" => 1_000_000.0,
+        "s" => 1_000_000_000.0,
+        _ => return None,
+    };
+    Some(value * scale)
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::core::config::ExecConfig;
+use crate::util::run_cmd_allow_fail;
+
+#[derive(Debug, Clone)]
+pub struct BuildResult {
+    pub success: bool,
+    pub stdout: String,
+}
+
+pub fn run_build_check(repo_root: &Path, env: &ExecConfig) -> Result<BuildResult> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(repo_root)
+        .args(["check", "--all-targets", "--message-format=short"]);
+    env.apply(&mut cmd);
+    let output = run_cmd_allow_fail(cmd)?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    stdout.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(BuildResult {
+        success: output.status.success(),
+        stdout,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ReleaseBuildResult {
+    pub success: bool,
+    pub stdout: String,
+    pub build_secs: f64,
+    pub binary_size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseMetrics {
+    pub build_secs: f64,
+    pub binary_size_bytes: Option<u64>,
+    pub build_secs_delta: Option<f64>,
+    pub binary_size_delta_bytes: Option<i64>,
+}
+
+pub fn run_release_build(repo_root: &Path, env: &ExecConfig) -> Result<ReleaseBuildResult> {
+    let mut clean = Command::new("cargo");
+    clean.current_dir(repo_root).args(["clean", "--release"]);
+    env.apply(&mut clean);
+    run_cmd_allow_fail(clean)?;
+
+    let started = Instant::now();
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(repo_root)
+        .args(["build", "--release", "--message-format=json"]);
+    env.apply(&mut cmd);
+    let output = run_cmd_allow_fail(cmd)?;
+    let build_secs = started.elapsed().as_secs_f64();
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let mut binary_size_bytes = None;
+    for line in stdout.lines() {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-artifact" {
+            continue;
+        }
+        if let Some(executable) = message["executable"].as_str() {
+            if let Ok(meta) = std::fs::metadata(executable) {
+                *binary_size_bytes.get_or_insert(0) += meta.len();
+            }
+        }
+    }
+    Ok(ReleaseBuildResult {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stderr).to_string(),
+        build_secs,
+        binary_size_bytes,
+    })
+}
+
+pub fn release_metrics(
+    variant: &ReleaseBuildResult,
+    base: Option<&ReleaseBuildResult>,
+) -> ReleaseMetrics {
+    let build_secs_delta = base.map(|b| variant.build_secs - b.build_secs);
+    let binary_size_delta_bytes = match (variant.binary_size_bytes, base) {
+        (Some(size), Some(b)) => b
+            .binary_size_bytes
+            .map(|base_size| size as i64 - base_size as i64),
+        _ => None,
+    };
+    ReleaseMetrics {
+        build_secs: variant.build_secs,
+        binary_size_bytes: variant.binary_size_bytes,
+        build_secs_delta,
+        binary_size_delta_bytes,
+    }
+}
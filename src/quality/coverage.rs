@@ -1,32 +1,106 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
 use std::path::Path;
 use std::process::Command;
 
 use anyhow::Result;
 
+use crate::core::config::ExecConfig;
 use crate::util::run_cmd_allow_fail;
 
+const TARPAULIN_DIR: &str = "target/codex-sdd-tarpaulin";
+
 #[derive(Debug, Clone)]
 pub struct CoverageResult {
     pub stdout: String,
     pub percent: Option<f64>,
+    pub lcov: Option<String>,
 }
 
-pub fn run_llvm_cov(repo_root: &Path) -> Result<CoverageResult> {
+pub fn run_llvm_cov(repo_root: &Path, env: &ExecConfig) -> Result<CoverageResult> {
     let mut cmd = Command::new("cargo");
     cmd.current_dir(repo_root).args(["llvm-cov", "--summary"]);
+    env.apply(&mut cmd);
     let output = run_cmd_allow_fail(cmd)?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let percent = parse_percent(&stdout);
-    Ok(CoverageResult { stdout, percent })
+    let mut report = Command::new("cargo");
+    report
+        .current_dir(repo_root)
+        .args(["llvm-cov", "report", "--lcov"]);
+    env.apply(&mut report);
+    let lcov = run_cmd_allow_fail(report)
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string());
+    Ok(CoverageResult {
+        stdout,
+        percent,
+        lcov,
+    })
 }
 
-pub fn run_tarpaulin(repo_root: &Path) -> Result<CoverageResult> {
+pub fn run_tarpaulin(repo_root: &Path, env: &ExecConfig) -> Result<CoverageResult> {
     let mut cmd = Command::new("cargo");
-    cmd.current_dir(repo_root).args(["tarpaulin", "--quiet"]);
+    cmd.current_dir(repo_root).args([
+        "tarpaulin",
+        "--quiet",
+        "--out",
+        "Stdout",
+        "--out",
+        "Lcov",
+        "--output-dir",
+        TARPAULIN_DIR,
+    ]);
+    env.apply(&mut cmd);
     let output = run_cmd_allow_fail(cmd)?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let percent = parse_percent(&stdout);
-    Ok(CoverageResult { stdout, percent })
+    let lcov = fs::read_to_string(repo_root.join(TARPAULIN_DIR).join("lcov.info")).ok();
+    Ok(CoverageResult {
+        stdout,
+        percent,
+        lcov,
+    })
+}
+
+pub fn changed_line_coverage(
+    lcov: &str,
+    repo_root: &Path,
+    changed: &BTreeMap<String, BTreeSet<u32>>,
+) -> Option<f64> {
+    let mut file = None;
+    let mut instrumented = 0u64;
+    let mut covered = 0u64;
+    for line in lcov.lines() {
+        if let Some(source) = line.strip_prefix("SF:") {
+            let source = Path::new(source);
+            let relative = source.strip_prefix(repo_root).unwrap_or(source);
+            file = changed.get(relative.to_string_lossy().replace('\\', "/").as_str());
+            continue;
+        }
+        if line == "end_of_record" {
+            file = None;
+            continue;
+        }
+        let (Some(lines), Some(data)) = (file, line.strip_prefix("DA:")) else {
+            continue;
+        };
+        let mut parts = data.split(',');
+        let (Some(Ok(number)), Some(Ok(hits))) = (
+            parts.next().map(str::parse::<u32>),
+            parts.next().map(str::parse::<u64>),
+        ) else {
+            continue;
+        };
+        if lines.contains(&number) {
+            instrumented += 1;
+            if hits > 0 {
+                covered += 1;
+            }
+        }
+    }
+    (instrumented > 0).then(|| covered as f64 * 100.0 / instrumented as f64)
 }
 
 fn parse_percent(output: &str) -> Option<f64> {
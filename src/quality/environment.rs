@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::run_cmd_allow_fail;
+
+const FINGERPRINT_ENV: [&str; 10] = [
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_BUILD_TARGET",
+    "CARGO_INCREMENTAL",
+    "CARGO_TARGET_DIR",
+    "CARGO_PROFILE_DEV_OPT_LEVEL",
+    "CARGO_PROFILE_TEST_OPT_LEVEL",
+    "CC",
+    "CXX",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvFingerprint {
+    pub rustc: Option<String>,
+    pub cargo: Option<String>,
+    pub codex: Option<String>,
+    pub os: String,
+    pub arch: String,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl EnvFingerprint {
+    pub fn capture(cwd: &Path, codex: Option<String>) -> Self {
+        Self {
+            rustc: tool_version(cwd, "rustc"),
+            cargo: tool_version(cwd, "cargo"),
+            codex,
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            env: FINGERPRINT_ENV
+                .iter()
+                .filter_map(|key| Some((key.to_string(), std::env::var(key).ok()?)))
+                .collect(),
+        }
+    }
+
+    pub fn differences(&self, other: &Self) -> Vec<&'static str> {
+        let mut keys = Vec::new();
+        if self.rustc != other.rustc {
+            keys.push("rustc");
+        }
+        if self.cargo != other.cargo {
+            keys.push("cargo");
+        }
+        if self.codex != other.codex {
+            keys.push("codex");
+        }
+        if self.os != other.os || self.arch != other.arch {
+            keys.push("os");
+        }
+        if self.env != other.env {
+            keys.push("env");
+        }
+        keys
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            format!("rustc={}", self.rustc.as_deref().unwrap_or("n/a")),
+            format!("cargo={}", self.cargo.as_deref().unwrap_or("n/a")),
+            format!("codex={}", self.codex.as_deref().unwrap_or("n/a")),
+            format!("os={}/{}", self.os, self.arch),
+        ];
+        parts.extend(self.env.iter().map(|(key, value)| format!("{key}={value}")));
+        parts.join(", ")
+    }
+}
+
+pub fn codex_version() -> Option<String> {
+    tool_version(Path::new("."), "codex")
+}
+
+fn tool_version(cwd: &Path, program: &str) -> Option<String> {
+    let mut cmd = Command::new(program);
+    cmd.current_dir(cwd).arg("--version");
+    let output = run_cmd_allow_fail(cmd).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, warn};
+
+use crate::core::config::{Config, ExecConfig};
+use crate::core::paths::RepoPaths;
+use crate::core::state::State;
+use crate::git::bootstrap::bootstrap_worktree;
+use crate::git::worktree::{
+    create_detached_worktree, current_commit, git_changed_lines, git_diff_names,
+};
+use crate::quality::audit::{new_advisories, run_audit};
+use crate::quality::bench::{compare_bench, run_bench, BenchComparison};
+use crate::quality::build::{
+    release_metrics, run_build_check, run_release_build, ReleaseBuildResult, ReleaseMetrics,
+};
+use crate::quality::coverage::{changed_line_coverage, run_llvm_cov, run_tarpaulin};
+use crate::quality::environment::{codex_version, EnvFingerprint};
+use crate::quality::mutants::{run_mutants, MutantsSummary};
+use crate::quality::setup::run_hooks;
+use crate::quality::tests::{run_tests, TestCounts};
+use crate::util::{now_rfc3339, read_to_string, write_string, write_string_atomic};
+
+const METRICS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsFile {
+    pub schema_version: u32,
+    pub updated_at: String,
+    pub variants: Vec<VariantMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantMetrics {
+    pub agent: String,
+    #[serde(default)]
+    pub measured_at: String,
+    #[serde(default = "default_true")]
+    pub build_passed: bool,
+    pub tests_passed: bool,
+    #[serde(default)]
+    pub test_counts: Option<TestCounts>,
+    pub coverage_percent: Option<f64>,
+    #[serde(default)]
+    pub changed_line_coverage_percent: Option<f64>,
+    pub coverage_tool: String,
+    pub test_output: String,
+    pub coverage_output: Option<String>,
+    #[serde(default)]
+    pub audit_tool: Option<String>,
+    #[serde(default)]
+    pub new_advisories: Vec<String>,
+    #[serde(default)]
+    pub bench: Option<BenchComparison>,
+    #[serde(default)]
+    pub release: Option<ReleaseMetrics>,
+    #[serde(default)]
+    pub mutants: Option<MutantsSummary>,
+    #[serde(default)]
+    pub stage_secs: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub environment: Option<EnvFingerprint>,
+}
+
+#[derive(Default)]
+struct CoverageSummary {
+    percent: Option<f64>,
+    changed_percent: Option<f64>,
+}
+
+pub struct MeasureOptions {
+    pub coverage: String,
+    pub audit: String,
+    pub bench: bool,
+    pub build_metrics: bool,
+    pub mutants: bool,
+}
+
+pub struct MeasureContext {
+    runs_dir: PathBuf,
+    options: MeasureOptions,
+    bench_command: String,
+    bench_threshold_percent: f64,
+    base_commit: Option<String>,
+    base_advisories: Vec<String>,
+    base_bench: Option<BTreeMap<String, f64>>,
+    base_release: Option<ReleaseBuildResult>,
+    codex_version: Option<String>,
+    exec_env: ExecConfig,
+    change_id: String,
+    pre_test: Vec<String>,
+    post_test: Vec<String>,
+}
+
+impl MeasureContext {
+    pub fn prepare(
+        paths: &RepoPaths,
+        state: &State,
+        change_id: &str,
+        config: &Config,
+        options: MeasureOptions,
+    ) -> Result<Self> {
+        let runs_dir = paths.runs_dir.join(change_id);
+
+        let base_advisories = if options.audit == "none" {
+            Vec::new()
+        } else {
+            let base = run_audit(&paths.repo_root, &options.audit)?;
+            write_string(&runs_dir.join("audit_base.txt"), &base.stdout)?;
+            base.advisories
+        };
+
+        let base_path = if options.bench || options.build_metrics {
+            Some(ensure_base_worktree(paths, state, config, change_id)?)
+        } else {
+            None
+        };
+        let base_bench = match &base_path {
+            Some(base_path) if options.bench => {
+                let base = run_bench(base_path, &config.quality.bench_command, &config.exec)?;
+                write_string(&runs_dir.join("bench_base.txt"), &base.stdout)?;
+                Some(base.timings)
+            }
+            _ => None,
+        };
+        let base_release = match &base_path {
+            Some(base_path) if options.build_metrics => {
+                let base = run_release_build(base_path, &config.exec)?;
+                write_string(&runs_dir.join("release_build_base.txt"), &base.stdout)?;
+                base.success.then_some(base)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            runs_dir,
+            options,
+            bench_command: config.quality.bench_command.clone(),
+            bench_threshold_percent: config.quality.bench_threshold_percent,
+            base_commit: state
+                .change_state(change_id)
+                .and_then(|c| c.base_commit.clone()),
+            base_advisories,
+            base_bench,
+            base_release,
+            codex_version: codex_version(),
+            exec_env: config.exec.clone(),
+            change_id: change_id.to_string(),
+            pre_test: config.quality.pre_test.clone(),
+            post_test: config.quality.post_test.clone(),
+        })
+    }
+
+    pub fn measure(&self, agent: &str, worktree_path: &Path) -> Result<VariantMetrics> {
+        let worktree = worktree_path.to_string_lossy();
+        let vars = [
+            ("CODEX_SDD_CHANGE", self.change_id.as_str()),
+            ("CODEX_SDD_AGENT", agent),
+            ("CODEX_SDD_WORKTREE", worktree.as_ref()),
+        ];
+        let mut stage_secs = BTreeMap::new();
+        let setup_ok = if self.pre_test.is_empty() {
+            true
+        } else {
+            let setup = timed(&mut stage_secs, "pre_test", || {
+                info_span!("pre_test")
+                    .in_scope(|| run_hooks(worktree_path, &self.pre_test, &self.exec_env, &vars))
+            })?;
+            write_string(
+                &self.runs_dir.join(format!("pre_test_{agent}.txt")),
+                &setup.stdout,
+            )?;
+            setup.success
+        };
+        let metrics = self.measure_variant(agent, worktree_path, setup_ok, stage_secs);
+        if self.post_test.is_empty() {
+            return metrics;
+        }
+        let mut post_secs = BTreeMap::new();
+        let cleanup = timed(&mut post_secs, "post_test", || {
+            info_span!("post_test")
+                .in_scope(|| run_hooks(worktree_path, &self.post_test, &self.exec_env, &vars))
+        })?;
+        write_string(
+            &self.runs_dir.join(format!("post_test_{agent}.txt")),
+            &cleanup.stdout,
+        )?;
+        if !cleanup.success {
+            warn!("post_test hook failed for {agent}");
+        }
+        let mut metrics = metrics?;
+        metrics.stage_secs.extend(post_secs);
+        Ok(metrics)
+    }
+
+    fn measure_variant(
+        &self,
+        agent: &str,
+        worktree_path: &Path,
+        setup_ok: bool,
+        mut stage_secs: BTreeMap<String, f64>,
+    ) -> Result<VariantMetrics> {
+        let environment = EnvFingerprint::capture(worktree_path, self.codex_version.clone());
+        let build = timed(&mut stage_secs, "build", || {
+            info_span!("build").in_scope(|| run_build_check(worktree_path, &self.exec_env))
+        })?;
+        let build_output_path = self.runs_dir.join(format!("build_{agent}.txt"));
+        write_string(&build_output_path, &build.stdout)?;
+
+        let (tests_passed, test_counts, test_output_path, coverage, coverage_output, coverage_tool) =
+            if build.success && setup_ok {
+                let test_result = timed(&mut stage_secs, "tests", || {
+                    info_span!("tests").in_scope(|| run_tests(worktree_path, &self.exec_env))
+                })?;
+                let test_output_path = self.runs_dir.join(format!("test_results_{agent}.txt"));
+                write_string(&test_output_path, &test_result.stdout)?;
+
+                let (coverage, coverage_output, coverage_tool) =
+                    timed(&mut stage_secs, "coverage", || {
+                        info_span!("coverage").in_scope(|| self.run_coverage(agent, worktree_path))
+                    })?;
+                (
+                    test_result.success,
+                    test_result.counts,
+                    test_output_path,
+                    coverage,
+                    coverage_output,
+                    coverage_tool,
+                )
+            } else if !setup_ok {
+                warn!("pre_test hook failed for {agent}; skip tests and coverage");
+                let setup_output_path = self.runs_dir.join(format!("pre_test_{agent}.txt"));
+                (
+                    false,
+                    None,
+                    setup_output_path,
+                    CoverageSummary::default(),
+                    None,
+                    "none".to_string(),
+                )
+            } else {
+                warn!("build failed for {agent}; skip tests and coverage");
+                (
+                    false,
+                    None,
+                    build_output_path,
+                    CoverageSummary::default(),
+                    None,
+                    "none".to_string(),
+                )
+            };
+
+        let (audit_tool, variant_new_advisories) = if self.options.audit == "none" {
+            (None, Vec::new())
+        } else {
+            let audit = timed(&mut stage_secs, "audit", || {
+                info_span!("audit").in_scope(|| run_audit(worktree_path, &self.options.audit))
+            })?;
+            write_string(
+                &self.runs_dir.join(format!("audit_{agent}.txt")),
+                &audit.stdout,
+            )?;
+            (
+                Some(self.options.audit.clone()),
+                new_advisories(&audit.advisories, &self.base_advisories),
+            )
+        };
+
+        let bench = match &self.base_bench {
+            Some(base_timings) if build.success => {
+                let result = timed(&mut stage_secs, "bench", || {
+                    info_span!("bench")
+                        .in_scope(|| run_bench(worktree_path, &self.bench_command, &self.exec_env))
+                })?;
+                write_string(
+                    &self.runs_dir.join(format!("bench_{agent}.txt")),
+                    &result.stdout,
+                )?;
+                Some(compare_bench(
+                    base_timings,
+                    &result.timings,
+                    self.bench_threshold_percent,
+                ))
+            }
+            _ => None,
+        };
+
+        let release = if self.options.build_metrics && build.success {
+            let result = timed(&mut stage_secs, "release", || {
+                info_span!("release").in_scope(|| run_release_build(worktree_path, &self.exec_env))
+            })?;
+            write_string(
+                &self.runs_dir.join(format!("release_build_{agent}.txt")),
+                &result.stdout,
+            )?;
+            result
+                .success
+                .then(|| release_metrics(&result, self.base_release.as_ref()))
+        } else {
+            None
+        };
+
+        let mutants = match &self.base_commit {
+            Some(base_commit) if self.options.mutants && build.success => {
+                let changed: Vec<String> = git_diff_names(worktree_path, base_commit)?
+                    .into_iter()
+                    .filter(|p| p.ends_with(".rs"))
+                    .collect();
+                if changed.is_empty() {
+                    None
+                } else {
+                    let run = timed(&mut stage_secs, "mutants", || {
+                        info_span!("mutants")
+                            .in_scope(|| run_mutants(worktree_path, &changed, &self.exec_env))
+                    })?;
+                    write_string(
+                        &self.runs_dir.join(format!("mutants_{agent}.txt")),
+                        &run.stdout,
+                    )?;
+                    Some(run.summary)
+                }
+            }
+            _ => None,
+        };
+
+        Ok(VariantMetrics {
+            agent: agent.to_string(),
+            measured_at: now_rfc3339(),
+            build_passed: build.success,
+            tests_passed,
+            test_counts,
+            coverage_percent: coverage.percent,
+            changed_line_coverage_percent: coverage.changed_percent,
+            coverage_tool,
+            test_output: test_output_path.to_string_lossy().to_string(),
+            coverage_output,
+            audit_tool,
+            new_advisories: variant_new_advisories,
+            bench,
+            release,
+            mutants,
+            stage_secs,
+            environment: Some(environment),
+        })
+    }
+
+    fn run_coverage(
+        &self,
+        agent: &str,
+        worktree_path: &Path,
+    ) -> Result<(CoverageSummary, Option<String>, String)> {
+        let (cov, tool) = match self.options.coverage.as_str() {
+            "none" => return Ok((CoverageSummary::default(), None, "none".to_string())),
+            "tarpaulin" => (run_tarpaulin(worktree_path, &self.exec_env)?, "tarpaulin"),
+            _ => (run_llvm_cov(worktree_path, &self.exec_env)?, "llvm-cov"),
+        };
+        let out_path = self.runs_dir.join(format!("coverage_{agent}.txt"));
+        write_string(&out_path, &cov.stdout)?;
+        let changed = match (&cov.lcov, &self.base_commit) {
+            (Some(lcov), Some(base_commit)) => {
+                let root = worktree_path
+                    .canonicalize()
+                    .unwrap_or_else(|_| worktree_path.to_path_buf());
+                changed_line_coverage(lcov, &root, &git_changed_lines(worktree_path, base_commit)?)
+            }
+            _ => None,
+        };
+        Ok((
+            CoverageSummary {
+                percent: cov.percent,
+                changed_percent: changed,
+            },
+            Some(out_path.to_string_lossy().to_string()),
+            tool.to_string(),
+        ))
+    }
+}
+
+impl MetricsFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                schema_version: METRICS_SCHEMA_VERSION,
+                updated_at: now_rfc3339(),
+                variants: Vec::new(),
+            });
+        }
+        let data = read_to_string(path)?;
+        let value: serde_json::Value =
+            serde_json::from_str(&data).with_context(|| format!("parse {}", path.display()))?;
+        if value.is_array() {
+            let variants: Vec<VariantMetrics> = serde_json::from_value(value)?;
+            return Ok(Self {
+                schema_version: METRICS_SCHEMA_VERSION,
+                updated_at: now_rfc3339(),
+                variants,
+            });
+        }
+        let file: MetricsFile = serde_json::from_value(value)?;
+        if file.schema_version != METRICS_SCHEMA_VERSION {
+            return Err(anyhow!(
+                "unsupported metrics schema version {}",
+                file.schema_version
+            ));
+        }
+        Ok(file)
+    }
+
+    pub fn get(&self, agent: &str) -> Option<&VariantMetrics> {
+        self.variants.iter().find(|m| m.agent == agent)
+    }
+
+    pub fn upsert(&mut self, metric: VariantMetrics) {
+        match self.variants.iter_mut().find(|m| m.agent == metric.agent) {
+            Some(entry) => *entry = metric,
+            None => self.variants.push(metric),
+        }
+        self.variants.sort_by(|a, b| a.agent.cmp(&b.agent));
+    }
+
+    pub fn retain_agents(&mut self, agents: &[String]) {
+        self.variants.retain(|m| agents.contains(&m.agent));
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        if path.exists() {
+            if let Some(parent) = path.parent() {
+                let snapshot = parent.join("metrics").join(format!(
+                    "{}.json",
+                    chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+                ));
+                write_string(&snapshot, &read_to_string(path)?)?;
+            }
+        }
+        self.schema_version = METRICS_SCHEMA_VERSION;
+        self.updated_at = now_rfc3339();
+        let data = serde_json::to_string_pretty(self).with_context(|| "serialize metrics")?;
+        write_string_atomic(path, &data)
+    }
+}
+
+fn timed<T>(
+    stage_secs: &mut BTreeMap<String, f64>,
+    stage: &str,
+    run: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = run();
+    stage_secs.insert(stage.to_string(), started.elapsed().as_secs_f64());
+    result
+}
+
+pub fn ensure_base_worktree(
+    paths: &RepoPaths,
+    state: &State,
+    config: &Config,
+    change_id: &str,
+) -> Result<PathBuf> {
+    let base_commit = match state
+        .change_state(change_id)
+        .and_then(|c| c.base_commit.clone())
+    {
+        Some(commit) => commit,
+        None => current_commit(&paths.repo_root)?,
+    };
+    let path = paths.worktrees_dir.join(format!("{change_id}.base"));
+    if !path.exists() {
+        create_detached_worktree(&paths.repo_root, &path, &base_commit)?;
+        bootstrap_worktree(
+            paths,
+            &config.worktrees,
+            &config.exec,
+            change_id,
+            "base",
+            &path,
+        )?;
+    }
+    Ok(path)
+}
+
+fn default_true() -> bool {
+    true
+}
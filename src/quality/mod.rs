@@ -1,2 +1,11 @@
+pub mod audit;
+pub mod bench;
+pub mod build;
 pub mod coverage;
+pub mod environment;
+pub mod metrics;
+pub mod mutants;
+pub mod risk;
+pub mod setup;
 pub mod tests;
+pub mod trend;
@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::config::ExecConfig;
+use crate::util::run_cmd_allow_fail;
+
+#[derive(Debug, Clone)]
+pub struct MutantsRun {
+    pub stdout: String,
+    pub summary: MutantsSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MutantsSummary {
+    pub caught: u64,
+    pub missed: u64,
+    pub timeout: u64,
+    pub unviable: u64,
+    pub score_percent: Option<f64>,
+}
+
+pub fn run_mutants(repo_root: &Path, files: &[String], env: &ExecConfig) -> Result<MutantsRun> {
+    let output_dir = repo_root.join("target");
+    let outcomes_path = output_dir.join("mutants.out").join("outcomes.json");
+    if outcomes_path.exists() {
+        fs::remove_file(&outcomes_path)
+            .with_context(|| format!("remove {}", outcomes_path.display()))?;
+    }
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(repo_root)
+        .args(["mutants", "--no-shuffle", "--output"])
+        .arg(&output_dir);
+    for file in files {
+        cmd.arg("--file").arg(file);
+    }
+    env.apply(&mut cmd);
+    let output = run_cmd_allow_fail(cmd)?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    stdout.push_str(&String::from_utf8_lossy(&output.stderr));
+    let outcomes = fs::read_to_string(&outcomes_path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<Outcomes>(&data).ok());
+    let Some(outcomes) = outcomes else {
+        let tail: Vec<&str> = stdout.lines().rev().take(20).collect();
+        let tail: Vec<&str> = tail.into_iter().rev().collect();
+        return Err(anyhow!(
+            "cargo mutants failed ({}) without {}:\n{}",
+            output.status,
+            outcomes_path.display(),
+            tail.join("\n")
+        ));
+    };
+    Ok(MutantsRun {
+        stdout,
+        summary: outcomes.summary(),
+    })
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct Outcomes {
+    caught: u64,
+    missed: u64,
+    timeout: u64,
+    unviable: u64,
+}
+
+impl Outcomes {
+    fn summary(&self) -> MutantsSummary {
+        let scored = self.caught + self.missed;
+        MutantsSummary {
+            caught: self.caught,
+            missed: self.missed,
+            timeout: self.timeout,
+            unviable: self.unviable,
+            score_percent: (scored > 0).then(|| self.caught as f64 / scored as f64 * 100.0),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::config::RiskConfig;
+use crate::docs::findings::Finding;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskScore {
+    pub score: f64,
+    pub findings: f64,
+    pub diff: f64,
+    pub churn: f64,
+    pub coverage: f64,
+    pub open_findings: usize,
+    pub lines_changed: u64,
+    pub churn_commits: u64,
+    pub changed_line_coverage_percent: Option<f64>,
+}
+
+pub struct RiskInputs<'a> {
+    pub findings: &'a [&'a Finding],
+    pub lines_changed: u64,
+    pub churn_commits: u64,
+    pub changed_line_coverage_percent: Option<f64>,
+}
+
+pub fn score(config: &RiskConfig, inputs: &RiskInputs) -> RiskScore {
+    let findings: f64 = inputs.findings.iter().fold(0.0, |total, finding| {
+        total + severity_weight(config, &finding.severity)
+    });
+    let diff = per_point(inputs.lines_changed as f64, config.lines_per_point);
+    let churn = per_point(inputs.churn_commits as f64, config.churn_per_point);
+    let coverage = inputs.changed_line_coverage_percent.map_or(0.0, |percent| {
+        config.uncovered_weight * (100.0 - percent.clamp(0.0, 100.0)) / 100.0
+    });
+    RiskScore {
+        score: round(findings + diff + churn + coverage),
+        findings: round(findings),
+        diff: round(diff),
+        churn: round(churn),
+        coverage: round(coverage),
+        open_findings: inputs.findings.len(),
+        lines_changed: inputs.lines_changed,
+        churn_commits: inputs.churn_commits,
+        changed_line_coverage_percent: inputs.changed_line_coverage_percent,
+    }
+}
+
+impl RiskScore {
+    pub fn exceeds(&self, config: &RiskConfig) -> Option<f64> {
+        config.max_score.filter(|max| self.score > *max)
+    }
+
+    pub fn summary(&self) -> String {
+        let coverage = self
+            .changed_line_coverage_percent
+            .map_or("n/a".to_string(), |percent| format!("{percent:.1}%"));
+        format!(
+            "{:.1} (findings {:.1} from {} open, diff {:.1} from {} lines, churn {:.1} from {} commits, coverage {:.1} from {coverage} of changed lines)",
+            self.score,
+            self.findings,
+            self.open_findings,
+            self.diff,
+            self.lines_changed,
+            self.churn,
+            self.churn_commits,
+            self.coverage
+        )
+    }
+}
+
+fn severity_weight(config: &RiskConfig, severity: &str) -> f64 {
+    config
+        .severity_weights
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(severity.trim()))
+        .map_or(0.0, |(_, weight)| *weight)
+}
+
+fn per_point(value: f64, per_point: f64) -> f64 {
+    if per_point > 0.0 {
+        value / per_point
+    } else {
+        0.0
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
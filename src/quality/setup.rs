@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::core::config::ExecConfig;
+use crate::util::{run_cmd_allow_fail, shell_command};
+
+#[derive(Debug, Clone)]
+pub struct HookRun {
+    pub success: bool,
+    pub stdout: String,
+}
+
+pub fn run_hooks(
+    worktree_path: &Path,
+    commands: &[String],
+    env: &ExecConfig,
+    vars: &[(&str, &str)],
+) -> Result<HookRun> {
+    let mut stdout = String::new();
+    for command in commands {
+        info!("run hook: {command}");
+        let mut cmd = shell_command(command);
+        cmd.current_dir(worktree_path);
+        env.apply(&mut cmd);
+        cmd.envs(vars.iter().copied());
+        let output = run_cmd_allow_fail(cmd)?;
+        stdout.push_str(&format!("$ {command}\n"));
+        stdout.push_str(&String::from_utf8_lossy(&output.stdout));
+        stdout.push_str(&String::from_utf8_lossy(&output.stderr));
+        let code = output
+            .status
+            .code()
+            .map_or("signal".to_string(), |code| code.to_string());
+        stdout.push_str(&format!("[exit {code}]\n\n"));
+        if !output.status.success() {
+            warn!("hook failed with exit {code}: {command}");
+            return Ok(HookRun {
+                success: false,
+                stdout,
+            });
+        }
+    }
+    Ok(HookRun {
+        success: true,
+        stdout,
+    })
+}
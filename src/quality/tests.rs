@@ -2,22 +2,60 @@ use std::path::Path;
 use std::process::Command;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
+use crate::core::config::ExecConfig;
 use crate::util::run_cmd_allow_fail;
 
 #[derive(Debug, Clone)]
 pub struct TestResult {
     pub success: bool,
     pub stdout: String,
+    pub counts: Option<TestCounts>,
 }
 
-pub fn run_tests(repo_root: &Path) -> Result<TestResult> {
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TestCounts {
+    pub passed: u64,
+    pub failed: u64,
+    pub ignored: u64,
+}
+
+pub fn run_tests(repo_root: &Path, env: &ExecConfig) -> Result<TestResult> {
     let mut cmd = Command::new("cargo");
     cmd.current_dir(repo_root).arg("test");
+    env.apply(&mut cmd);
     let output = run_cmd_allow_fail(cmd)?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     Ok(TestResult {
         success: output.status.success(),
+        counts: parse_counts(&stdout),
         stdout,
     })
 }
+
+fn parse_counts(stdout: &str) -> Option<TestCounts> {
+    let mut counts = None;
+    for line in stdout.lines() {
+        let Some(summary) = line.trim().strip_prefix("test result: ") else {
+            continue;
+        };
+        let total: &mut TestCounts = counts.get_or_insert_with(TestCounts::default);
+        for part in summary.split(['.', ';']) {
+            let mut words = part.split_whitespace();
+            let (Some(number), Some(label)) = (words.next(), words.next()) else {
+                continue;
+            };
+            let Ok(number) = number.parse::<u64>() else {
+                continue;
+            };
+            match label {
+                "passed" => total.passed += number,
+                "failed" => total.failed += number,
+                "ignored" => total.ignored += number,
+                _ => {}
+            }
+        }
+    }
+    counts
+}
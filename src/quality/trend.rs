@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::quality::metrics::VariantMetrics;
+use crate::quality::tests::TestCounts;
+use crate::util::{read_to_string, write_string_atomic};
+
+const TREND_ROWS: usize = 8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrendFile {
+    pub agents: BTreeMap<String, Vec<TrendPoint>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub measured_at: String,
+    pub commit: Option<String>,
+    pub build_passed: bool,
+    pub tests_passed: bool,
+    pub test_counts: Option<TestCounts>,
+    pub coverage_percent: Option<f64>,
+}
+
+impl TrendFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&read_to_string(path)?)
+            .with_context(|| format!("parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self).with_context(|| "serialize trend")?;
+        write_string_atomic(path, &data)
+    }
+
+    pub fn record(&mut self, metric: &VariantMetrics, commit: Option<String>) {
+        self.agents
+            .entry(metric.agent.clone())
+            .or_default()
+            .push(TrendPoint {
+                measured_at: metric.measured_at.clone(),
+                commit,
+                build_passed: metric.build_passed,
+                tests_passed: metric.tests_passed,
+                test_counts: metric.test_counts,
+                coverage_percent: metric.coverage_percent,
+            });
+    }
+
+    pub fn render(&self, agents: &[String]) -> String {
+        let mut out = String::from("## Trend\n");
+        for agent in agents {
+            let Some(points) = self.agents.get(agent) else {
+                continue;
+            };
+            out.push_str(&format!(
+                "\n### {agent}\n\n| run | measured_at | commit | tests | coverage |\n| --- | --- | --- | --- | --- |\n"
+            ));
+            let skip = points.len().saturating_sub(TREND_ROWS);
+            let mut previous: Option<&TrendPoint> = skip.checked_sub(1).map(|idx| &points[idx]);
+            for (idx, point) in points.iter().enumerate().skip(skip) {
+                let commit = point.commit.as_deref().map_or("-".to_string(), |commit| {
+                    format!("`{}`", &commit[..commit.len().min(8)])
+                });
+                out.push_str(&format!(
+                    "| {} | {} | {commit} | {} | {} |\n",
+                    idx + 1,
+                    point.measured_at,
+                    tests_cell(point, previous),
+                    coverage_cell(point, previous)
+                ));
+                previous = Some(point);
+            }
+        }
+        out
+    }
+}
+
+fn tests_cell(point: &TrendPoint, previous: Option<&TrendPoint>) -> String {
+    if !point.build_passed {
+        return "build failed".to_string();
+    }
+    let Some(counts) = point.test_counts else {
+        return if point.tests_passed { "pass" } else { "fail" }.to_string();
+    };
+    let mut cell = format!("{} passed / {} failed", counts.passed, counts.failed);
+    if let Some(before) = previous.and_then(|p| p.test_counts) {
+        let delta = counts.passed as i64 - before.passed as i64;
+        if delta != 0 {
+            cell.push_str(&format!(" ({delta:+})"));
+        }
+    }
+    cell
+}
+
+fn coverage_cell(point: &TrendPoint, previous: Option<&TrendPoint>) -> String {
+    let Some(percent) = point.coverage_percent else {
+        return "-".to_string();
+    };
+    match previous.and_then(|p| p.coverage_percent) {
+        Some(before) if (percent - before).abs() >= 0.01 => {
+            format!("{percent:.2}% ({:+.2})", percent - before)
+        }
+        _ => format!("{percent:.2}%"),
+    }
+}
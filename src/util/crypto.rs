@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use tracing::debug;
+
+use crate::core::config::EncryptionConfig;
+use crate::tr;
+use crate::util::{ensure_dir, write_bytes_atomic};
+
+const MAGIC: &[u8] = b"codex-sdd:enc:v1\n";
+const NONCE_LEN: usize = 12;
+const KEY_CONTEXT: &str = "codex-sdd 2026-10-16 run artifact and state encryption";
+
+struct Sealer {
+    cipher: Option<Aes256Gcm>,
+    key_error: Option<String>,
+    roots: Vec<PathBuf>,
+    key_env: String,
+}
+
+static SEALER: OnceLock<Sealer> = OnceLock::new();
+
+pub fn init(config: &EncryptionConfig, roots: Vec<PathBuf>) {
+    let (cipher, key_error) = match key_secret(config) {
+        Ok(Some(secret)) => {
+            let key = blake3::derive_key(KEY_CONTEXT, secret.as_bytes());
+            (Some(Aes256Gcm::new(&key.into())), None)
+        }
+        Ok(None) if !config.enabled => (None, None),
+        Ok(None) => (
+            None,
+            Some(tr!(
+                "encryption が有効ですが鍵がありません。{} か OS のキーチェーン (keychain = true) で指定してください",
+                "encryption is enabled but no key was found; set {} or store it in the OS keychain (keychain = true)",
+                config.key_env
+            )),
+        ),
+        Err(err) => (None, Some(format!("{err:#}"))),
+    };
+    let _ = SEALER.set(Sealer {
+        cipher,
+        key_error,
+        roots: if config.enabled { roots } else { Vec::new() },
+        key_env: config.key_env.clone(),
+    });
+}
+
+pub fn enabled() -> bool {
+    SEALER.get().is_some_and(|sealer| !sealer.roots.is_empty())
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn seal<'a>(path: &Path, contents: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    let Some(cipher) = protecting(path)? else {
+        return Ok(Cow::Borrowed(contents));
+    };
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: contents,
+                aad: MAGIC,
+            },
+        )
+        .map_err(|_| anyhow!("encrypt {}", path.display()))?;
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&nonce);
+    out.extend(sealed);
+    Ok(Cow::Owned(out))
+}
+
+pub fn open(path: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(body) = data.strip_prefix(MAGIC) else {
+        return Ok(data);
+    };
+    let sealer = SEALER.get();
+    let Some(cipher) = sealer.and_then(|sealer| sealer.cipher.as_ref()) else {
+        if let Some(err) = sealer.and_then(|sealer| sealer.key_error.as_ref()) {
+            return Err(anyhow!("{}: {err}", path.display()));
+        }
+        return Err(anyhow!(tr!(
+            "{} は暗号化されています。{} か OS のキーチェーンで鍵を指定してください",
+            "{} is encrypted; set {} or store the key in the OS keychain",
+            path.display(),
+            sealer.map_or("CODEX_SDD_KEY", |sealer| sealer.key_env.as_str())
+        )));
+    };
+    if body.len() < NONCE_LEN {
+        return Err(anyhow!("{} is truncated", path.display()));
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: MAGIC,
+            },
+        )
+        .map_err(|_| {
+            anyhow!(tr!(
+                "{} を復号できません。鍵が違うか、ファイルが壊れています",
+                "cannot decrypt {}: wrong key or corrupted file",
+                path.display()
+            ))
+        })
+}
+
+pub fn seal_file(path: &Path) -> Result<bool> {
+    if protecting(path)?.is_none() || !path.is_file() {
+        return Ok(false);
+    }
+    let data = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    if is_sealed(&data) {
+        return Ok(false);
+    }
+    write_bytes_atomic(path, &data)?;
+    debug!("sealed {}", path.display());
+    Ok(true)
+}
+
+pub struct PlainCopies {
+    dir: Option<PathBuf>,
+    pub paths: Vec<PathBuf>,
+}
+
+pub fn plain_copies(paths: Vec<PathBuf>) -> Result<PlainCopies> {
+    if !enabled() || paths.is_empty() {
+        return Ok(PlainCopies { dir: None, paths });
+    }
+    let dir = env::temp_dir().join(format!(
+        "codex-sdd-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    ensure_dir(&dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("chmod {}", dir.display()))?;
+    }
+    let mut copies = PlainCopies {
+        dir: Some(dir.clone()),
+        paths: Vec::new(),
+    };
+    for (idx, path) in paths.iter().enumerate() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let copy = dir.join(format!("{idx}_{name}"));
+        let data = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        fs::write(&copy, open(path, data)?).with_context(|| format!("write {}", copy.display()))?;
+        copies.paths.push(copy);
+    }
+    Ok(copies)
+}
+
+impl Drop for PlainCopies {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn protecting(path: &Path) -> Result<Option<&'static Aes256Gcm>> {
+    let Some(sealer) = SEALER.get() else {
+        return Ok(None);
+    };
+    if !sealer.roots.iter().any(|root| path.starts_with(root)) {
+        return Ok(None);
+    }
+    match (&sealer.cipher, &sealer.key_error) {
+        (Some(cipher), _) => Ok(Some(cipher)),
+        (None, Some(err)) => Err(anyhow!("{err}")),
+        (None, None) => Ok(None),
+    }
+}
+
+fn key_secret(config: &EncryptionConfig) -> Result<Option<String>> {
+    if let Ok(secret) = env::var(&config.key_env) {
+        if !secret.trim().is_empty() {
+            return Ok(Some(secret.trim().to_string()));
+        }
+    }
+    if !config.keychain {
+        return Ok(None);
+    }
+    keychain_secret(config)
+}
+
+#[cfg(feature = "keychain")]
+fn keychain_secret(config: &EncryptionConfig) -> Result<Option<String>> {
+    let entry = keyring::Entry::new(&config.keychain_service, &config.keychain_account)
+        .with_context(|| "open keychain entry")?;
+    match entry.get_password() {
+        Ok(secret) => Ok(Some(secret.trim().to_string())),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err).with_context(|| {
+            format!(
+                "read keychain entry {}/{}",
+                config.keychain_service, config.keychain_account
+            )
+        }),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+fn keychain_secret(_config: &EncryptionConfig) -> Result<Option<String>> {
+    Err(anyhow!(tr!(
+        "keychain = true には keychain feature 付きのビルドが必要です (cargo install codex-sdd --features keychain)",
+        "keychain = true needs a build with the keychain feature (cargo install codex-sdd --features keychain)"
+    )))
+}
@@ -0,0 +1,39 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Ja,
+    En,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+impl Lang {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "ja" => Ok(Self::Ja),
+            "en" => Ok(Self::En),
+            other => Err(anyhow!("unsupported language: {other} (ja|en)")),
+        }
+    }
+}
+
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+pub fn lang() -> Lang {
+    LANG.get().copied().unwrap_or(Lang::Ja)
+}
+
+#[macro_export]
+macro_rules! tr {
+    ($ja:literal, $en:literal $(, $args:expr)* $(,)?) => {
+        match $crate::util::i18n::lang() {
+            $crate::util::i18n::Lang::Ja => format!($ja $(, $args)*),
+            $crate::util::i18n::Lang::En => format!($en $(, $args)*),
+        }
+    };
+}
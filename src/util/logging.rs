@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+
+pub fn init(level: LevelFilter, log_dir: Option<&Path>) -> Result<()> {
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .with_filter(level);
+
+    let file_layer = match log_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+            let path = dir.join(format!("{}.jsonl", chrono::Utc::now().format("%Y-%m-%d")));
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("open {}", path.display()))?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_target(false)
+                    .with_thread_ids(true)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_writer(Mutex::new(file))
+                    .with_filter(LevelFilter::DEBUG.max(level)),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .try_init()
+        .with_context(|| "init logging")
+}
+
+pub fn level_from_flags(log_level: Option<&str>, verbose: bool, quiet: bool) -> LevelFilter {
+    match log_level {
+        Some("trace") => LevelFilter::TRACE,
+        Some("debug") => LevelFilter::DEBUG,
+        Some("info") => LevelFilter::INFO,
+        Some("warn") => LevelFilter::WARN,
+        Some("error") => LevelFilter::ERROR,
+        _ if verbose => LevelFilter::DEBUG,
+        _ if quiet => LevelFilter::ERROR,
+        _ => LevelFilter::INFO,
+    }
+}
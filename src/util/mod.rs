@@ -1,32 +1,20 @@
+pub mod crypto;
+pub mod i18n;
+pub mod logging;
+pub mod notify;
+pub mod progress;
+
 use std::fs;
-use std::io::Write;
+use std::io::{BufRead, Read, Write};
 use std::path::Path;
 use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use chrono::Utc;
-use serde::Serialize;
 
-#[derive(Serialize)]
-struct LogEvent<'a> {
-    ts: &'a str,
-    level: &'a str,
-    message: &'a str,
-}
-
-pub fn log_event(level: &str, message: &str) {
-    let ts = Utc::now().to_rfc3339();
-    let event = LogEvent {
-        ts: &ts,
-        level,
-        message,
-    };
-    if let Ok(line) = serde_json::to_string(&event) {
-        eprintln!("{line}");
-    } else {
-        eprintln!("{{\"ts\":\"{ts}\",\"level\":\"{level}\",\"message\":\"{message}\"}}");
-    }
-}
+use crate::tr;
 
 pub fn now_rfc3339() -> String {
     Utc::now().to_rfc3339()
@@ -40,7 +28,8 @@ pub fn write_file(path: &Path, contents: &str) -> Result<()> {
     if let Some(parent) = path.parent() {
         ensure_dir(parent)?;
     }
-    fs::write(path, contents).with_context(|| format!("write {}", path.display()))
+    fs::write(path, crypto::seal(path, contents.as_bytes())?)
+        .with_context(|| format!("write {}", path.display()))
 }
 
 pub fn write_file_if_missing(path: &Path, contents: &str) -> Result<bool> {
@@ -57,16 +46,132 @@ pub fn run_cmd_allow_fail(mut cmd: Command) -> Result<Output> {
     Ok(output)
 }
 
+pub fn run_cmd_timeout(mut cmd: Command, timeout: Duration) -> Result<(Output, bool)> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| "run command")?;
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = drain(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let deadline = Instant::now() + timeout;
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().with_context(|| "wait for command")? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            timed_out = true;
+            let _ = child.kill();
+            break child.wait().with_context(|| "wait for command")?;
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    let grace = Instant::now() + Duration::from_secs(2);
+    let collect = |handle: thread::JoinHandle<Vec<u8>>| {
+        while timed_out && !handle.is_finished() && Instant::now() < grace {
+            thread::sleep(Duration::from_millis(50));
+        }
+        if handle.is_finished() || !timed_out {
+            handle.join().unwrap_or_default()
+        } else {
+            Vec::new()
+        }
+    };
+    let output = Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    };
+    Ok((output, timed_out))
+}
+
+pub fn shell_command(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd
+}
+
+pub fn curl_quote(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
 pub fn write_string(path: &Path, contents: &str) -> Result<()> {
     if let Some(parent) = path.parent() {
         ensure_dir(parent)?;
     }
     let mut file = fs::File::create(path).with_context(|| format!("create {}", path.display()))?;
-    file.write_all(contents.as_bytes())
+    file.write_all(&crypto::seal(path, contents.as_bytes())?)
         .with_context(|| format!("write {}", path.display()))?;
     Ok(())
 }
 
+pub fn write_string_atomic(path: &Path, contents: &str) -> Result<()> {
+    write_bytes_atomic(path, contents.as_bytes())
+}
+
+pub fn write_bytes_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow!("invalid path: {}", path.display()))?;
+    ensure_dir(parent)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid path: {}", path.display()))?
+        .to_string_lossy();
+    let tmp_path = parent.join(format!(".{file_name}.tmp-{}", std::process::id()));
+    let contents = crypto::seal(path, contents)?;
+    {
+        let mut file = fs::File::create(&tmp_path)
+            .with_context(|| format!("create {}", tmp_path.display()))?;
+        file.write_all(&contents)
+            .with_context(|| format!("write {}", tmp_path.display()))?;
+        file.sync_all()
+            .with_context(|| format!("sync {}", tmp_path.display()))?;
+    }
+    if let Err(err) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err).with_context(|| format!("rename {}", path.display()));
+    }
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
 pub fn slugify(name: &str) -> String {
     let mut out = String::new();
     let mut last_dash = false;
@@ -96,5 +201,30 @@ pub fn normalize_path(path: &Path) -> Result<String> {
 }
 
 pub fn read_to_string(path: &Path) -> Result<String> {
-    fs::read_to_string(path).with_context(|| format!("read {}", path.display()))
+    let data = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    String::from_utf8(crypto::open(path, data)?).with_context(|| format!("read {}", path.display()))
+}
+
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+pub fn prompt_line(question: &str) -> Result<String> {
+    print!("{question}");
+    std::io::stdout().flush().with_context(|| "flush stdout")?;
+    let mut line = String::new();
+    let read = std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .with_context(|| "read stdin")?;
+    if read == 0 {
+        println!();
+        return Err(anyhow!(tr!(
+            "標準入力が閉じられたため中断しました",
+            "aborted: stdin was closed"
+        )));
+    }
+    Ok(line.trim().to_string())
 }
@@ -0,0 +1,36 @@
+use std::io::Write;
+
+use tracing::warn;
+
+pub fn notify(method: &str, title: &str, body: &str) {
+    match method {
+        "bell" => bell(),
+        "desktop" => desktop(title, body),
+        _ => {}
+    }
+}
+
+fn bell() {
+    let mut stderr = std::io::stderr();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
+
+#[cfg(feature = "desktop-notify")]
+fn desktop(title: &str, body: &str) {
+    if let Err(err) = notify_rust::Notification::new()
+        .appname("codex-sdd")
+        .summary(title)
+        .body(body)
+        .show()
+    {
+        warn!("desktop notification failed: {err}");
+        bell();
+    }
+}
+
+#[cfg(not(feature = "desktop-notify"))]
+fn desktop(_title: &str, _body: &str) {
+    warn!("desktop notifications need a build with --features desktop-notify; using terminal bell");
+    bell();
+}
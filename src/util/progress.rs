@@ -0,0 +1,90 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tracing::info;
+
+pub struct ProgressGroup {
+    multi: Option<MultiProgress>,
+}
+
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    label: String,
+    len: u64,
+    pos: AtomicU64,
+}
+
+impl ProgressGroup {
+    pub fn new() -> Self {
+        Self {
+            multi: std::io::stderr().is_terminal().then(MultiProgress::new),
+        }
+    }
+
+    pub fn bar(&self, len: u64, label: &str) -> Progress {
+        let bar = self.multi.as_ref().map(|multi| {
+            let bar = multi.add(ProgressBar::new(len));
+            bar.set_style(
+                ProgressStyle::with_template("{prefix:>12} [{bar:30}] {pos}/{len} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar())
+                    .progress_chars("=> "),
+            );
+            bar.set_prefix(label.to_string());
+            bar
+        });
+        Progress {
+            bar,
+            label: label.to_string(),
+            len,
+            pos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn spinner(&self, label: &str) -> Progress {
+        let bar = self.multi.as_ref().map(|multi| {
+            let bar = multi.add(ProgressBar::new_spinner());
+            bar.set_style(
+                ProgressStyle::with_template("{prefix:>12} {spinner} {elapsed} {msg}")
+                    .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            );
+            bar.set_prefix(label.to_string());
+            bar.enable_steady_tick(std::time::Duration::from_millis(120));
+            bar
+        });
+        Progress {
+            bar,
+            label: label.to_string(),
+            len: 0,
+            pos: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Progress {
+    pub fn inc(&self) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+            return;
+        }
+        let pos = self.pos.fetch_add(1, Ordering::Relaxed) + 1;
+        let step = (self.len / 10).max(1);
+        if pos.is_multiple_of(step) || pos == self.len {
+            info!("{}: {pos}/{}", self.label, self.len);
+        }
+    }
+
+    pub fn set_message(&self, message: &str) {
+        match &self.bar {
+            Some(bar) => bar.set_message(message.to_string()),
+            None => info!("{}: {message}", self.label),
+        }
+    }
+
+    pub fn finish(&self, message: &str) {
+        match &self.bar {
+            Some(bar) => bar.finish_with_message(message.to_string()),
+            None => info!("{}: {message}", self.label),
+        }
+    }
+}